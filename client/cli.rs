@@ -5,6 +5,13 @@ use chrono::Duration;
 use chrono_english::*;
 use clap::Clap;
 
+use pueue_lib::io_priority::IoPriority;
+use pueue_lib::log_rotation::LogRotation;
+use pueue_lib::signal::Signal;
+use pueue_lib::size::parse_size;
+
+// The options of `add` make up most of the size, which doesn't matter for a single parse.
+#[allow(clippy::large_enum_variant)]
 #[derive(Clap, Debug)]
 pub enum SubCommand {
     /// Enqueue a task for execution.
@@ -39,20 +46,170 @@ pub enum SubCommand {
 
         /// Start the task once all specified tasks have successfully finished.
         /// As soon as one of the dependencies fails, this task will fail as well.
-        #[clap(name = "after", short, long)]
+        #[clap(name = "after", short, long, visible_alias = "after-success")]
         dependencies: Vec<usize>,
 
+        /// Start the task once all specified tasks have failed, e.g. for cleanup or alerting.
+        /// As soon as one of them succeeds, this task will fail.
+        #[clap(long)]
+        after_failure: Vec<usize>,
+
+        /// Start the task once all specified tasks have finished, regardless of their result.
+        #[clap(long)]
+        after_any: Vec<usize>,
+
         /// Add some information for yourself.
         /// This string will be shown in the "status" table.
         /// There's no additional logic connected to it.
         #[clap(short, long)]
         label: Option<String>,
 
+        /// Kill the task, if it's still running after the given duration.
+        /// Accepts seconds or durations such as "90s", "30m", "2h" or "1d 12h".
+        /// If not specified, the default timeout of the task's group is used.
+        #[clap(short, long, parse(try_from_str=parse_duration))]
+        timeout: Option<u64>,
+
+        /// Enqueue the task again up to this many times, if it fails or times out.
+        /// Dependants only see the result of the final attempt.
+        #[clap(short, long, default_value = "0")]
+        retries: usize,
+
+        /// Wait this long before retrying a failed task. The delay doubles with every attempt.
+        /// Accepts the same formats as "--timeout".
+        #[clap(long, requires = "retries", parse(try_from_str=parse_duration))]
+        retry_delay: Option<u64>,
+
+        /// Queued tasks with a higher priority are started first.
+        /// Negative values are allowed. Defaults to 0.
+        #[clap(short = 'o', long, default_value = "0", allow_hyphen_values = true)]
+        priority: i32,
+
+        /// Only start the task, once the named resource is available.
+        /// Resources are shared across all groups and can be used to prevent
+        /// tasks from running concurrently, e.g. "--resource db".
+        /// The amount of a resource can be specified as well, e.g. "--resource gpu-slot:2".
+        /// The capacity of each resource is set in the daemon's config and defaults to 1.
+        /// Each resource may only be given once.
+        #[clap(long = "resource", parse(try_from_str=parse_resource))]
+        resources: Vec<(String, usize)>,
+
+        /// The amount of its group's parallel slots, which the task occupies while running.
+        /// Useful for heavy tasks, e.g. a build that should count as four parallel tasks.
+        /// Tasks that need more slots than the group has will run on their own.
+        #[clap(long, default_value = "1", validator=min_one)]
+        slots: usize,
+
+        /// Limit the memory of the task in bytes, e.g. "4096", "512M" or "4G".
+        /// The task is killed by the OOM killer, if it exceeds this limit.
+        /// This needs a delegated cgroup v2, which has to be configured in the daemon's config.
+        #[clap(long, parse(try_from_str=parse_size))]
+        memory: Option<u64>,
+
+        /// Limit the CPU time of the task to this many CPUs, e.g. "2" or "0.5".
+        /// This needs a delegated cgroup v2, which has to be configured in the daemon's config.
+        #[clap(long, parse(try_from_str=parse_cpus))]
+        cpus: Option<f64>,
+
+        /// Run the task with this niceness, from -20 (highest priority) to 19 (lowest priority).
+        /// Negative values need root privileges.
+        #[clap(long, allow_hyphen_values = true, parse(try_from_str=parse_nice))]
+        nice: Option<i32>,
+
+        /// Run the task with this I/O scheduling class, as known from `ionice`.
+        /// Either "idle", "best-effort" or "realtime", optionally followed by a level
+        /// from 0 (highest) to 7 (lowest), e.g. "best-effort:7". This is only supported on Linux.
+        #[clap(long)]
+        ionice: Option<IoPriority>,
+
+        /// The maximum amount of files, which the task's process may open at once.
+        #[clap(long, validator=min_one)]
+        max_open_files: Option<u64>,
+
+        /// The maximum CPU time, which the task's process may use.
+        /// The process receives SIGXCPU, once it exceeds this limit.
+        /// Accepts the same formats as "--timeout".
+        #[clap(long, parse(try_from_str=parse_duration))]
+        max_cpu_time: Option<u64>,
+
+        /// The shell that executes the command, e.g. "bash" or "zsh -o pipefail -c".
+        /// If only a program is given, "-c" is appended.
+        /// Defaults to the shell of the task's group or the daemon, which is "sh -c" by default.
+        #[clap(name = "shell", long, conflicts_with = "no-shell")]
+        shell: Option<String>,
+
+        /// Execute the command directly without a shell, so the task's process is the command itself.
+        /// A single argument is split like a shell would do it, e.g. 'rsync -a "my dir" backup'.
+        /// Multiple arguments are passed as they are.
+        #[clap(name = "no-shell", long = "no-shell", conflicts_with = "shell")]
+        no_shell: bool,
+
+        /// Run the task on a pseudo terminal, for programs that behave differently without one,
+        /// e.g. to keep colors and progress bars. Input from `send` is typed into the terminal.
+        /// Stdout and stderr of the task are both written to its stdout log.
+        /// This isn't supported on Windows.
+        #[clap(long)]
+        pty: bool,
+
+        /// The maximum size of each log of the task in bytes, e.g. "4096", "512K" or "100M".
+        /// Defaults to the maximum log size of the task's group.
+        #[clap(long, parse(try_from_str=parse_size))]
+        max_log_size: Option<u64>,
+
+        /// What happens, once a log exceeds its maximum size.
+        /// "truncate" only keeps the latest output, which is the default.
+        /// "rotate" moves the log to a numbered file and starts a new one, "compress" compresses
+        /// these files as well. Both keep 3 files, unless another amount is given, e.g. "rotate:5".
+        #[clap(long)]
+        log_rotation: Option<LogRotation>,
+
+        /// Add a task array with a task for each input.
+        /// Inputs are either the lines of a file, the paths matching a glob such as "images/*.jpg"
+        /// or a numeric range such as "1..100" (inclusive).
+        /// The placeholders "{}", "{name}", "{stem}" and "{index}" in the command are replaced by
+        /// the input, its file name, its file name without extension and its position.
+        #[clap(long, verbatim_doc_comment)]
+        each: Option<String>,
+
         /// Only return the task id instead of a text.
         /// This is useful when scripting and working with dependencies.
+        /// Task arrays return the ids of all their tasks.
         #[clap(short, long)]
         print_task_id: bool,
     },
+    /// Add all steps of a workflow file as tasks.
+    /// Steps reference each other by name, which allows to describe whole pipelines.
+    #[clap(after_help = "WORKFLOW FORMAT:
+
+    Workflows can be written in YAML or TOML (if the file ends with \".toml\").
+    Each step needs a unique name and a command. All other fields are optional.
+    Relative paths are relative to the current working directory.
+
+    steps:
+      - name: fetch
+        command: git pull
+      - name: build
+        command: cargo build --release
+        group: builds
+        after: [fetch]
+      - name: alert
+        command: notify-send 'Build failed'
+        after_failure: [build]
+      - name: cleanup
+        command: rm -rf target/tmp
+        label: cleanup
+        path: ./project
+        after_any: [build]
+")]
+    Submit {
+        /// The path to the workflow file.
+        file: PathBuf,
+
+        /// Print the ids of the added tasks as json.
+        #[clap(short, long)]
+        json: bool,
+    },
+
     /// Remove tasks from the list.
     /// Running or paused tasks need to be killed first.
     Remove {
@@ -126,8 +283,8 @@ pub enum SubCommand {
         #[clap(short, long)]
         all: bool,
 
-        /// Also resume direct child processes of your paused tasks.
-        /// By default only the main process will get a SIGSTART.
+        /// Deprecated: This has no effect, since tasks are always signaled together with their
+        /// whole process group, which includes their children.
         #[clap(short, long)]
         children: bool,
     },
@@ -157,6 +314,11 @@ pub enum SubCommand {
         #[clap(short, long)]
         all_failed: bool,
 
+        /// Restart all tasks of a task array.
+        /// In combination with "--all-failed", only its failed tasks are restarted.
+        #[clap(long)]
+        array: Option<usize>,
+
         /// Edit the tasks' command before restarting.
         #[clap(short, long)]
         edit: bool,
@@ -187,10 +349,8 @@ pub enum SubCommand {
         #[clap(short, long)]
         wait: bool,
 
-        /// Also pause direct child processes of a task's main process.
-        /// By default only the main process will get a SIGSTOP.
-        /// This is useful when calling bash scripts, which start other processes themselves.
-        /// This operation is not recursive!
+        /// Deprecated: This has no effect, since tasks are always signaled together with their
+        /// whole process group, which includes their children.
         #[clap(short, long)]
         children: bool,
     },
@@ -209,8 +369,25 @@ pub enum SubCommand {
         #[clap(short, long)]
         all: bool,
 
-        /// Send the SIGTERM signal to all children as well.
-        /// Useful when working with shell scripts.
+        /// Kill all tasks of a task array.
+        /// Its queued and stashed tasks are marked as killed and won't be started anymore.
+        #[clap(long, conflicts_with_all = &["group", "all"])]
+        array: Option<usize>,
+
+        /// Send this signal instead of SIGKILL, e.g. "TERM", so tasks get a chance to clean up.
+        /// Tasks that are still running after the grace period are killed with SIGKILL.
+        /// If not specified, the default kill signal of the task's group is used.
+        #[clap(short, long)]
+        signal: Option<Signal>,
+
+        /// How long tasks get to exit after receiving the signal.
+        /// If not specified, the default grace period of the task's group or 10 seconds are used.
+        /// Accepts the same formats as "add --timeout".
+        #[clap(long, parse(try_from_str=parse_duration))]
+        grace: Option<u64>,
+
+        /// Deprecated: This has no effect, since tasks are always signaled together with their
+        /// whole process group, which includes their children.
         #[clap(short, long)]
         children: bool,
     },
@@ -224,7 +401,23 @@ pub enum SubCommand {
         input: String,
     },
 
-    /// Edit the command or path of a stashed or queued task.
+    /// Send a signal to a running or paused task, e.g. "HUP" to make a service reload its config.
+    /// Signals are specified by their name with or without the "SIG" prefix.
+    /// Use `pause` and `start` instead of "STOP", "TSTP" and "CONT".
+    Signal {
+        /// The id of the task.
+        task_id: usize,
+
+        /// The signal that should be sent to the process, e.g. "USR1".
+        signal: Signal,
+
+        /// Deprecated: This has no effect, since tasks are always signaled together with their
+        /// whole process group, which includes their children.
+        #[clap(short, long)]
+        children: bool,
+    },
+
+    /// Edit the command, path, timeout or priority of a stashed or queued task.
     /// The command is edited by default.
     #[clap(verbatim_doc_comment)]
     Edit {
@@ -232,8 +425,17 @@ pub enum SubCommand {
         task_id: usize,
 
         /// Edit the path of the task.
-        #[clap(short, long)]
+        #[clap(short, long, conflicts_with_all = &["timeout", "priority"])]
         path: bool,
+
+        /// Edit the timeout of the task.
+        /// Remove the timeout by saving an empty line.
+        #[clap(short, long, conflicts_with = "priority")]
+        timeout: bool,
+
+        /// Edit the priority of the task.
+        #[clap(short = 'o', long)]
+        priority: bool,
     },
 
     /// Use this to add or remove groups.
@@ -249,6 +451,14 @@ pub enum SubCommand {
         remove: Option<String>,
     },
 
+    /// Manage recurring tasks, which are added to the queue every time their cron schedule fires.
+    /// By default, this will simply display all schedules.
+    #[clap(verbatim_doc_comment)]
+    Schedule {
+        #[clap(subcommand)]
+        cmd: Option<ScheduleCommand>,
+    },
+
     /// Display the current status of all tasks.
     Status {
         /// Print the current state as json to stdout.
@@ -260,6 +470,23 @@ pub enum SubCommand {
         #[clap(short, long)]
         /// Only show tasks of a specific group
         group: Option<String>,
+
+        /// Only show tasks of a specific task array.
+        #[clap(long)]
+        array: Option<usize>,
+    },
+
+    /// Display all running tasks sorted by their current CPU usage.
+    /// The usage includes all processes of a task and is sampled by the daemon every few seconds.
+    /// This is currently only supported on Linux.
+    Top {
+        /// Sort the tasks by their memory usage instead.
+        #[clap(short, long)]
+        memory: bool,
+
+        /// Only show tasks of a specific group.
+        #[clap(short, long)]
+        group: Option<String>,
     },
 
     /// Display the log output of finished tasks.
@@ -269,6 +496,11 @@ pub enum SubCommand {
     Log {
         /// View the task output of these specific tasks.
         task_ids: Vec<usize>,
+
+        /// View archived tasks instead. The ids are the ids shown by `pueue history`.
+        #[clap(short, long)]
+        archived: bool,
+
         /// Print the resulting tasks and output as json.
         /// Can be very large!
         #[clap(short, long)]
@@ -283,21 +515,58 @@ pub enum SubCommand {
         /// This is the default if only a single task is being looked at.
         #[clap(short, long)]
         full: bool,
+
+        /// Show the output of stdout and stderr in the order it has been written,
+        /// with the time at which each line has been written.
+        /// Only works for tasks that have been started while `combined_logs` was enabled.
+        #[clap(short, long)]
+        timestamps: bool,
+
+        /// Show the time of each line relative to the start of its task.
+        #[clap(long, requires = "timestamps")]
+        relative: bool,
+
+        /// Only show the output of stderr.
+        #[clap(long)]
+        stderr_only: bool,
+    },
+
+    /// Display the tasks in the archive, whose logs have been kept after they have been removed.
+    /// Only works while `archive_logs` is enabled in the daemon's config.
+    /// Use `pueue log --archived` to view their output.
+    History {
+        /// Print the archived tasks as json.
+        #[clap(short, long)]
+        json: bool,
     },
 
     /// Follow the output of a currently running task.
     /// This command works like tail -f.
     Follow {
-        /// The id of the task you want to watch.
+        /// The ids of the tasks you want to watch.
         /// If no or multiple tasks are running, you have to specify the id.
         /// If only a single task is running, you can omit the id.
-        task_id: Option<usize>,
+        /// The output of several tasks is shown line by line, prefixed with the id of its task.
+        task_ids: Vec<usize>,
+
+        /// Follow all tasks of a group, including tasks that are started later on.
+        /// This goes on, until all tasks of the group finished.
+        #[clap(short, long, conflicts_with = "task-ids")]
+        group: Option<String>,
 
         /// Show stderr instead of stdout.
         #[clap(short, long)]
         err: bool,
     },
 
+    /// Attach to a running task. Its output is shown live and everything you type is sent to it.
+    /// Tasks that have been started with "--pty" behave just like a terminal.
+    /// Press Ctrl-] to detach.
+    Attach {
+        /// The id of the task you want to attach to.
+        task_id: usize,
+    },
+
     /// Wait until tasks are finished. This can be quite useful for scripting.
     /// By default, this will wait for all tasks in the default group to finish.
     /// Note: This will also wait for all tasks that aren't somehow 'Done'.
@@ -314,6 +583,10 @@ pub enum SubCommand {
         #[clap(short, long)]
         all: bool,
 
+        /// Wait for all tasks of a task array.
+        #[clap(long, conflicts_with_all = &["group", "all"])]
+        array: Option<usize>,
+
         /// Don't show any log output while waiting
         #[clap(short, long)]
         quiet: bool,
@@ -328,8 +601,8 @@ pub enum SubCommand {
 
     /// Kill all tasks, clean up afterwards and reset EVERYTHING!
     Reset {
-        /// Send the SIGTERM signal to all children as well.
-        /// Useful when working with shell scripts.
+        /// Deprecated: This has no effect, since tasks are always signaled together with their
+        /// whole process group, which includes their children.
         #[clap(short, long)]
         children: bool,
 
@@ -342,6 +615,7 @@ pub enum SubCommand {
     Shutdown,
 
     /// Set the amount of allowed parallel tasks.
+    /// Tasks that have been added with `--slots` count as several tasks.
     /// By default, adjusts the amount of the default group.
     Parallel {
         /// The amount of allowed parallel tasks.
@@ -364,6 +638,96 @@ pub enum SubCommand {
     },
 }
 
+#[derive(Clap, Debug)]
+pub enum ScheduleCommand {
+    /// Add a recurring task.
+    /// Every time the schedule fires, a fresh copy of the task is added to the queue.
+    #[clap(after_help = "SCHEDULE FORMAT:
+
+    The schedule is a cron expression with the usual five fields. \
+    An optional sixth field at the front specifies the seconds.
+
+    ┌──────── minute (0-59)
+    │ ┌────── hour (0-23)
+    │ │ ┌──── day of month (1-31)
+    │ │ │ ┌── month (1-12 or JAN-DEC)
+    │ │ │ │ ┌ day of week (0-7 or SUN-SAT, 0 and 7 are Sunday)
+    0 3 * * *          // Every day at 03:00
+    */15 * * * 1-5     // Every 15 minutes from Monday to Friday
+    30 0 9 1 * *       // On the first day of every month at 09:00:30
+")]
+    Add {
+        /// The cron expression, which determines when the task is added.
+        /// It needs to be quoted, e.g. "0 3 * * *". See SCHEDULE FORMAT below.
+        expression: String,
+
+        /// The command to be added.
+        #[clap(required = true)]
+        command: Vec<String>,
+
+        /// Escape any special shell characters (" ", "&", "!", etc.).
+        /// Beware: This implicitly disables nearly all shell specific syntax ("&&", "&>").
+        #[clap(short, long)]
+        escape: bool,
+
+        /// Assign the spawned tasks to a group.
+        /// If no group is specified, the default group will be used.
+        #[clap(name = "group", short, long)]
+        group: Option<String>,
+
+        /// Start the spawned tasks once all specified tasks have successfully finished.
+        #[clap(name = "after", short, long)]
+        dependencies: Vec<usize>,
+
+        /// Add some information for yourself.
+        /// The label is copied to every spawned task.
+        #[clap(short, long)]
+        label: Option<String>,
+
+        /// Kill the spawned tasks, if they're still running after the given duration.
+        /// See "pueue add --help" for accepted formats.
+        #[clap(short, long, parse(try_from_str=parse_duration))]
+        timeout: Option<u64>,
+
+        /// Enqueue a spawned task again up to this many times, if it fails or times out.
+        #[clap(short, long, default_value = "0")]
+        retries: usize,
+
+        /// Wait this long before retrying a failed task. The delay doubles with every attempt.
+        #[clap(long, requires = "retries", parse(try_from_str=parse_duration))]
+        retry_delay: Option<u64>,
+
+        /// Queued tasks with a higher priority are started first.
+        #[clap(short = 'o', long, default_value = "0", allow_hyphen_values = true)]
+        priority: i32,
+    },
+
+    /// Display all schedules and the time they fire next.
+    List,
+
+    /// Remove schedules. Tasks that have already been added to the queue aren't touched.
+    Remove {
+        /// The schedule ids to be removed.
+        #[clap(required = true)]
+        schedule_ids: Vec<usize>,
+    },
+
+    /// Pause schedules. Paused schedules don't add any tasks.
+    Pause {
+        /// The schedule ids to be paused.
+        #[clap(required = true)]
+        schedule_ids: Vec<usize>,
+    },
+
+    /// Resume paused schedules.
+    /// Runs that have been missed while the schedule was paused are skipped.
+    Resume {
+        /// The schedule ids to be resumed.
+        #[clap(required = true)]
+        schedule_ids: Vec<usize>,
+    },
+}
+
 #[derive(Clap, Debug, PartialEq)]
 pub enum Shell {
     Bash,
@@ -409,6 +773,125 @@ fn parse_delay_until(src: &str) -> Result<DateTime<Local>, String> {
     ))
 }
 
+/// Parse a resource such as "db" or "gpu-slot:2" into its name and the needed amount.
+fn parse_resource(src: &str) -> Result<(String, usize), String> {
+    let (name, amount) = match src.rsplit_once(':') {
+        Some((name, amount)) => {
+            let amount = amount
+                .parse::<usize>()
+                .map_err(|_| format!("invalid amount '{}'", amount))?;
+            (name, amount)
+        }
+        None => (src, 1),
+    };
+
+    if name.is_empty() {
+        return Err(String::from("the resource name must not be empty"));
+    }
+    if amount == 0 {
+        return Err(String::from("the amount must be at least 1"));
+    }
+
+    Ok((name.to_string(), amount))
+}
+
+/// The longest duration, which is accepted for timeouts, delays and grace periods.
+/// Longer durations can't be represented as points in time.
+const MAX_DURATION: u64 = 100 * 365 * 24 * 60 * 60;
+
+/// Parse a duration such as "90", "30s", "15m", "2h" or "1d 12h" into seconds.
+/// Plain numbers are interpreted as seconds. Durations of zero seconds are rejected.
+pub fn parse_duration(src: &str) -> Result<u64, String> {
+    let seconds = match src.trim().parse::<u64>() {
+        Ok(seconds) => seconds,
+        Err(_) => parse_duration_units(src)?,
+    };
+
+    if seconds == 0 {
+        return Err(String::from(
+            "the duration must be longer than zero seconds",
+        ));
+    }
+    if seconds > MAX_DURATION {
+        return Err(String::from("the duration must be shorter than 100 years"));
+    }
+
+    Ok(seconds)
+}
+
+/// Sum up the parts of a duration such as "1d 12h".
+fn parse_duration_units(src: &str) -> Result<u64, String> {
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for character in src.chars() {
+        if character.is_ascii_digit() {
+            number.push(character);
+            continue;
+        } else if character.is_whitespace() {
+            continue;
+        }
+
+        let unit = match character {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 60 * 60 * 24,
+            _ => return Err(format!("unknown time unit '{}'", character)),
+        };
+        let value = number
+            .parse::<u64>()
+            .map_err(|_| format!("missing number before time unit '{}'", character))?;
+        seconds = value
+            .checked_mul(unit)
+            .and_then(|value| seconds.checked_add(value))
+            .ok_or_else(|| String::from("the duration must be shorter than 100 years"))?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return Err(format!("missing time unit after '{}'", number));
+    }
+
+    if src.trim().is_empty() {
+        return Err(String::from("could not parse as seconds or duration"));
+    }
+
+    Ok(seconds)
+}
+
+/// Parse an amount of CPUs, which has to be bigger than 0. Fractions are allowed.
+fn parse_cpus(src: &str) -> Result<f64, String> {
+    match src.parse::<f64>() {
+        Ok(cpus) if cpus.is_finite() && cpus > 0.0 => Ok(cpus),
+        Ok(_) => Err(String::from("the amount of CPUs must be bigger than 0")),
+        Err(_) => Err(format!("could not parse '{}' as amount of CPUs", src)),
+    }
+}
+
+/// Parse a niceness, which has to be between -20 and 19.
+fn parse_nice(src: &str) -> Result<i32, String> {
+    match src.parse::<i32>() {
+        Ok(nice) if (-20..=19).contains(&nice) => Ok(nice),
+        Ok(_) => Err(String::from("the niceness must be between -20 and 19")),
+        Err(_) => Err(format!("could not parse '{}' as niceness", src)),
+    }
+}
+
+/// Parse a shell into its program and arguments, e.g. "zsh -o pipefail -c".
+/// If the shell consists of a single program, "-c" is appended.
+pub fn parse_shell(src: &str) -> Result<Vec<String>, String> {
+    let mut shell = shell_words::split(src)
+        .map_err(|err| format!("could not parse '{}' as shell: {}", src, err))?;
+    match shell.len() {
+        0 => Err(String::from("the shell mustn't be empty")),
+        1 => {
+            shell.push("-c".to_string());
+            Ok(shell)
+        }
+        _ => Ok(shell),
+    }
+}
+
 /// Validator function. The input string has to be parsable as int and bigger than 0
 fn min_one(value: &str) -> Result<(), String> {
     match value.parse::<usize>() {
@@ -421,3 +904,44 @@ fn min_one(value: &str) -> Result<(), String> {
         Err(_) => Err("Failed to parse integer".into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shell() {
+        assert_eq!(parse_shell("bash").unwrap(), vec!["bash", "-c"]);
+        assert_eq!(
+            parse_shell("zsh -o pipefail -c").unwrap(),
+            vec!["zsh", "-o", "pipefail", "-c"]
+        );
+        assert_eq!(
+            parse_shell("'/opt/my shell/sh' -c").unwrap(),
+            vec!["/opt/my shell/sh", "-c"]
+        );
+        assert!(parse_shell("").is_err());
+        assert!(parse_shell("bash 'unclosed").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90").unwrap(), 90);
+        assert_eq!(parse_duration("30s").unwrap(), 30);
+        assert_eq!(parse_duration("15m").unwrap(), 15 * 60);
+        assert_eq!(parse_duration("2h").unwrap(), 2 * 60 * 60);
+        assert_eq!(parse_duration("1d 12h").unwrap(), 36 * 60 * 60);
+        assert_eq!(parse_duration("1h30m").unwrap(), 90 * 60);
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("0s 0m").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("1h 30").is_err());
+        assert!(parse_duration("36500d").is_ok());
+        assert!(parse_duration("36501d").is_err());
+        assert!(parse_duration("18446744073709551615").is_err());
+        assert!(parse_duration("18446744073709551615d").is_err());
+    }
+}
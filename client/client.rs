@@ -1,8 +1,11 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::env::{current_dir, vars};
+use std::fs::read_to_string;
 use std::io::{self, Write};
-use std::{borrow::Cow, collections::HashMap};
+use std::path::Path;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use colors::Colors;
 use log::error;
 
@@ -10,11 +13,12 @@ use pueue_lib::network::message::*;
 use pueue_lib::network::protocol::*;
 use pueue_lib::network::secret::read_shared_secret;
 use pueue_lib::settings::Settings;
+use pueue_lib::task::DependencyCondition;
 
-use crate::cli::{CliArguments, SubCommand};
+use crate::cli::{parse_shell, CliArguments, ScheduleCommand, SubCommand};
+use crate::commands::attach::attach;
 use crate::commands::edit::edit;
 use crate::commands::get_state;
-use crate::commands::local_follow::local_follow;
 use crate::commands::restart::restart;
 use crate::commands::wait::wait;
 use crate::display::*;
@@ -24,7 +28,7 @@ use crate::display::*;
 /// and interpreting their responses.
 ///
 /// Most commands are a simple ping-pong. However, some commands require a more complex
-/// communication pattern, such as the `follow` command, which can read local files,
+/// communication pattern, such as the `attach` command, which sends input while receiving output,
 /// or the `edit` command, which needs to open an editor locally.
 pub struct Client {
     opt: CliArguments,
@@ -38,6 +42,83 @@ pub fn group_or_default(group: &Option<String>) -> String {
     group.clone().unwrap_or_else(|| "default".to_string())
 }
 
+/// Get the current working directory and all environment variables.
+/// Both are saved for later injection into the started task.
+fn get_path_and_envs() -> Result<(String, HashMap<String, String>)> {
+    let cwd_pathbuf = current_dir()?;
+    let cwd = cwd_pathbuf
+        .to_str()
+        .context("Cannot parse current working directory (Invalid utf8?)")?;
+
+    let mut envs = HashMap::new();
+    for (key, value) in vars() {
+        envs.insert(key, value);
+    }
+
+    Ok((cwd.to_string(), envs))
+}
+
+/// Join all parameters of a command to a single string.
+/// If requested, any special shell characters are escaped before the concatenation.
+fn join_command(command: &[String], escape: bool) -> String {
+    let command: Vec<String> = if escape {
+        command
+            .iter()
+            .map(|parameter| shell_escape::escape(Cow::from(parameter)).into_owned())
+            .collect()
+    } else {
+        command.to_vec()
+    };
+
+    command.join(" ")
+}
+
+/// Get the inputs of a task array.
+/// The source is either a numeric range such as `1..100`, a file with an input per line
+/// or a glob such as `images/*.jpg`.
+fn get_array_inputs(source: &str) -> Result<Vec<String>> {
+    let bounds: Vec<&str> = source.splitn(2, "..").collect();
+    if let [start, end] = bounds.as_slice() {
+        if let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) {
+            return Ok((start..=end).map(|number| number.to_string()).collect());
+        }
+    }
+
+    let inputs: Vec<String> = if Path::new(source).is_file() {
+        read_to_string(source)
+            .with_context(|| format!("Failed to read inputs from {:?}", source))?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(String::from)
+            .collect()
+    } else {
+        let paths = glob::glob(source).with_context(|| format!("Invalid glob {:?}", source))?;
+        paths
+            .filter_map(|path| path.ok())
+            .map(|path| path.to_string_lossy().to_string())
+            .collect()
+    };
+
+    if inputs.is_empty() {
+        bail!("Couldn't find any inputs for {:?}", source);
+    }
+
+    Ok(inputs)
+}
+
+/// Collect the named resources of a task.
+/// Each resource may only be given once, since later values would silently replace earlier ones.
+fn get_resources(resources: &[(String, usize)]) -> Result<BTreeMap<String, usize>> {
+    let mut collected = BTreeMap::new();
+    for (resource, amount) in resources {
+        if collected.insert(resource.clone(), *amount).is_some() {
+            bail!("The resource {:?} has been given more than once", resource);
+        }
+    }
+
+    Ok(collected)
+}
+
 impl Client {
     /// Connect to the daemon, authorize via secret and return a new initialized Client.
     pub async fn new(settings: Settings, opt: CliArguments) -> Result<Self> {
@@ -108,8 +189,13 @@ impl Client {
                 Ok(false)
             }
 
-            SubCommand::Edit { task_id, path } => {
-                let message = edit(&mut self.stream, *task_id, *path).await?;
+            SubCommand::Edit {
+                task_id,
+                path,
+                timeout,
+                priority,
+            } => {
+                let message = edit(&mut self.stream, *task_id, *path, *timeout, *priority).await?;
                 self.handle_response(message);
                 Ok(true)
             }
@@ -117,6 +203,7 @@ impl Client {
                 task_ids,
                 group,
                 all,
+                array,
                 quiet,
             } => {
                 let group = group_or_default(group);
@@ -125,6 +212,7 @@ impl Client {
                     task_ids,
                     &group,
                     *all,
+                    *array,
                     *quiet,
                     &self.colors,
                 )
@@ -139,6 +227,7 @@ impl Client {
                 edit_path,
                 in_place,
                 all_failed,
+                array,
             } => {
                 restart(
                     &mut self.stream,
@@ -149,27 +238,17 @@ impl Client {
                     *edit_path,
                     *in_place,
                     *all_failed,
+                    *array,
                 )
                 .await?;
                 Ok(true)
             }
 
-            SubCommand::Follow { task_id, err } => {
-                // Simple log output follows for local logs don't need any communication with the daemon.
-                // Thereby we handle this separately over here.
-                if self.settings.client.read_local_logs {
-                    local_follow(
-                        &mut self.stream,
-                        &self.settings.shared.pueue_directory,
-                        task_id,
-                        *err,
-                    )
-                    .await?;
-                    return Ok(true);
-                }
-                Ok(false)
+            SubCommand::Attach { task_id } => {
+                let message = attach(&mut self.stream, *task_id).await?;
+                self.handle_response(message);
+                Ok(true)
             }
-
             _ => Ok(false),
         }
     }
@@ -217,12 +296,28 @@ impl Client {
             Message::LogResponse(task_logs) => {
                 print_logs(task_logs, &self.opt.cmd, &self.colors, &self.settings)
             }
+            Message::HistoryResponse(archived) => {
+                print_history(archived, &self.opt.cmd, &self.colors)
+            }
             Message::GroupResponse(groups) => print_groups(groups, &self.colors),
+            Message::SubmitResponse(task_ids) => print_submitted_tasks(task_ids, &self.opt.cmd),
             Message::Stream(text) => {
                 print!("{}", text);
                 io::stdout().flush().unwrap();
                 return true;
             }
+            Message::TaskStream(message) => {
+                print_task_stream(message);
+                return true;
+            }
+            Message::StreamEnd(message) => {
+                print_stream_end(message, &self.colors);
+                // When following several tasks, the stream goes on until all of them finished.
+                return matches!(
+                    &self.opt.cmd,
+                    SubCommand::Follow { task_ids, group, .. } if task_ids.len() > 1 || group.is_some()
+                );
+            }
             _ => error!("Received unhandled response message"),
         };
 
@@ -238,7 +333,7 @@ impl Client {
             action,
             task_ids
                 .iter()
-                .map(|t| format!("task{}", t.to_string()))
+                .map(|t| format!("task{}", t))
                 .collect::<Vec<String>>()
                 .join(", ")
         );
@@ -280,43 +375,105 @@ impl Client {
                 group,
                 delay_until,
                 dependencies,
+                after_failure,
+                after_any,
                 label,
+                timeout,
+                retries,
+                retry_delay,
+                priority,
+                resources,
+                slots,
+                memory,
+                cpus,
+                nice,
+                ionice,
+                max_open_files,
+                max_cpu_time,
+                shell,
+                no_shell,
+                pty,
+                max_log_size,
+                log_rotation,
+                each,
                 print_task_id,
             } => {
-                let cwd_pathbuf = current_dir()?;
-                let cwd = cwd_pathbuf
-                    .to_str()
-                    .context("Cannot parse current working directory (Invalid utf8?)")?;
-
-                let mut envs = HashMap::new();
-                // Save all environment variables for later injection into the started task
-                for (key, value) in vars() {
-                    envs.insert(key, value);
-                }
-
-                // Escape any special shell characters in all strings before we concatenated them
-                // to a single string.
-                let command: Vec<String> = if *escape {
-                    command
-                        .iter()
-                        .map(|parameter| shell_escape::escape(Cow::from(parameter)).into_owned())
-                        .collect()
-                } else {
-                    command.clone()
+                let (path, envs) = get_path_and_envs()?;
+                let each = match each {
+                    Some(source) => get_array_inputs(source)?,
+                    None => Vec::new(),
                 };
-
                 let group = group_or_default(group);
-                Ok(Message::Add(AddMessage {
-                    command: command.join(" "),
-                    path: cwd.to_string(),
+                let resources = get_resources(resources)?;
+                let shell = shell
+                    .as_deref()
+                    .map(parse_shell)
+                    .transpose()
+                    .map_err(|err| anyhow!(err))?;
+                // Without a shell, multiple arguments are escaped, so they're passed as they are.
+                let escape = *escape || (*no_shell && command.len() > 1);
+
+                // All dependencies are sent as a single list.
+                // Only the conditions of those that don't need to succeed are sent separately.
+                let mut dependency_conditions = BTreeMap::new();
+                for id in after_failure {
+                    dependency_conditions.insert(*id, DependencyCondition::Failure);
+                }
+                for id in after_any {
+                    dependency_conditions.insert(*id, DependencyCondition::Any);
+                }
+                let mut all_dependencies = dependencies.to_vec();
+                all_dependencies.extend(dependency_conditions.keys());
+
+                Ok(Message::Add(Box::new(AddMessage {
+                    command: join_command(command, escape),
+                    path,
                     envs,
                     start_immediately: *start_immediately,
                     stashed: *stashed,
                     group,
                     enqueue_at: *delay_until,
-                    dependencies: dependencies.to_vec(),
+                    dependencies: all_dependencies,
+                    dependency_conditions,
                     label: label.clone(),
+                    timeout: *timeout,
+                    retries: *retries,
+                    retry_delay: *retry_delay,
+                    priority: *priority,
+                    resources,
+                    slots: *slots,
+                    memory_limit: *memory,
+                    cpu_limit: *cpus,
+                    nice: *nice,
+                    ionice: *ionice,
+                    max_open_files: *max_open_files,
+                    max_cpu_time: *max_cpu_time,
+                    shell,
+                    no_shell: *no_shell,
+                    pty: *pty,
+                    max_log_size: *max_log_size,
+                    log_rotation: *log_rotation,
+                    each,
+                    array_id: None,
                     print_task_id: *print_task_id,
+                })))
+            }
+            SubCommand::Submit { file, .. } => {
+                let content = read_to_string(file)
+                    .with_context(|| format!("Failed to read workflow file {:?}", file))?;
+                let is_toml =
+                    file.extension().and_then(|extension| extension.to_str()) == Some("toml");
+                let workflow: Workflow = if is_toml {
+                    toml::from_str(&content).context("Failed to parse workflow file")?
+                } else {
+                    serde_yaml::from_str(&content).context("Failed to parse workflow file")?
+                };
+
+                let (path, envs) = get_path_and_envs()?;
+                Ok(Message::Submit(SubmitMessage {
+                    workflow,
+                    path,
+                    envs,
                 }))
             }
             SubCommand::Remove { task_ids } => {
@@ -382,6 +539,9 @@ impl Client {
                 task_ids,
                 group,
                 all,
+                array,
+                signal,
+                grace,
                 children,
             } => {
                 if self.settings.client.show_confirmation_questions {
@@ -392,6 +552,9 @@ impl Client {
                     task_ids: task_ids.clone(),
                     group,
                     all: *all,
+                    array_id: *array,
+                    signal: *signal,
+                    grace: *grace,
                     children: *children,
                 };
                 Ok(Message::Kill(message))
@@ -403,6 +566,18 @@ impl Client {
                 };
                 Ok(Message::Send(message))
             }
+            SubCommand::Signal {
+                task_id,
+                signal,
+                children,
+            } => {
+                let message = SignalMessage {
+                    task_id: *task_id,
+                    signal: *signal,
+                    children: *children,
+                };
+                Ok(Message::Signal(message))
+            }
             SubCommand::Group { add, remove } => {
                 let message = GroupMessage {
                     add: add.clone(),
@@ -410,24 +585,81 @@ impl Client {
                 };
                 Ok(Message::Group(message))
             }
+            SubCommand::Schedule { cmd } => {
+                let message = match cmd {
+                    Some(ScheduleCommand::Add {
+                        expression,
+                        command,
+                        escape,
+                        group,
+                        dependencies,
+                        label,
+                        timeout,
+                        retries,
+                        retry_delay,
+                        priority,
+                    }) => {
+                        let (path, envs) = get_path_and_envs()?;
+                        ScheduleMessage::Add(Box::new(ScheduleAddMessage {
+                            expression: expression.clone(),
+                            command: join_command(command, *escape),
+                            path,
+                            envs,
+                            group: group_or_default(group),
+                            dependencies: dependencies.to_vec(),
+                            label: label.clone(),
+                            timeout: *timeout,
+                            retries: *retries,
+                            retry_delay: *retry_delay,
+                            priority: *priority,
+                        }))
+                    }
+                    // Schedules are part of the state, which is then displayed by the client.
+                    Some(ScheduleCommand::List) | None => return Ok(Message::Status),
+                    Some(ScheduleCommand::Remove { schedule_ids }) => {
+                        ScheduleMessage::Remove(schedule_ids.clone())
+                    }
+                    Some(ScheduleCommand::Pause { schedule_ids }) => {
+                        ScheduleMessage::Pause(schedule_ids.clone())
+                    }
+                    Some(ScheduleCommand::Resume { schedule_ids }) => {
+                        ScheduleMessage::Resume(schedule_ids.clone())
+                    }
+                };
+                Ok(Message::Schedule(message))
+            }
             SubCommand::Status { .. } => Ok(Message::Status),
+            SubCommand::Top { .. } => Ok(Message::Status),
             SubCommand::Log {
                 task_ids,
+                archived,
                 lines,
                 full,
+                timestamps,
+                stderr_only,
                 ..
             } => {
                 let message = LogRequestMessage {
                     task_ids: task_ids.clone(),
-                    send_logs: !self.settings.client.read_local_logs,
+                    // Archived logs only exist in the archive, which is read by the daemon.
+                    send_logs: !self.settings.client.read_local_logs || *archived,
                     lines: *lines,
                     full: *full,
+                    timestamps: *timestamps,
+                    stderr_only: *stderr_only,
+                    archived: *archived,
                 };
                 Ok(Message::Log(message))
             }
-            SubCommand::Follow { task_id, err } => {
+            SubCommand::History { .. } => Ok(Message::History),
+            SubCommand::Follow {
+                task_ids,
+                group,
+                err,
+            } => {
                 let message = StreamRequestMessage {
-                    task_id: *task_id,
+                    task_ids: task_ids.clone(),
+                    group: group.clone(),
                     err: *err,
                 };
                 Ok(Message::StreamRequest(message))
@@ -465,6 +697,7 @@ impl Client {
             SubCommand::Restart { .. } => bail!("Restarts have to be handled earlier"),
             SubCommand::Edit { .. } => bail!("Edits have to be handled earlier"),
             SubCommand::Wait { .. } => bail!("Wait has to be handled earlier"),
+            SubCommand::Attach { .. } => bail!("Attach has to be handled earlier"),
         }
     }
 }
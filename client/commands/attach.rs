@@ -0,0 +1,131 @@
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::*;
+
+/// The key that detaches from a task, which is `Ctrl-]`.
+const DETACH_KEY: u8 = 0x1d;
+
+/// How long the client waits for output of the task, before it sends the input of the user.
+const INPUT_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Puts the terminal into raw mode, while it's alive.
+/// This way, the terminal is restored, even if an error occurs while being attached.
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> Result<RawMode> {
+        enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
+
+/// Attach to a running task.
+///
+/// The terminal is put into raw mode, so every key press is sent to the task as it is.
+/// Meanwhile, the daemon sends the output of the task, which is printed as it arrives.
+/// This goes on, until the user presses the detach key or the task finishes.
+///
+/// Returns the final message of the daemon, which should be handled by the caller.
+pub async fn attach(stream: &mut GenericStream, task_id: usize) -> Result<Message> {
+    send_message(Message::AttachRequest(task_id), stream).await?;
+
+    // The daemon answers with the latest output of the task, if we're allowed to attach.
+    match receive_message(stream).await? {
+        Message::Stream(output) => {
+            println!("Attached to task {}. Press Ctrl-] to detach.", task_id);
+            print_output(&output);
+        }
+        message => return Ok(message),
+    }
+
+    let _raw_mode = RawMode::enable()?;
+    let input = read_input();
+
+    let mut detached = false;
+    loop {
+        // Output is printed, until the daemon sends its final message.
+        match try_receive_message(stream, INPUT_INTERVAL).await? {
+            Some(Message::Stream(output)) => print_output(&output),
+            Some(message @ Message::Success(_)) | Some(message @ Message::Failure(_)) => {
+                // Start the final message in a new line.
+                println!("\r");
+                return Ok(message);
+            }
+            Some(_) => bail!("Received unexpected message while being attached"),
+            None => (),
+        }
+
+        // Any further input is ignored, once the user detached.
+        if detached {
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        while let Ok(chunk) = input.try_recv() {
+            bytes.extend(chunk);
+        }
+
+        // Everything that has been typed before the detach key is still sent to the task.
+        let detach = match bytes.iter().position(|byte| *byte == DETACH_KEY) {
+            Some(position) => {
+                bytes.truncate(position);
+                true
+            }
+            None => false,
+        };
+
+        if !bytes.is_empty() {
+            let message = AttachInputMessage {
+                task_id,
+                input: bytes,
+            };
+            send_message(Message::AttachInput(message), stream).await?;
+        }
+
+        if detach {
+            send_message(Message::Detach, stream).await?;
+            detached = true;
+        }
+    }
+}
+
+/// Read the input of the user in a separate thread, since reading from stdin blocks.
+fn read_input() -> Receiver<Vec<u8>> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buffer = [0; 1024];
+        loop {
+            let count = match stdin.read(&mut buffer) {
+                Ok(0) | Err(_) => return,
+                Ok(count) => count,
+            };
+            if sender.send(buffer[..count].to_vec()).is_err() {
+                return;
+            }
+        }
+    });
+
+    receiver
+}
+
+/// Print output of the task.
+/// Newlines don't return the cursor to the start of the line in raw mode,
+/// which is why a carriage return is added to each of them, unless there already is one.
+fn print_output(output: &str) {
+    print!("{}", output.replace("\r\n", "\n").replace('\n', "\r\n"));
+    io::stdout().flush().unwrap();
+}
@@ -2,12 +2,15 @@ use std::env;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::process::Command;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use tempfile::NamedTempFile;
 
 use pueue_lib::network::message::*;
 use pueue_lib::network::protocol::*;
 
+use crate::cli::parse_duration;
+use crate::display::helper::format_duration;
+
 /// This function handles the logic for editing tasks.
 /// At first, we request the daemon to send us the task to edit.
 /// This also results in the task being `Locked` on the daemon side, preventing it from being
@@ -15,7 +18,13 @@ use pueue_lib::network::protocol::*;
 ///
 /// After receiving the task information, the user can then edit it in their editor.
 /// Upon exiting the text editor, the line will then be read and sent to the server
-pub async fn edit(stream: &mut GenericStream, task_id: usize, edit_path: bool) -> Result<Message> {
+pub async fn edit(
+    stream: &mut GenericStream,
+    task_id: usize,
+    edit_path: bool,
+    edit_timeout: bool,
+    edit_priority: bool,
+) -> Result<Message> {
     // Request the data to edit from the server and issue a task-lock while doing so.
     let init_message = Message::EditRequest(task_id);
     send_message(init_message, stream).await?;
@@ -31,11 +40,31 @@ pub async fn edit(stream: &mut GenericStream, task_id: usize, edit_path: bool) -
         return Ok(init_response);
     };
 
-    // Edit either the path or the command, depending on the `path` flag.
+    // Edit either the path, the timeout, the priority or the command,
+    // depending on the given flags.
     let mut command = init_response.command;
     let mut path = init_response.path;
+    let mut timeout = init_response.timeout;
+    let mut priority = init_response.priority;
     if edit_path {
         path = edit_line(&path)?;
+    } else if edit_timeout {
+        let line = edit_line(&timeout.map(format_duration).unwrap_or_default())?;
+        // An empty line removes the timeout.
+        timeout = if line.trim().is_empty() {
+            None
+        } else {
+            match parse_duration(&line) {
+                Ok(timeout) => Some(timeout),
+                Err(err) => bail!("Failed to parse timeout \"{}\": {}", line, err),
+            }
+        };
+    } else if edit_priority {
+        let line = edit_line(&priority.to_string())?;
+        priority = match line.trim().parse() {
+            Ok(priority) => priority,
+            Err(_) => bail!("Failed to parse priority \"{}\"", line),
+        };
     } else {
         command = edit_line(&command)?
     };
@@ -45,6 +74,8 @@ pub async fn edit(stream: &mut GenericStream, task_id: usize, edit_path: bool) -
         task_id,
         command,
         path,
+        timeout,
+        priority,
     });
     send_message(edit_message, stream).await?;
 
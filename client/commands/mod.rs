@@ -4,8 +4,8 @@ use pueue_lib::network::message::Message;
 use pueue_lib::network::protocol::*;
 use pueue_lib::state::State;
 
+pub mod attach;
 pub mod edit;
-pub mod local_follow;
 pub mod restart;
 pub mod wait;
 
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use anyhow::{bail, Result};
 
 use pueue_lib::network::message::*;
@@ -11,7 +13,8 @@ use crate::commands::get_state;
 /// is create from the existing task in the state.
 ///
 /// This is done on the client-side, so we can easily edit the task before restarting it.
-/// It's also necessary to get all failed tasks, in case the user specified the --all_failed flag.
+/// It's also necessary to get all failed tasks, in case the user specified the --all_failed flag,
+/// and all tasks of a task array.
 #[allow(clippy::too_many_arguments)]
 pub async fn restart(
     stream: &mut GenericStream,
@@ -22,6 +25,7 @@ pub async fn restart(
     edit_path: bool,
     in_place: bool,
     all_failed: bool,
+    array: Option<usize>,
 ) -> Result<()> {
     let new_status = if stashed {
         TaskStatus::Stashed
@@ -30,10 +34,22 @@ pub async fn restart(
     };
 
     let state = get_state(stream).await?;
+    // Only consider the tasks of a task array, if one has been specified.
+    let array_task_ids = match array {
+        Some(array_id) => {
+            let task_ids = state.array_task_ids(array_id);
+            if task_ids.is_empty() {
+                bail!("There's no task array {}", array_id);
+            }
+            Some(task_ids)
+        }
+        None => None,
+    };
+
     let (matching, mismatching) = if all_failed {
         // All failed tasks need to be restarted.
         // First we have to get all finished tasks (Done)
-        let (matching, _) = state.tasks_in_statuses(vec![TaskStatus::Done], None);
+        let (matching, _) = state.tasks_in_statuses(vec![TaskStatus::Done], array_task_ids);
 
         // Now remove all tasks that finished succesfully
         let failed = matching
@@ -47,6 +63,8 @@ pub async fn restart(
         // We return an empty vec for the mismatching tasks, since there shouldn't be any.
         // Any User provided ids are ignored in this mode.
         (failed, Vec::new())
+    } else if array_task_ids.is_some() {
+        state.tasks_in_statuses(vec![TaskStatus::Done], array_task_ids)
     } else if task_ids.is_empty() {
         bail!("Please provide the ids of the tasks you want to restart.");
     } else {
@@ -90,7 +108,7 @@ pub async fn restart(
         }
 
         // Create a AddMessage to send the task to the daemon from the updated info and the old task.
-        let add_task_message = Message::Add(AddMessage {
+        let add_task_message = Message::Add(Box::new(AddMessage {
             command,
             path,
             envs: task.envs.clone(),
@@ -99,9 +117,29 @@ pub async fn restart(
             group: task.group.clone(),
             enqueue_at: None,
             dependencies: Vec::new(),
+            dependency_conditions: BTreeMap::new(),
             label: task.label.clone(),
+            timeout: task.timeout,
+            retries: task.retries,
+            retry_delay: task.retry_delay,
+            priority: task.priority,
+            resources: task.resources.clone(),
+            slots: task.slots,
+            memory_limit: task.memory_limit,
+            cpu_limit: task.cpu_limit,
+            nice: task.nice,
+            ionice: task.ionice,
+            max_open_files: task.max_open_files,
+            max_cpu_time: task.max_cpu_time,
+            shell: task.shell.clone(),
+            no_shell: task.no_shell,
+            pty: task.pty,
+            max_log_size: task.max_log_size,
+            log_rotation: task.log_rotation,
+            each: Vec::new(),
+            array_id: task.array_id,
             print_task_id: false,
-        });
+        }));
 
         // Send the cloned task to the daemon and abort on any failure messages.
         send_message(add_task_message, stream).await?;
@@ -17,6 +17,7 @@ use crate::{commands::get_state, display::colors::Colors};
 /// - Default queue (no parameter given)
 /// - Group
 /// - A list of task ids
+/// - A task array
 /// - All tasks (`all == true`)
 ///
 /// By default, this will log status changes on tasks.
@@ -26,6 +27,7 @@ pub async fn wait(
     task_ids: &[usize],
     group: &str,
     all: bool,
+    array: Option<usize>,
     quiet: bool,
     colors: &Colors,
 ) -> Result<()> {
@@ -45,9 +47,17 @@ pub async fn wait(
                 .filter(|(id, _)| task_ids.contains(id))
                 .map(|(_, task)| task.clone())
                 .collect()
+        } else if let Some(array_id) = array {
+            // Get all tasks of a specific task array
+            state
+                .tasks
+                .iter()
+                .filter(|(_, task)| task.array_id == Some(array_id))
+                .map(|(_, task)| task.clone())
+                .collect()
         } else if all {
             // Get all tasks
-            state.tasks.iter().map(|(_, task)| task.clone()).collect()
+            state.tasks.values().cloned().collect()
         } else {
             // Get all tasks of a specific group
             state
@@ -92,7 +102,7 @@ pub async fn wait(
             // Update the (previous) task status and log any changes
             watched_tasks.insert(task.id, task.status.clone());
             if !quiet {
-                log_status_change(&current_time, previous_status, &task, colors);
+                log_status_change(&current_time, previous_status, task, colors);
             }
         }
 
@@ -165,6 +175,27 @@ fn log_status_change(
                     style_text("killed", Some(colors.red()), None)
                 )
             }
+            Some(TaskResult::Signaled(ref signal)) => {
+                format!(
+                    "Task {} has been killed by {}",
+                    style_text(task.id, None, Some(Attribute::Bold)),
+                    style_text(signal, Some(colors.red()), None)
+                )
+            }
+            Some(TaskResult::TimedOut) => {
+                format!(
+                    "Task {} has been killed due to its {}",
+                    style_text(task.id, None, Some(Attribute::Bold)),
+                    style_text("timeout", Some(colors.red()), None)
+                )
+            }
+            Some(TaskResult::OutOfMemory) => {
+                format!(
+                    "Task {} has been killed due to its {}",
+                    style_text(task.id, None, Some(Attribute::Bold)),
+                    style_text("memory limit", Some(colors.red()), None)
+                )
+            }
             None => panic!("Got a 'Done' task without a task result. Please report this bug."),
         };
         println!("{} - {}", current_time, text);
@@ -1,45 +1,42 @@
-use std::io;
-use std::path::Path;
-use std::thread::sleep;
-use std::time::Duration;
+use std::io::{self, Write};
 
-use pueue_lib::log::{get_log_file_handles, get_log_paths};
+use crossterm::style::{Attribute, Color};
 
-/// Follow the log ouput of running task.
-///
-/// If no task is specified, this will check for the following cases:
-///
-/// - No running task: Print an error that there are no running tasks
-/// - Single running task: Follow the output of that task
-/// - Multiple running tasks: Print out the list of possible tasks to follow.
-pub fn follow_local_task_logs(pueue_directory: &Path, task_id: usize, stderr: bool) {
-    let (stdout_handle, stderr_handle) = match get_log_file_handles(task_id, pueue_directory) {
-        Ok((stdout, stderr)) => (stdout, stderr),
-        Err(err) => {
-            println!("Failed to get log file handles: {}", err);
-            return;
-        }
-    };
-    let mut handle = if stderr { stderr_handle } else { stdout_handle };
+use pueue_lib::network::message::{StreamEndMessage, TaskStreamMessage};
+
+use super::colors::Colors;
+use super::helper::{get_result_text, style_text};
+
+/// The colors of the prefixes, which show the task that printed a line of output.
+const PREFIX_COLORS: [Color; 5] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Blue,
+    Color::Yellow,
+    Color::Green,
+];
 
-    let (out_path, err_path) = get_log_paths(task_id, pueue_directory);
-    let handle_path = if stderr { err_path } else { out_path };
+/// Print the final result of a followed task, once the daemon ends the stream.
+pub fn print_stream_end(message: StreamEndMessage, colors: &Colors) {
+    let (text, color) = match &message.result {
+        Some(result) => get_result_text(result, colors),
+        None => ("has been removed".into(), colors.white()),
+    };
+    let text = format!("Task {} {}", message.task_id, text);
+    println!("{}", style_text(text, Some(color), None));
+}
 
-    // Stdout handler to directly write log file output to io::stdout
-    // without having to load anything into memory.
-    let mut stdout = io::stdout();
-    loop {
-        // Check whether the file still exists. Exit if it doesn't.
-        if !handle_path.exists() {
-            println!("File has gone away. Did somebody remove the task?");
-            return;
-        }
-        // Read the next chunk of text from the last position.
-        if let Err(err) = io::copy(&mut handle, &mut stdout) {
-            println!("Error while reading file: {}", err);
-            return;
-        };
-        let timeout = Duration::from_millis(100);
-        sleep(timeout);
+/// Print the output of one of several followed tasks.
+/// Each line is prefixed with the id of the task in the task's own color.
+pub fn print_task_stream(message: TaskStreamMessage) {
+    let color = PREFIX_COLORS[message.task_id % PREFIX_COLORS.len()];
+    let prefix = style_text(
+        format!("[{}]", message.task_id),
+        Some(color),
+        Some(Attribute::Bold),
+    );
+    for line in message.text.lines() {
+        println!("{} {}", prefix, line);
     }
+    io::stdout().flush().unwrap();
 }
@@ -7,7 +7,7 @@ pub fn print_groups(message: GroupResponseMessage, colors: &Colors) {
     let mut group_iter = message.groups.iter().peekable();
     while let Some((name, status)) = group_iter.next() {
         let parallel = *message.settings.get(name).unwrap();
-        let styled = get_group_headline(name, &status, parallel, colors);
+        let styled = get_group_headline(name, status, parallel, colors);
 
         text.push_str(&styled);
         if group_iter.peek().is_some() {
@@ -5,7 +5,7 @@ use crossterm::style::{style, Attribute, Color};
 use crossterm::tty::IsTty;
 
 use pueue_lib::state::GroupStatus;
-use pueue_lib::task::Task;
+use pueue_lib::task::{Task, TaskResult};
 
 use super::colors::Colors;
 
@@ -34,21 +34,100 @@ pub fn style_text<T: ToString>(
     styled.to_string()
 }
 
+/// Describe the result of a finished task, e.g. "failed with exit code 1".
+/// Also returns the color, in which the result should be shown.
+pub fn get_result_text(result: &TaskResult, colors: &Colors) -> (String, Color) {
+    match result {
+        TaskResult::Success => ("completed successfully".into(), colors.green()),
+        TaskResult::Failed(exit_code) => {
+            (format!("failed with exit code {}", exit_code), colors.red())
+        }
+        TaskResult::FailedToSpawn(err) => (format!("failed to spawn: {}", err), colors.red()),
+        TaskResult::Killed => ("killed by system or user".into(), colors.red()),
+        TaskResult::Signaled(signal) => (format!("killed by {}", signal), colors.red()),
+        TaskResult::Errored => ("some IO error.\n Check daemon log.".into(), colors.red()),
+        TaskResult::DependencyFailed => ("dependency failed".into(), colors.red()),
+        TaskResult::TimedOut => ("killed after exceeding its timeout".into(), colors.red()),
+        TaskResult::OutOfMemory => (
+            "killed after exceeding its memory limit".into(),
+            colors.red(),
+        ),
+    }
+}
+
+/// The optional columns of the status table, which are only shown if any task needs them.
+pub struct SpecialColumns {
+    pub delayed_tasks: bool,
+    pub dependencies: bool,
+    pub labels: bool,
+    pub timeouts: bool,
+    pub retries: bool,
+    pub priorities: bool,
+    pub resources: bool,
+    pub slots: bool,
+    pub arrays: bool,
+    pub usage: bool,
+}
+
 /// By default, several columns aren't shown until there's actually some data to display.
 /// This function determines, which of those columns actually need to be shown.
-pub fn has_special_columns(tasks: &BTreeMap<usize, Task>) -> (bool, bool, bool) {
-    // Check whether there are any delayed tasks.
-    let has_delayed_tasks = tasks.iter().any(|(_id, task)| task.enqueue_at.is_some());
+pub fn has_special_columns(tasks: &BTreeMap<usize, Task>) -> SpecialColumns {
+    let any = |check: fn(&Task) -> bool| tasks.values().any(check);
 
-    // Check whether there are any tasks with dependencies.
-    let has_dependencies = tasks
-        .iter()
-        .any(|(_id, task)| !task.dependencies.is_empty());
+    SpecialColumns {
+        delayed_tasks: any(|task| task.enqueue_at.is_some()),
+        dependencies: any(|task| !task.dependencies.is_empty()),
+        labels: any(|task| task.label.is_some()),
+        timeouts: any(|task| task.timeout.is_some()),
+        retries: any(|task| task.retries > 0),
+        // Only tasks with a non-default priority are of interest.
+        priorities: any(|task| task.priority != 0),
+        resources: any(|task| !task.resources.is_empty()),
+        // Only tasks that occupy more than a single slot are of interest.
+        slots: any(|task| task.slots != 1),
+        arrays: any(|task| task.array_id.is_some()),
+        // The resource usage is only available, once it has been sampled.
+        usage: any(|task| task.current_usage.is_some()),
+    }
+}
+
+/// Format a duration in seconds into a short human readable representation, e.g. `1h 30m`.
+pub fn format_duration(seconds: u64) -> String {
+    let units = [("d", 60 * 60 * 24), ("h", 60 * 60), ("m", 60), ("s", 1)];
 
-    // Check whether there are any tasks a label.
-    let has_labels = tasks.iter().any(|(_id, task)| task.label.is_some());
+    let mut remaining = seconds;
+    let mut parts = Vec::new();
+    for (unit, size) in units.iter() {
+        if remaining >= *size {
+            parts.push(format!("{}{}", remaining / size, unit));
+            remaining %= size;
+        }
+    }
 
-    (has_delayed_tasks, has_dependencies, has_labels)
+    if parts.is_empty() {
+        return "0s".into();
+    }
+    parts.join(" ")
+}
+
+/// Format an amount of bytes into a short human readable representation, e.g. `1.5G`.
+/// Binary units are used, i.e. `1K` are 1024 bytes.
+pub fn format_size(bytes: u64) -> String {
+    let units = [
+        ("T", 1u64 << 40),
+        ("G", 1 << 30),
+        ("M", 1 << 20),
+        ("K", 1 << 10),
+    ];
+
+    for (unit, size) in units.iter() {
+        if bytes >= *size {
+            let value = format!("{:.1}", bytes as f64 / *size as f64);
+            return format!("{}{}", value.trim_end_matches(".0"), unit);
+        }
+    }
+
+    format!("{}B", bytes)
 }
 
 /// Return a nicely formatted headline that's displayed above group tables
@@ -89,3 +168,27 @@ pub fn sort_tasks_by_group(
 
     sorted_task_groups
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(59), "59s");
+        assert_eq!(format_duration(90 * 60), "1h 30m");
+        assert_eq!(format_duration(36 * 60 * 60 + 5), "1d 12h 5s");
+    }
+
+    #[test]
+    /// Formatted durations can be parsed again, e.g. when editing the timeout of a task.
+    fn test_format_duration_roundtrip() {
+        for seconds in [1, 61, 3600, 90061].iter() {
+            assert_eq!(
+                crate::cli::parse_duration(&format_duration(*seconds)),
+                Ok(*seconds)
+            );
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use comfy_table::presets::UTF8_HORIZONTAL_BORDERS_ONLY;
+use comfy_table::*;
+
+use pueue_lib::archive::ArchivedTask;
+use pueue_lib::task::{TaskResult, TaskStatus};
+
+use super::{colors::Colors, helper::*};
+use crate::cli::SubCommand;
+
+/// Print all archived tasks into a table, together with the time they have been archived.
+pub fn print_history(archived: Vec<ArchivedTask>, cli_command: &SubCommand, colors: &Colors) {
+    let json = match cli_command {
+        SubCommand::History { json } => *json,
+        _ => panic!(
+            "Got wrong Subcommand {:?} in print_history. This shouldn't happen",
+            cli_command
+        ),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string(&archived).unwrap());
+        return;
+    }
+
+    if archived.is_empty() {
+        println!("The archive is empty. Enable `archive_logs` in the daemon's config to fill it.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .load_preset(UTF8_HORIZONTAL_BORDERS_ONLY)
+        .set_header(vec![
+            Cell::new("Index"),
+            Cell::new("Task"),
+            Cell::new("Status"),
+            Cell::new("Command"),
+            Cell::new("End"),
+            Cell::new("Archived"),
+            Cell::new("Size"),
+        ]);
+
+    for entry in archived.iter() {
+        let task = &entry.task;
+        let mut row = Row::new();
+        row.add_cell(Cell::new(entry.id.to_string()));
+        row.add_cell(Cell::new(task.id.to_string()));
+
+        // Tasks can be removed before they ever ran, in which case they don't have a result.
+        let status = match (&task.status, &task.result) {
+            (TaskStatus::Done, Some(TaskResult::Success)) => {
+                Cell::new(TaskResult::Success.to_string()).fg(colors.green())
+            }
+            (TaskStatus::Done, Some(result)) => Cell::new(result.to_string()).fg(colors.red()),
+            (status, _) => Cell::new(status.to_string()).fg(colors.yellow()),
+        };
+        row.add_cell(status);
+
+        row.add_cell(Cell::new(&task.original_command));
+        if let Some(end) = task.end {
+            row.add_cell(Cell::new(end.format("%Y-%m-%d\n%H:%M:%S")));
+        } else {
+            row.add_cell(Cell::new(""));
+        }
+        row.add_cell(Cell::new(entry.archived_at.format("%Y-%m-%d\n%H:%M:%S")));
+        row.add_cell(Cell::new(format_size(entry.size)));
+
+        table.add_row(row);
+    }
+
+    println!("{}", style_text("History", None, Some(Attribute::Bold)));
+    println!("{}", table);
+}
@@ -1,12 +1,13 @@
 use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{self, Stdout};
+use std::io::{self, Read, Stdout};
+use std::path::Path;
 
 use anyhow::Result;
 use comfy_table::*;
 use snap::read::FrameDecoder;
 
-use pueue_lib::log::{get_log_file_handles, read_last_lines};
+use pueue_lib::log::*;
 use pueue_lib::network::message::TaskLogMessage;
 use pueue_lib::settings::Settings;
 use pueue_lib::task::{Task, TaskResult, TaskStatus};
@@ -14,6 +15,15 @@ use pueue_lib::task::{Task, TaskResult, TaskStatus};
 use super::{colors::Colors, helper::*};
 use crate::cli::SubCommand;
 
+/// How the time of each line of a combined log is shown.
+#[derive(Clone, Copy, Debug)]
+pub enum TimestampFormat {
+    /// The time of the day.
+    Absolute,
+    /// The time since the start of the task.
+    Relative,
+}
+
 /// Print the log ouput of finished tasks.
 /// Either print the logs of every task
 /// or only print the logs of the specified tasks.
@@ -25,13 +35,32 @@ pub fn print_logs(
 ) {
     // Get actual commandline options.
     // This is necessary to know how we should display/return the log information.
-    let (json, task_ids, lines, full) = match cli_command {
+    let (json, task_ids, archived, lines, full, timestamps, stderr_only) = match cli_command {
         SubCommand::Log {
             json,
             task_ids,
+            archived,
             lines,
             full,
-        } => (*json, task_ids.clone(), *lines, *full),
+            timestamps,
+            relative,
+            stderr_only,
+        } => {
+            let timestamps = match (timestamps, relative) {
+                (false, _) => None,
+                (true, false) => Some(TimestampFormat::Absolute),
+                (true, true) => Some(TimestampFormat::Relative),
+            };
+            (
+                *json,
+                task_ids.clone(),
+                *archived,
+                *lines,
+                *full,
+                timestamps,
+                *stderr_only,
+            )
+        }
         _ => panic!(
             "Got wrong Subcommand {:?} in print_log. This shouldn't happen",
             cli_command
@@ -47,6 +76,11 @@ pub fn print_logs(
     }
 
     // Check some early return conditions
+    if archived && task_logs.is_empty() {
+        println!("There are no archived tasks");
+        return;
+    }
+
     if task_ids.is_empty() && task_logs.is_empty() {
         println!("There are no finished tasks");
         return;
@@ -75,12 +109,12 @@ pub fn print_logs(
 
     // Do the actual log printing
     let mut task_iter = task_logs.iter_mut().peekable();
-    while let Some((_, mut task_log)) = task_iter.next() {
-        print_log(&mut task_log, colors, settings, lines);
+    while let Some((_, task_log)) = task_iter.next() {
+        print_log(task_log, colors, settings, lines, timestamps, stderr_only);
 
         // Add a newline if there is another task that's going to be printed.
         if let Some((_, task_log)) = task_iter.peek() {
-            if vec![TaskStatus::Done, TaskStatus::Running, TaskStatus::Paused]
+            if [TaskStatus::Done, TaskStatus::Running, TaskStatus::Paused]
                 .contains(&task_log.task.status)
             {
                 println!();
@@ -96,24 +130,36 @@ pub fn print_logs(
 /// lines: Whether we should reduce the log output of each task to a specific number of lines.
 ///         `None` implicates that everything should be printed.
 ///         This is only important, if we read local lines.
+/// timestamps: Show the combined log of the task with the time of each line, if it has one.
+/// stderr_only: Only show the output of stderr.
 pub fn print_log(
     message: &mut TaskLogMessage,
     colors: &Colors,
     settings: &Settings,
     lines: Option<usize>,
+    timestamps: Option<TimestampFormat>,
+    stderr_only: bool,
 ) {
     let task = &message.task;
     // We only show logs of finished or running tasks.
-    if !vec![TaskStatus::Done, TaskStatus::Running, TaskStatus::Paused].contains(&task.status) {
+    if ![TaskStatus::Done, TaskStatus::Running, TaskStatus::Paused].contains(&task.status) {
         return;
     }
 
     print_task_info(task, colors);
 
-    if settings.client.read_local_logs {
-        print_local_log(message.task.id, colors, settings, lines);
-    } else if message.stdout.is_some() && message.stderr.is_some() {
-        print_remote_log(message, colors);
+    if let Some(format) = timestamps {
+        if print_combined_log(message, colors, settings, lines, format, stderr_only) {
+            return;
+        }
+        println!("\nThere's no combined log for this task. Enable `combined_logs` in the daemon's config to get timestamps.");
+    }
+
+    // Logs of archived tasks are always sent by the daemon, even if local logs are read.
+    if message.stdout.is_some() && message.stderr.is_some() {
+        print_remote_log(message, colors, stderr_only);
+    } else if settings.client.read_local_logs {
+        print_local_log(message.task.id, colors, settings, lines, stderr_only);
     } else {
         println!("Logs requested from pueue daemon, but none received. Please report this bug.");
     }
@@ -125,14 +171,7 @@ fn print_task_info(task: &Task, colors: &Colors) {
     let task_cell = Cell::new(format!("Task {}: ", task.id)).add_attribute(Attribute::Bold);
 
     let (exit_status, color) = match &task.result {
-        Some(TaskResult::Success) => ("completed successfully".into(), colors.green()),
-        Some(TaskResult::Failed(exit_code)) => {
-            (format!("failed with exit code {}", exit_code), colors.red())
-        }
-        Some(TaskResult::FailedToSpawn(err)) => (format!("failed to spawn: {}", err), colors.red()),
-        Some(TaskResult::Killed) => ("killed by system or user".into(), colors.red()),
-        Some(TaskResult::Errored) => ("some IO error.\n Check daemon log.".into(), colors.red()),
-        Some(TaskResult::DependencyFailed) => ("dependency failed".into(), colors.red()),
+        Some(result) => get_result_text(result, colors),
         None => match &task.status {
             TaskStatus::Paused => ("paused".into(), colors.white()),
             TaskStatus::Running => ("running".into(), colors.yellow()),
@@ -163,6 +202,92 @@ fn print_task_info(task: &Task, colors: &Colors) {
         Cell::new(&task.path),
     ]);
 
+    if let Some(timeout) = task.timeout {
+        table.add_row(vec![
+            Cell::new("Timeout:").add_attribute(Attribute::Bold),
+            Cell::new(format_duration(timeout)),
+        ]);
+    }
+    if let Some(memory_limit) = task.memory_limit {
+        table.add_row(vec![
+            Cell::new("Memory limit:").add_attribute(Attribute::Bold),
+            Cell::new(format_size(memory_limit)),
+        ]);
+    }
+    if let Some(cpu_limit) = task.cpu_limit {
+        table.add_row(vec![
+            Cell::new("CPU limit:").add_attribute(Attribute::Bold),
+            Cell::new(cpu_limit.to_string()),
+        ]);
+    }
+    if let Some(nice) = task.nice {
+        table.add_row(vec![
+            Cell::new("Nice:").add_attribute(Attribute::Bold),
+            Cell::new(nice.to_string()),
+        ]);
+    }
+    if let Some(ionice) = task.ionice {
+        table.add_row(vec![
+            Cell::new("I/O priority:").add_attribute(Attribute::Bold),
+            Cell::new(ionice.to_string()),
+        ]);
+    }
+    if let Some(max_open_files) = task.max_open_files {
+        table.add_row(vec![
+            Cell::new("Max open files:").add_attribute(Attribute::Bold),
+            Cell::new(max_open_files.to_string()),
+        ]);
+    }
+    if let Some(max_cpu_time) = task.max_cpu_time {
+        table.add_row(vec![
+            Cell::new("Max CPU time:").add_attribute(Attribute::Bold),
+            Cell::new(format_duration(max_cpu_time)),
+        ]);
+    }
+    if task.pty {
+        table.add_row(vec![
+            Cell::new("Terminal:").add_attribute(Attribute::Bold),
+            Cell::new("pseudo terminal"),
+        ]);
+    }
+    if let Some(max_log_size) = task.max_log_size {
+        let rotation = task.log_rotation.unwrap_or_default();
+        table.add_row(vec![
+            Cell::new("Max log size:").add_attribute(Attribute::Bold),
+            Cell::new(format!("{} ({})", format_size(max_log_size), rotation)),
+        ]);
+    }
+    if task.no_shell {
+        table.add_row(vec![
+            Cell::new("Shell:").add_attribute(Attribute::Bold),
+            Cell::new("none"),
+        ]);
+    } else if let Some(shell) = &task.shell {
+        table.add_row(vec![
+            Cell::new("Shell:").add_attribute(Attribute::Bold),
+            Cell::new(shell_words::join(shell)),
+        ]);
+    }
+
+    // Show the results of all previous attempts of the task.
+    if !task.attempts.is_empty() {
+        let attempts = task
+            .attempts
+            .iter()
+            .map(|result| match result {
+                TaskResult::Failed(exit_code) => format!("exit code {}", exit_code),
+                TaskResult::TimedOut => "timed out".to_string(),
+                TaskResult::OutOfMemory => "out of memory".to_string(),
+                result => result.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        table.add_row(vec![
+            Cell::new("Previous attempts:").add_attribute(Attribute::Bold),
+            Cell::new(attempts),
+        ]);
+    }
+
     // Start and end time
     if let Some(start) = task.start {
         table.add_row(vec![
@@ -177,6 +302,35 @@ fn print_task_info(task: &Task, colors: &Colors) {
         ]);
     }
 
+    // The resources used by the task, once it finished.
+    if let Some(usage) = &task.resource_usage {
+        table.add_row(vec![
+            Cell::new("CPU time:").add_attribute(Attribute::Bold),
+            Cell::new(format!(
+                "{:.2}s user, {:.2}s system",
+                usage.user_time, usage.system_time
+            )),
+        ]);
+        table.add_row(vec![
+            Cell::new("Peak memory:").add_attribute(Attribute::Bold),
+            Cell::new(format_size(usage.max_rss)),
+        ]);
+        table.add_row(vec![
+            Cell::new("Block I/O:").add_attribute(Attribute::Bold),
+            Cell::new(format!(
+                "{} inputs, {} outputs",
+                usage.block_input, usage.block_output
+            )),
+        ]);
+        table.add_row(vec![
+            Cell::new("Context switches:").add_attribute(Attribute::Bold),
+            Cell::new(format!(
+                "{} voluntary, {} involuntary",
+                usage.voluntary_context_switches, usage.involuntary_context_switches
+            )),
+        ]);
+    }
+
     // Set the padding of the left column to 0 align the keys to the right
     let first_column = table.get_column_mut(0).unwrap();
     first_column.set_cell_alignment(CellAlignment::Right);
@@ -185,9 +339,84 @@ fn print_task_info(task: &Task, colors: &Colors) {
     println!("{}", table);
 }
 
+/// Print the combined log of a task, with the time at which each line has been written.
+/// The log is either read from the local pueue directory or has been sent by the daemon.
+///
+/// Returns `false`, if the task doesn't have a combined log.
+fn print_combined_log(
+    message: &TaskLogMessage,
+    colors: &Colors,
+    settings: &Settings,
+    lines: Option<usize>,
+    format: TimestampFormat,
+    stderr_only: bool,
+) -> bool {
+    let task = &message.task;
+    let pueue_directory = &settings.shared.pueue_directory;
+    // The daemon only sends logs, if they aren't read locally or if the task has been archived.
+    let combined_lines = if message.stdout.is_none() {
+        if !get_combined_log_path(task.id, pueue_directory).exists() {
+            return false;
+        }
+        match read_combined_log(task.id, pueue_directory, lines, stderr_only) {
+            Ok(combined_lines) => combined_lines,
+            Err(err) => {
+                println!("Failed reading combined log file: {}", err);
+                return true;
+            }
+        }
+    } else {
+        let bytes = match &message.combined {
+            Some(bytes) => bytes,
+            None => return false,
+        };
+        let mut decompressed = Vec::new();
+        if let Err(err) = FrameDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed) {
+            println!("Error while parsing combined log: {}", err);
+            return true;
+        }
+        String::from_utf8_lossy(&decompressed)
+            .lines()
+            .filter_map(parse_combined_line)
+            .collect()
+    };
+
+    if combined_lines.is_empty() {
+        return true;
+    }
+
+    println!(
+        "\n{}",
+        style_text("output:", Some(colors.green()), Some(Attribute::Bold))
+    );
+    for line in combined_lines {
+        let time = match format {
+            TimestampFormat::Absolute => line.time.format("%H:%M:%S%.3f").to_string(),
+            TimestampFormat::Relative => {
+                let start = task.start.unwrap_or(line.time);
+                let milliseconds = (line.time - start).num_milliseconds().max(0);
+                format!("{:>10}", format!("+{:.3}s", milliseconds as f64 / 1000.0))
+            }
+        };
+        let stream = match line.stream {
+            LogStream::Stdout => style_text("out", Some(colors.green()), None),
+            LogStream::Stderr => style_text("err", Some(colors.red()), None),
+        };
+        println!("{} {} {}", time, stream, line.text);
+    }
+
+    true
+}
+
 /// The daemon didn't send any log output, thereby we didn't request any.
 /// If that's the case, read the log files from the local pueue directory
-fn print_local_log(task_id: usize, colors: &Colors, settings: &Settings, lines: Option<usize>) {
+fn print_local_log(
+    task_id: usize,
+    colors: &Colors,
+    settings: &Settings,
+    lines: Option<usize>,
+    stderr_only: bool,
+) {
     let (mut stdout_file, mut stderr_file) =
         match get_log_file_handles(task_id, &settings.shared.pueue_directory) {
             Ok((stdout, stderr)) => (stdout, stderr),
@@ -196,41 +425,55 @@ fn print_local_log(task_id: usize, colors: &Colors, settings: &Settings, lines:
                 return;
             }
         };
+    let (out_path, err_path) = get_log_paths(task_id, &settings.shared.pueue_directory);
     // Stdout handler to directly write log file output to io::stdout
     // without having to load anything into memory.
     let mut stdout = io::stdout();
 
-    print_local_file(
-        &mut stdout,
-        &mut stdout_file,
-        &lines,
-        style_text("stdout:", Some(colors.green()), Some(Attribute::Bold)),
-    );
+    if !stderr_only {
+        print_local_file(
+            &mut stdout,
+            &mut stdout_file,
+            &out_path,
+            &lines,
+            style_text("stdout:", Some(colors.green()), Some(Attribute::Bold)),
+        );
+    }
 
     print_local_file(
         &mut stdout,
         &mut stderr_file,
+        &err_path,
         &lines,
         style_text("stderr:", Some(colors.red()), Some(Attribute::Bold)),
     );
 }
 
-/// Print a local log file.
+/// Print a local log file, including the rotated segments of its log.
 /// This is usually either the stdout or the stderr
-pub fn print_local_file(stdout: &mut Stdout, file: &mut File, lines: &Option<usize>, text: String) {
+pub fn print_local_file(
+    stdout: &mut Stdout,
+    file: &mut File,
+    path: &Path,
+    lines: &Option<usize>,
+    text: String,
+) {
     if let Ok(metadata) = file.metadata() {
-        if metadata.len() != 0 {
+        if metadata.len() != 0 || !get_rotated_segments(path).is_empty() {
             // Don't print a newline between the task information and the first output
             println!("\n{}", text);
 
             // Only print the last lines if requested
             if let Some(lines) = lines {
-                println!("{}", read_last_lines(file, *lines));
+                match read_last_log_lines(path, *lines) {
+                    Ok(output) => println!("{}", output),
+                    Err(err) => println!("Failed reading local log file: {}", err),
+                }
                 return;
             }
 
             // Print everything
-            if let Err(err) = io::copy(file, stdout) {
+            if let Err(err) = open_log(path).and_then(|mut log| io::copy(&mut log, stdout)) {
                 println!("Failed reading local log file: {}", err);
             };
         }
@@ -240,16 +483,16 @@ pub fn print_local_file(stdout: &mut Stdout, file: &mut File, lines: &Option<usi
 /// Prints log output received from the daemon.
 /// We can safely call .unwrap() on stdout and stderr in here, since this
 /// branch is always called after ensuring that both are `Some`.
-pub fn print_remote_log(task_log: &TaskLogMessage, colors: &Colors) {
+pub fn print_remote_log(task_log: &TaskLogMessage, colors: &Colors, stderr_only: bool) {
     // Save whether stdout was printed, so we can add a newline between outputs.
-    if !task_log.stdout.as_ref().unwrap().is_empty() {
-        if let Err(err) = print_remote_task_log(&task_log, colors, true) {
+    if !stderr_only && !task_log.stdout.as_ref().unwrap().is_empty() {
+        if let Err(err) = print_remote_task_log(task_log, colors, true) {
             println!("Error while parsing stdout: {}", err);
         }
     }
 
     if !task_log.stderr.as_ref().unwrap().is_empty() {
-        if let Err(err) = print_remote_task_log(&task_log, colors, false) {
+        if let Err(err) = print_remote_task_log(task_log, colors, false) {
             println!("Error while parsing stderr: {}", err);
         };
     }
@@ -2,16 +2,22 @@ pub mod colors;
 mod follow;
 mod group;
 pub mod helper;
+mod history;
 mod log;
+mod schedule;
 mod state;
+mod submit;
+mod top;
 
 use self::{colors::Colors, helper::style_text};
 
 // Re-exports
-pub use self::follow::follow_local_task_logs;
+pub use self::follow::{print_stream_end, print_task_stream};
 pub use self::group::print_groups;
+pub use self::history::print_history;
 pub use self::log::print_logs;
 pub use self::state::print_state;
+pub use self::submit::print_submitted_tasks;
 
 /// Used to style any generic success message from the daemon.
 pub fn print_success(_colors: &Colors, message: &str) {
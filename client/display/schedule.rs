@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+
+use comfy_table::presets::UTF8_HORIZONTAL_BORDERS_ONLY;
+use comfy_table::*;
+
+use pueue_lib::schedule::Schedule;
+use pueue_lib::settings::Settings;
+
+use super::{colors::Colors, helper::*};
+
+/// Print all schedules into a table, including the time they fire next.
+pub fn print_schedules(
+    schedules: &BTreeMap<usize, Schedule>,
+    colors: &Colors,
+    settings: &Settings,
+) {
+    if schedules.is_empty() {
+        println!(
+            "There are no schedules yet. Add them with `pueue schedule add \"[cron]\" -- [cmd]`"
+        );
+        return;
+    }
+
+    let has_labels = schedules
+        .iter()
+        .any(|(_id, schedule)| schedule.task.label.is_some());
+
+    let mut headers = vec![
+        Cell::new("Index"),
+        Cell::new("Schedule"),
+        Cell::new("Next run"),
+        Cell::new("Group"),
+    ];
+    if has_labels {
+        headers.push(Cell::new("Label"));
+    }
+    headers.append(&mut vec![
+        Cell::new("Last task"),
+        Cell::new("Command"),
+        Cell::new("Path"),
+    ]);
+
+    let mut table = Table::new();
+    table
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .load_preset(UTF8_HORIZONTAL_BORDERS_ONLY)
+        .set_header(headers);
+
+    for (id, schedule) in schedules {
+        let mut row = Row::new();
+        if let Some(height) = settings.client.max_status_lines {
+            row.max_height(height);
+        }
+        row.add_cell(Cell::new(id.to_string()));
+        row.add_cell(Cell::new(&schedule.expression));
+
+        if schedule.paused {
+            row.add_cell(Cell::new("paused").fg(colors.yellow()));
+        } else {
+            let next_run = schedule.next_run.format("%Y-%m-%d\n%H:%M:%S");
+            row.add_cell(Cell::new(next_run));
+        }
+        row.add_cell(Cell::new(&schedule.task.group));
+
+        if has_labels {
+            row.add_cell(Cell::new(schedule.task.label.as_deref().unwrap_or("")));
+        }
+
+        if let Some(task_id) = schedule.last_task_id {
+            row.add_cell(Cell::new(task_id.to_string()));
+        } else {
+            row.add_cell(Cell::new(""));
+        }
+
+        if settings.client.show_expanded_aliases {
+            row.add_cell(Cell::new(&schedule.task.command));
+        } else {
+            row.add_cell(Cell::new(&schedule.task.original_command));
+        }
+        row.add_cell(Cell::new(&schedule.task.path));
+
+        table.add_row(row);
+    }
+
+    println!("{}", style_text("Schedules", None, Some(Attribute::Bold)));
+    println!("{}", table);
+}
@@ -4,38 +4,76 @@ use std::string::ToString;
 use comfy_table::presets::UTF8_HORIZONTAL_BORDERS_ONLY;
 use comfy_table::*;
 
+use pueue_lib::schedule::Schedule;
 use pueue_lib::settings::Settings;
 use pueue_lib::state::State;
-use pueue_lib::task::{Task, TaskResult, TaskStatus};
+use pueue_lib::task::{DependencyCondition, Task, TaskResult, TaskStatus};
 
-use super::{colors::Colors, helper::*};
+use super::{colors::Colors, helper::*, schedule::print_schedules, top::print_top};
 use crate::cli::SubCommand;
 
 /// Print the current state of the daemon in a nicely formatted table.
-pub fn print_state(state: State, cli_command: &SubCommand, colors: &Colors, settings: &Settings) {
-    let (json, group_only) = match cli_command {
-        SubCommand::Status { json, group } => (*json, group.clone()),
+pub fn print_state(
+    mut state: State,
+    cli_command: &SubCommand,
+    colors: &Colors,
+    settings: &Settings,
+) {
+    let (json, group_only, array_only) = match cli_command {
+        SubCommand::Status { json, group, array } => (*json, group.clone(), *array),
+        SubCommand::Schedule { .. } => {
+            print_schedules(&state.schedules, colors, settings);
+            return;
+        }
+        SubCommand::Top { memory, group } => {
+            print_top(&state, *memory, group, settings);
+            return;
+        }
         _ => panic!(
             "Got wrong Subcommand {:?} in print_state. This shouldn't happen",
             cli_command
         ),
     };
 
+    // Only show the tasks of the requested task array.
+    if let Some(array_id) = array_only {
+        state
+            .tasks
+            .retain(|_, task| task.array_id == Some(array_id));
+        state.schedules.clear();
+    }
+
     // If the json flag is specified, print the state as json and exit.
     if json {
         println!("{}", serde_json::to_string(&state).unwrap());
         return;
     }
 
+    // Only show the schedules of the requested group.
+    let schedules: BTreeMap<usize, Schedule> = state
+        .schedules
+        .iter()
+        .filter(|(_, schedule)| match &group_only {
+            Some(group) => schedule.task.group.eq(group),
+            None => true,
+        })
+        .map(|(id, schedule)| (*id, schedule.clone()))
+        .collect();
+
     // Sort all tasks by their respective group;
     let sorted_tasks = sort_tasks_by_group(&state.tasks);
 
     if let Some(group) = group_only {
         print_single_group(state, settings, colors, sorted_tasks, group);
-        return;
+    } else {
+        print_all_groups(state, settings, colors, sorted_tasks);
     }
 
-    print_all_groups(state, settings, colors, sorted_tasks);
+    // Show the next run of all recurring tasks below the task tables.
+    if !schedules.is_empty() {
+        println!();
+        print_schedules(&schedules, colors, settings);
+    }
 }
 
 fn print_single_group(
@@ -47,12 +85,7 @@ fn print_single_group(
 ) {
     // Only a single group is requested. Print that group and return.
     let tasks = sorted_tasks.entry(group.clone()).or_default();
-    let headline = get_group_headline(
-        &group,
-        &state.groups.get(&group).unwrap(),
-        *state.settings.daemon.groups.get(&group).unwrap(),
-        colors,
-    );
+    let headline = get_headline(&group, &state, colors);
     println!("{}", headline);
 
     // Show a message if the requested group doesn't have any tasks.
@@ -63,7 +96,7 @@ fn print_single_group(
         );
         return;
     }
-    print_table(&tasks, colors, settings);
+    print_table(tasks, &state, colors, settings);
 }
 
 fn print_all_groups(
@@ -76,28 +109,18 @@ fn print_all_groups(
     // Print the state of the default group anyway, since this is information one wants to
     // see most of the time anyway.
     if state.tasks.is_empty() {
-        let headline = get_group_headline(
-            &"default",
-            &state.groups.get("default").unwrap(),
-            *state.settings.daemon.groups.get("default").unwrap(),
-            colors,
-        );
+        let headline = get_headline("default", &state, colors);
         println!("{}\n", headline);
         println!("Task list is empty. Add tasks with `pueue add -- [cmd]`");
         return;
     }
 
     // Always print the default queue at the very top, if no specific group is requested.
-    if sorted_tasks.get("default").is_some() {
+    if sorted_tasks.contains_key("default") {
         let tasks = sorted_tasks.get("default").unwrap();
-        let headline = get_group_headline(
-            &"default",
-            &state.groups.get("default").unwrap(),
-            *state.settings.daemon.groups.get("default").unwrap(),
-            colors,
-        );
+        let headline = get_headline("default", &state, colors);
         println!("{}", headline);
-        print_table(&tasks, colors, settings);
+        print_table(tasks, &state, colors, settings);
 
         // Add a newline if there are further groups to be printed
         if sorted_tasks.len() > 1 {
@@ -114,14 +137,9 @@ fn print_all_groups(
             continue;
         }
 
-        let headline = get_group_headline(
-            &group,
-            &state.groups.get(group).unwrap(),
-            *state.settings.daemon.groups.get(group).unwrap(),
-            colors,
-        );
+        let headline = get_headline(group, &state, colors);
         println!("{}", headline);
-        print_table(&tasks, colors, settings);
+        print_table(tasks, &state, colors, settings);
 
         // Add a newline between groups
         if sorted_iter.peek().is_some() {
@@ -130,25 +148,68 @@ fn print_all_groups(
     }
 }
 
+/// Get the headline of a group.
+/// If new tasks of the group are held back due to its load limits, the reason is shown as well.
+fn get_headline(group: &str, state: &State, colors: &Colors) -> String {
+    let mut headline = get_group_headline(
+        group,
+        state.groups.get(group).unwrap(),
+        *state.settings.daemon.groups.get(group).unwrap(),
+        colors,
+    );
+    if let Some(reason) = state.held_back_groups.get(group) {
+        let reason = format!(" (held back: {})", reason);
+        headline.push_str(&style_text(reason, Some(colors.yellow()), None));
+    }
+
+    headline
+}
+
 /// Print some tasks into a nicely formatted table
-fn print_table(tasks: &BTreeMap<usize, Task>, colors: &Colors, settings: &Settings) {
-    let (has_delayed_tasks, has_dependencies, has_labels) = has_special_columns(tasks);
+fn print_table(tasks: &BTreeMap<usize, Task>, state: &State, colors: &Colors, settings: &Settings) {
+    let columns = has_special_columns(tasks);
+    let used_resources = state.used_resources();
 
     // Create table header row
     let mut headers = vec![Cell::new("Index"), Cell::new("Status")];
-    if has_delayed_tasks {
+    if columns.arrays {
+        headers.push(Cell::new("Array"));
+    }
+    if columns.priorities {
+        headers.push(Cell::new("Prio"));
+    }
+    if columns.delayed_tasks {
         headers.push(Cell::new("Enqueue At"));
     }
-    if has_dependencies {
+    if columns.dependencies {
         headers.push(Cell::new("Deps"));
     }
+    if columns.resources {
+        headers.push(Cell::new("Resources"));
+    }
+    if columns.slots {
+        headers.push(Cell::new("Slots"));
+    }
 
     headers.push(Cell::new("Exitcode"));
 
-    if has_labels {
+    if columns.labels {
         headers.push(Cell::new("Label"));
     }
 
+    if columns.timeouts {
+        headers.push(Cell::new("Timeout"));
+    }
+
+    if columns.retries {
+        headers.push(Cell::new("Retries"));
+    }
+
+    if columns.usage {
+        headers.push(Cell::new("CPU"));
+        headers.push(Cell::new("Memory"));
+    }
+
     headers.append(&mut vec![
         Cell::new("Command"),
         Cell::new("Path"),
@@ -169,7 +230,7 @@ fn print_table(tasks: &BTreeMap<usize, Task>, colors: &Colors, settings: &Settin
         if let Some(height) = settings.client.max_status_lines {
             row.max_height(height);
         }
-        row.add_cell(Cell::new(&id.to_string()));
+        row.add_cell(Cell::new(id.to_string()));
 
         // Determine the human readable task status representation and the respective color.
         let status_string = task.status.to_string();
@@ -182,6 +243,11 @@ fn print_table(tasks: &BTreeMap<usize, Task>, colors: &Colors, settings: &Settin
                     ("Dependency failed".to_string(), colors.red())
                 }
                 Some(TaskResult::FailedToSpawn(_)) => ("Failed to spawn".to_string(), colors.red()),
+                Some(TaskResult::TimedOut) => ("Timed out".to_string(), colors.red()),
+                Some(TaskResult::OutOfMemory) => ("Out of memory".to_string(), colors.red()),
+                Some(TaskResult::Signaled(signal)) => {
+                    (format!("Killed ({})", signal), colors.red())
+                }
                 Some(result) => (result.to_string(), colors.red()),
                 None => panic!("Got a 'Done' task without a task result. Please report this bug."),
             },
@@ -189,7 +255,19 @@ fn print_table(tasks: &BTreeMap<usize, Task>, colors: &Colors, settings: &Settin
         };
         row.add_cell(Cell::new(status_text).fg(color));
 
-        if has_delayed_tasks {
+        if columns.arrays {
+            if let Some(array_id) = task.array_id {
+                row.add_cell(Cell::new(array_id.to_string()));
+            } else {
+                row.add_cell(Cell::new(""));
+            }
+        }
+
+        if columns.priorities {
+            row.add_cell(Cell::new(task.priority.to_string()));
+        }
+
+        if columns.delayed_tasks {
             if let Some(enqueue_at) = task.enqueue_at {
                 row.add_cell(Cell::new(enqueue_at.format("%Y-%m-%d\n%H:%M:%S")));
             } else {
@@ -197,25 +275,63 @@ fn print_table(tasks: &BTreeMap<usize, Task>, colors: &Colors, settings: &Settin
             }
         }
 
-        if has_dependencies {
+        if columns.dependencies {
             let text = task
                 .dependencies
                 .iter()
-                .map(|id| id.to_string())
+                .map(|id| match task.dependency_condition(*id) {
+                    DependencyCondition::Success => id.to_string(),
+                    DependencyCondition::Failure => format!("{} (failure)", id),
+                    DependencyCondition::Any => format!("{} (any)", id),
+                })
                 .collect::<Vec<String>>()
                 .join(", ");
             row.add_cell(Cell::new(text));
         }
 
+        // Show the resources of the task and point out, which ones a queued task is waiting for.
+        if columns.resources {
+            let blocking = if task.status == TaskStatus::Queued {
+                state.blocking_resources(task, &used_resources)
+            } else {
+                Vec::new()
+            };
+            let text = task
+                .resources
+                .iter()
+                .map(|(resource, amount)| {
+                    let mut text = resource.clone();
+                    if *amount > 1 {
+                        text.push_str(&format!(":{}", amount));
+                    }
+                    if blocking.contains(resource) {
+                        text.push_str(" (blocked)");
+                    }
+                    text
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+
+            if blocking.is_empty() {
+                row.add_cell(Cell::new(text));
+            } else {
+                row.add_cell(Cell::new(text).fg(colors.yellow()));
+            }
+        }
+
+        if columns.slots {
+            row.add_cell(Cell::new(task.slots.to_string()));
+        }
+
         // Match the color of the exit code.
         // If the exit_code is none, it has been killed by the task handler.
         let exit_code_cell = match task.result {
             Some(TaskResult::Success) => Cell::new("0").fg(colors.green()),
-            Some(TaskResult::Failed(code)) => Cell::new(&code.to_string()).fg(colors.red()),
+            Some(TaskResult::Failed(code)) => Cell::new(code.to_string()).fg(colors.red()),
             _ => Cell::new(""),
         };
         row.add_cell(exit_code_cell);
-        if has_labels {
+        if columns.labels {
             if let Some(label) = &task.label {
                 row.add_cell(label.to_cell());
             } else {
@@ -223,6 +339,35 @@ fn print_table(tasks: &BTreeMap<usize, Task>, colors: &Colors, settings: &Settin
             }
         }
 
+        if columns.timeouts {
+            if let Some(timeout) = task.timeout {
+                row.add_cell(Cell::new(format_duration(timeout)));
+            } else {
+                row.add_cell(Cell::new(""));
+            }
+        }
+
+        // Show how many of the task's retries have already been used up.
+        if columns.retries {
+            if task.retries > 0 {
+                let text = format!("{}/{}", task.attempts.len(), task.retries);
+                row.add_cell(Cell::new(text));
+            } else {
+                row.add_cell(Cell::new(""));
+            }
+        }
+
+        // Show the current resource usage of running tasks.
+        if columns.usage {
+            if let Some(usage) = &task.current_usage {
+                row.add_cell(Cell::new(format!("{:.0}%", usage.cpu)));
+                row.add_cell(Cell::new(format_size(usage.memory)));
+            } else {
+                row.add_cell(Cell::new(""));
+                row.add_cell(Cell::new(""));
+            }
+        }
+
         // Add command and path.
         if settings.client.show_expanded_aliases {
             row.add_cell(Cell::new(&task.command));
@@ -0,0 +1,28 @@
+use std::collections::BTreeMap;
+
+use crate::cli::SubCommand;
+
+/// Print the ids of all tasks of a submitted workflow by their step name.
+pub fn print_submitted_tasks(task_ids: BTreeMap<String, usize>, cli_command: &SubCommand) {
+    let json = match cli_command {
+        SubCommand::Submit { json, .. } => *json,
+        _ => panic!(
+            "Got wrong Subcommand {:?} in print_submitted_tasks. This shouldn't happen",
+            cli_command
+        ),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string(&task_ids).unwrap());
+        return;
+    }
+
+    // Print the steps in the order of their task ids.
+    let mut steps: Vec<(String, usize)> = task_ids.into_iter().collect();
+    steps.sort_by_key(|(_, id)| *id);
+
+    println!("New tasks added:");
+    for (name, id) in steps {
+        println!("  {}: {}", name, id);
+    }
+}
@@ -0,0 +1,83 @@
+use std::cmp::Reverse;
+
+use chrono::prelude::*;
+use comfy_table::presets::UTF8_HORIZONTAL_BORDERS_ONLY;
+use comfy_table::*;
+
+use pueue_lib::settings::Settings;
+use pueue_lib::state::State;
+use pueue_lib::task::Task;
+
+use super::helper::*;
+
+/// Print all running tasks sorted by their current CPU or memory usage.
+pub fn print_top(state: &State, sort_by_memory: bool, group: &Option<String>, settings: &Settings) {
+    let mut tasks: Vec<&Task> = state
+        .tasks
+        .values()
+        .filter(|task| task.is_running())
+        .filter(|task| match group {
+            Some(group) => task.group.eq(group),
+            None => true,
+        })
+        .collect();
+
+    if tasks.is_empty() {
+        println!("There are no running tasks.");
+        return;
+    }
+
+    // Tasks that haven't been sampled yet are shown last.
+    let usage = |task: &Task| task.current_usage.clone().unwrap_or_default();
+    if sort_by_memory {
+        tasks.sort_by_key(|task| Reverse(usage(task).memory));
+    } else {
+        tasks.sort_by(|a, b| usage(b).cpu.partial_cmp(&usage(a).cpu).unwrap());
+    }
+
+    let mut table = Table::new();
+    table
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .load_preset(UTF8_HORIZONTAL_BORDERS_ONLY)
+        .set_header(vec![
+            Cell::new("Index"),
+            Cell::new("Group"),
+            Cell::new("CPU"),
+            Cell::new("Memory"),
+            Cell::new("Runtime"),
+            Cell::new("Command"),
+        ]);
+
+    for task in tasks {
+        let mut row = Row::new();
+        if let Some(height) = settings.client.max_status_lines {
+            row.max_height(height);
+        }
+        row.add_cell(Cell::new(task.id.to_string()));
+        row.add_cell(Cell::new(&task.group));
+
+        if let Some(usage) = &task.current_usage {
+            row.add_cell(Cell::new(format!("{:.0}%", usage.cpu)));
+            row.add_cell(Cell::new(format_size(usage.memory)));
+        } else {
+            row.add_cell(Cell::new(""));
+            row.add_cell(Cell::new(""));
+        }
+
+        let runtime = task
+            .start
+            .map(|start| (Local::now() - start).num_seconds().max(0) as u64)
+            .unwrap_or(0);
+        row.add_cell(Cell::new(format_duration(runtime)));
+
+        if settings.client.show_expanded_aliases {
+            row.add_cell(Cell::new(&task.command));
+        } else {
+            row.add_cell(Cell::new(&task.original_command));
+        }
+
+        table.add_row(row);
+    }
+
+    println!("{}", table);
+}
@@ -17,11 +17,14 @@ use pueue_lib::state::State;
 
 use crate::cli::CliArguments;
 use crate::network::socket::accept_incoming;
+use crate::platform::cgroup::init_cgroup;
 use crate::task_handler::TaskHandler;
 
 mod cli;
 mod network;
+mod output;
 mod platform;
+mod schedule;
 mod task_handler;
 
 #[async_std::main]
@@ -66,6 +69,9 @@ async fn main() -> Result<()> {
         create_certificates(&settings)?;
     }
     init_shared_secret(&settings.shared.shared_secret_path)?;
+    if let Some(cgroup) = &settings.daemon.cgroup {
+        init_cgroup(cgroup)?;
+    }
 
     let mut state = State::new(&settings, opt.config.clone());
     // Restore the previous state and save any changes that might have happened during this process
@@ -112,7 +118,7 @@ async fn main() -> Result<()> {
 fn init_directories(pueue_dir: &Path) {
     // Pueue base path
     if !pueue_dir.exists() {
-        if let Err(error) = create_dir_all(&pueue_dir) {
+        if let Err(error) = create_dir_all(pueue_dir) {
             panic!(
                 "Failed to create main directory at {:?} error: {:?}",
                 pueue_dir, error
@@ -0,0 +1,252 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use pueue_lib::log::*;
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::{send_message, try_receive_message, GenericStream};
+use pueue_lib::state::SharedState;
+use pueue_lib::task::TaskStatus;
+
+use crate::network::follow_log::take_complete_text;
+use crate::network::message_handler::SENDER_ERR;
+
+/// How many bytes of previous output are shown, when attaching to a task.
+const CONTEXT_BYTES: u64 = 2048;
+
+/// How long the daemon waits for input of the client, before it checks for new output.
+const OUTPUT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Handle an attached client.
+///
+/// Input of the client is forwarded to the task, as soon as it arrives.
+/// Meanwhile, any new output of the task is sent to the client.
+/// This goes on until the client detaches or the task finishes.
+pub async fn handle_attach(
+    pueue_directory: &Path,
+    stream: &mut GenericStream,
+    state: &SharedState,
+    sender: &Sender<Message>,
+    task_id: usize,
+) -> Result<Message> {
+    let pty = {
+        let state = state.lock().unwrap();
+        match state.tasks.get(&task_id) {
+            Some(task) if task.status == TaskStatus::Running => task.pty,
+            Some(_) => {
+                return Ok(create_failure_message(
+                    "You can only attach to a running task",
+                ))
+            }
+            None => return Ok(create_failure_message("No task with this id.")),
+        }
+    };
+
+    let (out_path, err_path) = get_log_paths(task_id, pueue_directory);
+    let (mut stdout, mut stderr) = match get_log_file_handles(task_id, pueue_directory) {
+        Ok(handles) => handles,
+        Err(_) => {
+            return Ok(create_failure_message(
+                "Couldn't find output files for task. Maybe it finished? Try `log`",
+            ))
+        }
+    };
+
+    // Start with the last few lines of the task's output, so the user sees e.g. a pending prompt.
+    // Only complete lines are shown, unless the output is short enough to be shown completely.
+    let length = stdout.metadata()?.len();
+    let start = length.saturating_sub(CONTEXT_BYTES);
+    stdout.seek(SeekFrom::Start(start))?;
+    stderr.seek(SeekFrom::End(0))?;
+    let mut out_buffer = Vec::new();
+    let mut err_buffer = Vec::new();
+    stdout.read_to_end(&mut out_buffer)?;
+    let mut stdout = LogTail::new(out_path, stdout);
+    let mut stderr = LogTail::new(err_path, stderr);
+    if start > 0 {
+        let line_start = out_buffer
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(0, |position| position + 1);
+        out_buffer.drain(..line_start);
+    }
+    let context = take_complete_text(&mut out_buffer);
+    send_message(Message::Stream(context), stream).await?;
+
+    loop {
+        match try_receive_message(stream, OUTPUT_INTERVAL).await? {
+            Some(Message::AttachInput(message)) => {
+                let mut input = message.input;
+                // Terminals send a carriage return on enter.
+                // Tasks without a pseudo terminal expect a newline instead.
+                if !pty {
+                    for byte in input.iter_mut().filter(|byte| **byte == b'\r') {
+                        *byte = b'\n';
+                    }
+                }
+                // Input is only ever sent to the task, the client is attached to.
+                let message = AttachInputMessage { task_id, input };
+                sender
+                    .send(Message::AttachInput(message))
+                    .expect(SENDER_ERR);
+            }
+            Some(Message::Detach) => {
+                return Ok(create_success_message(format!(
+                    "Detached from task {}",
+                    task_id
+                )))
+            }
+            Some(_) => {
+                return Ok(create_failure_message(
+                    "Expected input of an attached client",
+                ))
+            }
+            None => (),
+        }
+
+        // Check whether the task finished, before its remaining output is read.
+        let finished = {
+            let state = state.lock().unwrap();
+            match state.tasks.get(&task_id) {
+                Some(task) => !task.is_running(),
+                None => true,
+            }
+        };
+
+        stdout.read_to_end(&mut out_buffer)?;
+        stderr.read_to_end(&mut err_buffer)?;
+        let text = take_complete_text(&mut out_buffer) + &take_complete_text(&mut err_buffer);
+        if finished && text.is_empty() {
+            return Ok(create_success_message(format!("Task {} finished", task_id)));
+        }
+
+        if !text.is_empty() {
+            send_message(Message::Stream(text), stream).await?;
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs::{create_dir, File};
+    use std::io::Write;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+
+    use async_std::os::unix::net::UnixStream;
+    use async_std::task;
+    use tempfile::{tempdir, TempDir};
+
+    use pueue_lib::network::protocol::receive_message;
+    use pueue_lib::settings::Settings;
+    use pueue_lib::state::State;
+    use pueue_lib::task::Task;
+
+    use super::*;
+
+    /// Create a state with a single running task, whose stdout log already contains a line.
+    fn get_running_task() -> (TempDir, SharedState, File) {
+        let dir = tempdir().unwrap();
+        create_dir(dir.path().join("task_logs")).unwrap();
+        let (mut stdout, _) = create_log_file_handles(0, dir.path()).unwrap();
+        stdout.write_all(b"hello\n").unwrap();
+
+        let settings: Settings = Settings::default_config()
+            .expect("Failed to get default config")
+            .try_into()
+            .expect("Failed to get test settings");
+        let mut state = State::new(&settings, None);
+        state.insert_task(Task::new(
+            "cat".into(),
+            "/tmp".into(),
+            HashMap::new(),
+            "default".into(),
+            TaskStatus::Running,
+            None,
+            Vec::new(),
+            None,
+        ));
+
+        (dir, Arc::new(Mutex::new(state)), stdout)
+    }
+
+    /// Create a connected pair of streams for the client and the daemon.
+    fn get_streams() -> (GenericStream, GenericStream) {
+        let (client, daemon) = UnixStream::pair().unwrap();
+        (Box::new(client), Box::new(daemon))
+    }
+
+    async fn receive_output(stream: &mut GenericStream) -> String {
+        match receive_message(stream).await.unwrap() {
+            Message::Stream(output) => output,
+            message => panic!("Received unexpected message {:?}", message),
+        }
+    }
+
+    #[async_std::test]
+    /// Raw input is forwarded to the attached task,
+    /// while new output is sent without waiting for any input of the client.
+    async fn test_attach_input_and_output() {
+        let (dir, state, mut stdout) = get_running_task();
+        let (sender, receiver) = channel();
+        let (mut client, mut daemon) = get_streams();
+
+        let client = task::spawn(async move {
+            assert_eq!(receive_output(&mut client).await, "hello\n");
+
+            // Input is always sent to the attached task.
+            let input = AttachInputMessage {
+                task_id: 3,
+                input: vec![b'y', b'\r', 0xff],
+            };
+            send_message(Message::AttachInput(input), &mut client)
+                .await
+                .unwrap();
+
+            stdout.write_all(b"world\n").unwrap();
+            assert_eq!(receive_output(&mut client).await, "world\n");
+            send_message(Message::Detach, &mut client).await.unwrap();
+        });
+
+        let response = handle_attach(dir.path(), &mut daemon, &state, &sender, 0)
+            .await
+            .unwrap();
+        client.await;
+        assert!(matches!(response, Message::Success(text) if text == "Detached from task 0"));
+
+        match receiver.try_recv().unwrap() {
+            Message::AttachInput(message) => {
+                assert_eq!(message.task_id, 0);
+                assert_eq!(message.input, vec![b'y', b'\n', 0xff]);
+            }
+            message => panic!("Received unexpected message {:?}", message),
+        }
+    }
+
+    #[async_std::test]
+    /// The daemon ends the attachment by itself, once the task finished.
+    async fn test_attach_until_finished() {
+        let (dir, state, _stdout) = get_running_task();
+        let (sender, _receiver) = channel();
+        let (mut client, mut daemon) = get_streams();
+
+        let client_state = state.clone();
+        let client = task::spawn(async move {
+            assert_eq!(receive_output(&mut client).await, "hello\n");
+            let mut state = client_state.lock().unwrap();
+            state.tasks.get_mut(&0).unwrap().status = TaskStatus::Done;
+            // The client stays connected, until the daemon ended the attachment.
+            client
+        });
+
+        let response = handle_attach(dir.path(), &mut daemon, &state, &sender, 0)
+            .await
+            .unwrap();
+        let _client = client.await;
+        assert!(matches!(response, Message::Success(text) if text == "Task 0 finished"));
+    }
+}
@@ -1,14 +1,26 @@
-use std::io::Read;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Read};
+use std::path::Path;
 use std::time::Duration;
-use std::{fs::File, path::Path};
 
 use anyhow::Result;
 use async_std::task::sleep;
+use log::debug;
 
 use pueue_lib::log::*;
 use pueue_lib::network::message::*;
 use pueue_lib::network::protocol::{send_message, GenericStream};
-use pueue_lib::state::SharedState;
+use pueue_lib::state::{SharedState, State};
+use pueue_lib::task::{TaskResult, TaskStatus};
+
+use crate::network::response_helper::ensure_group_exists;
+use crate::platform::file_watcher::FileWatcher;
+
+/// How long we wait for changes of a watched log, before checking whether the task finished.
+const WATCH_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How often a log is checked for new output, if it can't be watched.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
 
 /// Handle the continuous stream of a message.
 pub async fn handle_follow(
@@ -17,10 +29,14 @@ pub async fn handle_follow(
     state: &SharedState,
     message: StreamRequestMessage,
 ) -> Result<Message> {
+    if message.task_ids.len() > 1 || message.group.is_some() {
+        return follow_several_tasks(pueue_directory, stream, state, message).await;
+    }
+
     // The user can specify the id of the task they want to follow
     // If the id isn't specified and there's only a single running task, this task will be used.
     // However, if there are multiple running tasks, the user will have to specify an id.
-    let task_id = if let Some(task_id) = message.task_id {
+    let task_id = if let Some(task_id) = message.task_ids.first().cloned() {
         task_id
     } else {
         // Get all ids of running tasks
@@ -51,47 +67,395 @@ pub async fn handle_follow(
         }
     };
 
+    // Get the stdout/stderr path.
+    // We need to check continuously, whether the file still exists,
+    // since the file can go away (e.g. due to finishing a task).
+    let (out_path, err_path) = get_log_paths(task_id, pueue_directory);
+    let handle_path = if message.err { err_path } else { out_path };
+
     // The client requested streaming of stdout.
-    let mut handle: File;
-    match get_log_file_handles(task_id, pueue_directory) {
+    let mut handle = match get_log_file_handles(task_id, pueue_directory) {
         Err(_) => {
             return Ok(create_failure_message(
                 "Couldn't find output files for task. Maybe it finished? Try `log`",
             ))
         }
         Ok((stdout_handle, stderr_handle)) => {
-            handle = if message.err {
+            let handle = if message.err {
                 stderr_handle
             } else {
                 stdout_handle
             };
+            LogTail::new(handle_path.clone(), handle)
         }
-    }
+    };
 
-    // Get the stdout/stderr path.
-    // We need to check continuously, whether the file still exists,
-    // since the file can go away (e.g. due to finishing a task).
-    let (out_path, err_path) = get_log_paths(task_id, pueue_directory);
-    let handle_path = if message.err { err_path } else { out_path };
+    // Get notified about new output as soon as it's written.
+    // If that isn't possible, the file is checked for new output in a fixed interval.
+    let mut watcher = match FileWatcher::new(&handle_path) {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            debug!("Polling log of task {}: {:#}", task_id, err);
+            None
+        }
+    };
 
+    let mut buffer = Vec::new();
     loop {
-        // Check whether the file still exists. Exit if it doesn't.
-        if !handle_path.exists() {
-            return Ok(create_success_message(
-                "File has gone away. Did somebody remove the task?",
-            ));
-        }
-        // Read the next chunk of text from the last position.
-        let mut buffer = Vec::new();
+        // Check whether the task finished, before its remaining output is read.
+        // The stream also ends, if the task or its log have been removed in the meantime.
+        let end = {
+            let state = state.lock().unwrap();
+            match state.tasks.get(&task_id) {
+                Some(task) if task.status == TaskStatus::Done => Some(task.result.clone()),
+                Some(_) if handle_path.exists() => None,
+                _ => Some(None),
+            }
+        };
 
+        // Read the next chunk of text from the last position.
         if let Err(err) = handle.read_to_end(&mut buffer) {
             return Ok(create_failure_message(format!("Error: {}", err)));
         };
-        let text = String::from_utf8_lossy(&buffer).to_string();
 
-        // Send the new chunk and wait for 1 second.
-        let response = Message::Stream(text);
-        send_message(response, stream).await?;
-        sleep(Duration::from_millis(1000)).await;
+        let text = take_complete_text(&mut buffer);
+        if !text.is_empty() {
+            send_message(Message::Stream(text), stream).await?;
+        }
+
+        // A rotated log continues in a new file, which needs to be watched instead.
+        if handle.was_rotated() && watcher.is_some() {
+            watcher = FileWatcher::new(&handle_path).ok();
+        }
+
+        if let Some(result) = end {
+            return Ok(Message::StreamEnd(StreamEndMessage { task_id, result }));
+        }
+
+        match &watcher {
+            Some(watcher) => watcher.wait(WATCH_TIMEOUT).await,
+            None => sleep(POLL_INTERVAL).await,
+        }
+    }
+}
+
+/// The log of one of several followed tasks.
+struct FollowedLog {
+    handle: LogTail,
+    /// Output, whose line hasn't been completed yet.
+    buffer: Vec<u8>,
+}
+
+impl FollowedLog {
+    /// Read all new output and take its complete lines.
+    fn read_lines(&mut self) -> io::Result<String> {
+        self.handle.read_to_end(&mut self.buffer)?;
+        let end = match self.buffer.iter().rposition(|byte| *byte == b'\n') {
+            Some(position) => position + 1,
+            None => return Ok(String::new()),
+        };
+        let incomplete = self.buffer.split_off(end);
+        let text = String::from_utf8_lossy(&self.buffer).to_string();
+        self.buffer = incomplete;
+
+        Ok(text)
+    }
+
+    /// Read all remaining output of a finished task, including an incomplete last line.
+    fn read_rest(&mut self) -> io::Result<String> {
+        let mut text = self.read_lines()?;
+        if !self.buffer.is_empty() {
+            text.push_str(&String::from_utf8_lossy(&self.buffer));
+            text.push('\n');
+            self.buffer.clear();
+        }
+
+        Ok(text)
+    }
+}
+
+/// Follow the output of several tasks or of a whole group at once.
+///
+/// The output is sent line by line, together with the id of its task.
+/// Tasks that haven't been started yet are followed as soon as they start.
+/// When following a group, this includes tasks that are added to the group in the meantime.
+/// Each task's output ends with its result and the stream ends, once all tasks finished.
+async fn follow_several_tasks(
+    pueue_directory: &Path,
+    stream: &mut GenericStream,
+    state: &SharedState,
+    message: StreamRequestMessage,
+) -> Result<Message> {
+    // Tasks of a group, which already finished before we started following, are skipped.
+    let mut ended: BTreeSet<usize> = BTreeSet::new();
+    {
+        let state = state.lock().unwrap();
+        if let Some(group) = &message.group {
+            if let Err(message) = ensure_group_exists(&state, group) {
+                return Ok(message);
+            }
+            ended = get_finished_group_tasks(&state, group);
+        }
+        let not_found: Vec<_> = message
+            .task_ids
+            .iter()
+            .filter(|id| !state.tasks.contains_key(id))
+            .collect();
+        if !not_found.is_empty() {
+            return Ok(create_failure_message(format!(
+                "Couldn't find tasks: {:?}",
+                not_found
+            )));
+        }
+    }
+
+    // Watch the whole log directory, which also tells us when the logs of new tasks are created.
+    let watcher = match FileWatcher::new(&pueue_directory.join("task_logs")) {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            debug!("Polling logs of followed tasks: {:#}", err);
+            None
+        }
+    };
+
+    let mut followed: BTreeMap<usize, FollowedLog> = BTreeMap::new();
+    let mut first_run = true;
+    loop {
+        let selected = {
+            let state = state.lock().unwrap();
+            get_selected_tasks(&state, &message, &ended)
+        };
+
+        if selected.is_empty() {
+            return Ok(match &message.group {
+                Some(group) if first_run => create_failure_message(format!(
+                    "There are no unfinished tasks in group {}",
+                    group
+                )),
+                Some(group) => {
+                    create_success_message(format!("All tasks of group {} finished", group))
+                }
+                None => create_success_message("All followed tasks finished"),
+            });
+        }
+        first_run = false;
+
+        for (task_id, started, end) in selected {
+            // Start following the log of a task, as soon as it has been started.
+            if started && !followed.contains_key(&task_id) {
+                if let Ok((stdout, stderr)) = get_log_file_handles(task_id, pueue_directory) {
+                    let (out_path, err_path) = get_log_paths(task_id, pueue_directory);
+                    let handle = if message.err {
+                        LogTail::new(err_path, stderr)
+                    } else {
+                        LogTail::new(out_path, stdout)
+                    };
+                    let log = FollowedLog {
+                        handle,
+                        buffer: Vec::new(),
+                    };
+                    followed.insert(task_id, log);
+                }
+            }
+
+            if let Some(log) = followed.get_mut(&task_id) {
+                let text = match end {
+                    Some(_) => log.read_rest(),
+                    None => log.read_lines(),
+                };
+                let text = match text {
+                    Ok(text) => text,
+                    Err(err) => return Ok(create_failure_message(format!("Error: {}", err))),
+                };
+                if !text.is_empty() {
+                    let message = Message::TaskStream(TaskStreamMessage { task_id, text });
+                    send_message(message, stream).await?;
+                }
+            }
+
+            if let Some(result) = end {
+                followed.remove(&task_id);
+                ended.insert(task_id);
+                let message = Message::StreamEnd(StreamEndMessage { task_id, result });
+                send_message(message, stream).await?;
+            }
+        }
+
+        match &watcher {
+            Some(watcher) => watcher.wait(WATCH_TIMEOUT).await,
+            None => sleep(POLL_INTERVAL).await,
+        }
+    }
+}
+
+/// A followed task, whether it has been started and its result, once its output ended.
+/// Tasks that have been removed in the meantime end without a result.
+type SelectedTask = (usize, bool, Option<Option<TaskResult>>);
+
+/// Return the ids of all tasks of a group, which already finished.
+fn get_finished_group_tasks(state: &State, group: &str) -> BTreeSet<usize> {
+    state
+        .tasks
+        .iter()
+        .filter(|(_, task)| task.group == group && task.status == TaskStatus::Done)
+        .map(|(id, _)| *id)
+        .collect()
+}
+
+/// Get all selected tasks, whose output hasn't ended yet.
+/// When following a group, this includes all tasks that have been added to it in the meantime.
+fn get_selected_tasks(
+    state: &State,
+    message: &StreamRequestMessage,
+    ended: &BTreeSet<usize>,
+) -> Vec<SelectedTask> {
+    let task_ids: Vec<usize> = match &message.group {
+        Some(group) => state
+            .tasks
+            .iter()
+            .filter(|(_, task)| &task.group == group)
+            .map(|(id, _)| *id)
+            .collect(),
+        None => message.task_ids.clone(),
+    };
+
+    task_ids
+        .into_iter()
+        .filter(|id| !ended.contains(id))
+        .map(|id| match state.tasks.get(&id) {
+            Some(task) if task.status == TaskStatus::Done => (id, true, Some(task.result.clone())),
+            Some(task) => (id, task.start.is_some(), None),
+            None => (id, false, Some(None)),
+        })
+        .collect()
+}
+
+/// Take all complete characters from the buffer.
+/// A multi-byte character, which has only been written partially so far, is kept in the buffer
+/// and sent together with the next chunk of output.
+pub fn take_complete_text(buffer: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(buffer) {
+        Err(err) if err.error_len().is_none() => err.valid_up_to(),
+        _ => buffer.len(),
+    };
+    let incomplete = buffer.split_off(complete);
+    let text = String::from_utf8_lossy(buffer).to_string();
+    *buffer = incomplete;
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pueue_lib::settings::Settings;
+    use pueue_lib::task::Task;
+
+    use super::*;
+
+    fn get_state() -> State {
+        let settings: Settings = Settings::default_config()
+            .expect("Failed to get default config")
+            .try_into()
+            .expect("Failed to get test settings");
+        let mut state = State::new(&settings, None);
+        for status in [TaskStatus::Done, TaskStatus::Queued].iter() {
+            let task = Task::new(
+                "ls".into(),
+                "/tmp".into(),
+                HashMap::new(),
+                "default".into(),
+                status.clone(),
+                None,
+                Vec::new(),
+                None,
+            );
+            state.tasks.insert(state.tasks.len(), task);
+        }
+
+        state
+    }
+
+    #[test]
+    /// Tasks of a group, which finished before following started, are never followed.
+    fn follow_group_skips_finished_tasks() {
+        let mut state = get_state();
+        let message = StreamRequestMessage {
+            task_ids: Vec::new(),
+            group: Some("default".into()),
+            err: false,
+        };
+        let mut ended = get_finished_group_tasks(&state, "default");
+        assert_eq!(ended, vec![0].into_iter().collect());
+
+        // The queued task finishes and its output ends.
+        let selected = get_selected_tasks(&state, &message, &ended);
+        assert_eq!(selected, vec![(1, false, None)]);
+        state.tasks.get_mut(&1).unwrap().status = TaskStatus::Done;
+        let selected = get_selected_tasks(&state, &message, &ended);
+        assert_eq!(selected, vec![(1, true, Some(None))]);
+        ended.insert(1);
+
+        assert!(get_selected_tasks(&state, &message, &ended).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[async_std::test]
+    /// A single task is followed until it finished and the stream ends with its result.
+    /// This is what `pueue follow <id>` does with the default settings.
+    async fn follow_single_task_until_finished() {
+        use std::fs::create_dir;
+        use std::io::Write;
+        use std::sync::{Arc, Mutex};
+
+        use async_std::os::unix::net::UnixStream;
+        use async_std::task;
+        use tempfile::tempdir;
+
+        use pueue_lib::network::protocol::receive_message;
+
+        let dir = tempdir().unwrap();
+        create_dir(dir.path().join("task_logs")).unwrap();
+        let (mut stdout, _) = create_log_file_handles(1, dir.path()).unwrap();
+        stdout.write_all(b"hello\n").unwrap();
+
+        let mut state = get_state();
+        state.tasks.get_mut(&1).unwrap().status = TaskStatus::Running;
+        let state = Arc::new(Mutex::new(state));
+        let (client, daemon) = UnixStream::pair().unwrap();
+        let mut client: GenericStream = Box::new(client);
+        let mut daemon: GenericStream = Box::new(daemon);
+
+        let client_state = state.clone();
+        let client = task::spawn(async move {
+            match receive_message(&mut client).await.unwrap() {
+                Message::Stream(output) => assert_eq!(output, "hello\n"),
+                message => panic!("Received unexpected message {:?}", message),
+            }
+            let mut state = client_state.lock().unwrap();
+            let task = state.tasks.get_mut(&1).unwrap();
+            task.status = TaskStatus::Done;
+            task.result = Some(TaskResult::Success);
+            // The client stays connected, until the daemon ended the stream.
+            client
+        });
+
+        let message = StreamRequestMessage {
+            task_ids: vec![1],
+            group: None,
+            err: false,
+        };
+        let response = handle_follow(dir.path(), &mut daemon, &state, message)
+            .await
+            .unwrap();
+        let _client = client.await;
+        assert!(matches!(
+            response,
+            Message::StreamEnd(StreamEndMessage {
+                task_id: 1,
+                result: Some(TaskResult::Success),
+            })
+        ));
     }
 }
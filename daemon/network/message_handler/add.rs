@@ -1,8 +1,12 @@
-use std::sync::mpsc::Sender;
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::{mpsc::Sender, MutexGuard};
 
+use pueue_lib::aliasing::insert_alias;
+use pueue_lib::io_priority::MAX_LEVEL;
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
-use pueue_lib::task::{Task, TaskStatus};
+use pueue_lib::state::{SharedState, State};
+use pueue_lib::task::{DependencyCondition, Task, TaskStatus};
 
 use super::*;
 
@@ -15,6 +19,10 @@ pub fn add_task(message: AddMessage, sender: &Sender<Message>, state: &SharedSta
         return message;
     }
 
+    if message.slots == 0 {
+        return create_failure_message("Tasks need at least one slot");
+    }
+
     let starting_status = if message.stashed || message.enqueue_at.is_some() {
         TaskStatus::Stashed
     } else {
@@ -34,7 +42,34 @@ pub fn add_task(message: AddMessage, sender: &Sender<Message>, state: &SharedSta
         ));
     }
 
-    // Create a new task and add it to the state.
+    // Ensure that the task doesn't request more of a resource than there'll ever be.
+    for (resource, amount) in message.resources.iter() {
+        let capacity = state.resource_capacity(resource);
+        if *amount > capacity {
+            return create_failure_message(format!(
+                "The task requests {} of resource \"{}\", but its capacity is {}",
+                amount, resource, capacity
+            ));
+        }
+    }
+
+    if let Some(ionice) = message.ionice.filter(|ionice| !ionice.is_valid()) {
+        return create_failure_message(format!(
+            "Invalid I/O priority \"{}\". Levels range from 0 to {}",
+            ionice, MAX_LEVEL
+        ));
+    }
+
+    // Resource limits are enforced via cgroups, which have to be set up by the user.
+    if (message.memory_limit.is_some() || message.cpu_limit.is_some())
+        && state.settings.daemon.cgroup.is_none()
+    {
+        return create_failure_message(
+            "Memory and CPU limits need a delegated cgroup. Please set `cgroup` in the daemon's config",
+        );
+    }
+
+    // Create a new task.
     let mut task = Task::new(
         message.command,
         message.path,
@@ -45,9 +80,70 @@ pub fn add_task(message: AddMessage, sender: &Sender<Message>, state: &SharedSta
         message.dependencies,
         message.label,
     );
+    task.timeout = message.timeout;
+    task.retries = message.retries;
+    task.retry_delay = message.retry_delay;
+    task.priority = message.priority;
+    task.resources = message.resources;
+    task.slots = message.slots;
+    task.memory_limit = message.memory_limit;
+    task.cpu_limit = message.cpu_limit;
+    task.nice = message.nice;
+    task.ionice = message.ionice;
+    task.max_open_files = message.max_open_files;
+    task.max_cpu_time = message.max_cpu_time;
+    task.shell = message.shell;
+    task.no_shell = message.no_shell;
+    task.pty = message.pty;
+    task.max_log_size = message.max_log_size;
+    task.log_rotation = message.log_rotation;
+    task.dependency_conditions = message.dependency_conditions;
+    task.array_id = message.array_id;
     // Sort and deduplicate dependency id.
     task.dependencies.sort_unstable();
     task.dependencies.dedup();
+    // Only keep conditions of actual dependencies, which don't need to succeed.
+    let dependencies = &task.dependencies;
+    task.dependency_conditions.retain(|id, condition| {
+        dependencies.contains(id) && *condition != DependencyCondition::Success
+    });
+
+    // Add a whole task array, if there are any inputs.
+    if !message.each.is_empty() {
+        let array_id = state.next_array_id();
+        let task_ids = add_array(task, array_id, &message.each, &mut state);
+
+        // Notify the task handler, in case the client wants to start the tasks immediately.
+        if message.start_immediately {
+            sender
+                .send(Message::Start(StartMessage {
+                    task_ids: task_ids.clone(),
+                    ..Default::default()
+                }))
+                .expect(SENDER_ERR);
+        }
+        // Create the customized response for the client.
+        let range = format!("tasks {} to {}", task_ids[0], task_ids[task_ids.len() - 1]);
+        let message = if message.print_task_id {
+            task_ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        } else if let Some(enqueue_at) = message.enqueue_at {
+            format!(
+                "New task array {} added ({}). It will be enqueued at {}",
+                array_id,
+                range,
+                enqueue_at.format("%Y-%m-%d %H:%M:%S")
+            )
+        } else {
+            format!("New task array {} added ({}).", array_id, range)
+        };
+        state.save();
+
+        return create_success_message(message);
+    }
 
     let task_id = state.add_task(task);
 
@@ -76,3 +172,92 @@ pub fn add_task(message: AddMessage, sender: &Sender<Message>, state: &SharedSta
 
     create_success_message(message)
 }
+
+/// Add a task for each input of a task array and return the ids of the new tasks.
+/// All tasks are copies of the given task, whose command contains the placeholders.
+fn add_array(
+    template: Task,
+    array_id: usize,
+    inputs: &[String],
+    state: &mut MutexGuard<State>,
+) -> Vec<usize> {
+    let mut task_ids = Vec::new();
+    for (index, input) in inputs.iter().enumerate() {
+        let mut task = template.clone();
+        task.original_command = insert_placeholders(&template.original_command, input, index);
+        task.command = insert_alias(task.original_command.clone());
+        task.array_id = Some(array_id);
+        task_ids.push(state.insert_task(task));
+    }
+
+    task_ids
+}
+
+/// Replace the placeholders in the command of an array task with its input.
+/// - `{}` The input itself
+/// - `{name}` The file name of the input
+/// - `{stem}` The file name of the input without its extension
+/// - `{index}` The position of the input, starting at 0
+///
+/// All inputs are escaped, as they're most likely file names.
+fn insert_placeholders(command: &str, input: &str, index: usize) -> String {
+    let path = Path::new(input);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let placeholders = [
+        ("{}", shell_escape::escape(Cow::from(input))),
+        ("{name}", shell_escape::escape(name)),
+        ("{stem}", shell_escape::escape(stem)),
+        ("{index}", Cow::from(index.to_string())),
+    ];
+
+    // Replace all placeholders in a single pass, so inputs that look like placeholders are kept.
+    let mut result = String::new();
+    let mut rest = command;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match placeholders
+            .iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+        {
+            Some((placeholder, value)) => {
+                result.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                result.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_placeholders() {
+        let command = "convert {} out/{stem}.png";
+        assert_eq!(
+            insert_placeholders(command, "images/cat.jpg", 0),
+            "convert images/cat.jpg out/cat.png"
+        );
+
+        // Inputs are escaped and aren't searched for placeholders themselves.
+        assert_eq!(
+            insert_placeholders("echo {index} {name}", "my {stem}.txt", 3),
+            "echo 3 'my {stem}.txt'"
+        );
+    }
+}
@@ -1,8 +1,9 @@
-use pueue_lib::log::clean_log_handles;
 use pueue_lib::network::message::*;
 use pueue_lib::state::SharedState;
 use pueue_lib::task::{TaskResult, TaskStatus};
 
+use super::history::archive_and_clean_logs;
+
 /// Invoked when calling `pueue clean`.
 /// Remove all failed or done tasks from the state.
 pub fn clean(message: CleanMessage, state: &SharedState) -> Message {
@@ -10,6 +11,7 @@ pub fn clean(message: CleanMessage, state: &SharedState) -> Message {
     state.backup();
     let (matching, _) = state.tasks_in_statuses(vec![TaskStatus::Done], None);
 
+    let mut removed = Vec::new();
     for task_id in &matching {
         // Ensure the task is removable, i.e. there are no dependant tasks.
         if !state.is_task_removable(task_id, &[]) {
@@ -23,11 +25,15 @@ pub fn clean(message: CleanMessage, state: &SharedState) -> Message {
                 }
             }
         }
-        let _ = state.tasks.remove(task_id).unwrap();
-        clean_log_handles(*task_id, &state.settings.shared.pueue_directory);
+        removed.push(state.tasks.remove(task_id).unwrap());
     }
 
     state.save();
+    let settings = state.settings.clone();
+    drop(state);
+
+    // The logs are archived and removed in a separate thread, so the daemon isn't blocked.
+    archive_and_clean_logs(removed, settings);
 
     if message.successful_only {
         create_success_message("All successfully finished tasks have been removed")
@@ -129,6 +135,6 @@ mod tests {
         // Assert that only the first entry has been deleted (TaskResult::Success)
         let state = state.lock().unwrap();
         assert_eq!(state.tasks.len(), 5);
-        assert!(state.tasks.get(&0).is_none());
+        assert!(!state.tasks.contains_key(&0));
     }
 }
@@ -21,6 +21,8 @@ pub fn edit_request(task_id: usize, state: &SharedState) -> Message {
                 task_id: task.id,
                 command: task.original_command.clone(),
                 path: task.path.clone(),
+                timeout: task.timeout,
+                priority: task.priority,
             };
             Message::EditResponse(message)
         }
@@ -43,6 +45,8 @@ pub fn edit(message: EditMessage, state: &SharedState) -> Message {
             task.original_command = message.command.clone();
             task.command = insert_alias(message.command.clone());
             task.path = message.path.clone();
+            task.timeout = message.timeout;
+            task.priority = message.priority;
             state.save();
 
             create_success_message("Command has been updated")
@@ -0,0 +1,132 @@
+use std::thread;
+
+use log::error;
+
+use pueue_lib::archive::{apply_archive_retention, archive_task, get_archived_tasks};
+use pueue_lib::log::{clean_log_handles, get_log_paths};
+use pueue_lib::network::message::*;
+use pueue_lib::settings::Settings;
+use pueue_lib::state::SharedState;
+use pueue_lib::task::Task;
+
+/// Invoked when calling `pueue history`.
+/// Return all archived tasks, after old ones have been removed from the archive.
+pub fn history(state: &SharedState) -> Message {
+    let settings = state.lock().unwrap().settings.clone();
+    apply_retention(&settings);
+
+    Message::HistoryResponse(get_archived_tasks(&settings.shared.pueue_directory))
+}
+
+/// Archive the logs of removed tasks, if archiving is enabled. \
+/// This has to happen before their logs are cleaned up.
+/// Tasks that never started don't have any logs and aren't archived.
+///
+/// Compressing the logs can take a while, which is why this mustn't be called,
+/// while the state is locked. Use [archive_and_clean_logs] in message handlers.
+pub fn archive_logs(tasks: &[Task], settings: &Settings) {
+    if !settings.daemon.archive_logs {
+        return;
+    }
+
+    let pueue_directory = &settings.shared.pueue_directory;
+    for task in tasks {
+        let (stdout_path, _) = get_log_paths(task.id, pueue_directory);
+        if !stdout_path.exists() {
+            continue;
+        }
+        if let Err(err) = archive_task(task, pueue_directory) {
+            error!("Failed to archive task {} with error {:?}", task.id, err);
+        }
+    }
+    apply_retention(settings);
+}
+
+/// Archive the logs of removed tasks and clean up their logs afterwards. \
+/// Compressing the logs can take a while, so this happens in a separate thread.
+pub fn archive_and_clean_logs(tasks: Vec<Task>, settings: Settings) {
+    thread::spawn(move || {
+        archive_logs(&tasks, &settings);
+        for task in tasks {
+            clean_log_handles(task.id, &settings.shared.pueue_directory);
+        }
+    });
+}
+
+/// Remove archived tasks, which exceed the maximum age or size of the archive.
+fn apply_retention(settings: &Settings) {
+    apply_archive_retention(
+        &settings.shared.pueue_directory,
+        settings.daemon.archive_max_age,
+        settings.daemon.archive_max_size,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::create_dir_all;
+    use std::io::{Read, Write};
+
+    use snap::read::FrameDecoder;
+    use tempfile::tempdir;
+
+    use pueue_lib::archive::read_archived_logs;
+    use pueue_lib::log::create_log_file_handles;
+
+    use super::super::fixtures::*;
+    use super::*;
+
+    fn archive_stub_task(id: usize, output: &[u8], settings: &Settings) {
+        let mut task = get_stub_task("0", TaskStatus::Done);
+        task.id = id;
+        let pueue_directory = &settings.shared.pueue_directory;
+        let (mut stdout, _) = create_log_file_handles(task.id, pueue_directory).unwrap();
+        stdout.write_all(output).unwrap();
+
+        archive_logs(std::slice::from_ref(&task), settings);
+        clean_log_handles(task.id, pueue_directory);
+    }
+
+    #[test]
+    /// The logs of archived tasks are kept, after the logs of the task have been cleaned up.
+    fn archive_task_logs() {
+        let dir = tempdir().unwrap();
+        create_dir_all(dir.path().join("task_logs")).unwrap();
+        let mut settings = get_settings();
+        settings.shared.pueue_directory = dir.path().to_path_buf();
+        settings.daemon.archive_logs = true;
+
+        archive_stub_task(0, b"first\nsecond\n", &settings);
+        archive_stub_task(1, b"other\n", &settings);
+
+        let archived = get_archived_tasks(dir.path());
+        assert_eq!(archived.len(), 2);
+        assert_eq!(archived[1].task.id, 1);
+
+        let (stdout, _) = read_archived_logs(0, dir.path(), Some(1)).unwrap();
+        let mut output = String::new();
+        FrameDecoder::new(stdout.as_slice())
+            .read_to_string(&mut output)
+            .unwrap();
+        assert_eq!(output, "second");
+    }
+
+    #[test]
+    /// The oldest archived tasks are removed, once the archive exceeds its maximum size.
+    fn archive_retention() {
+        let dir = tempdir().unwrap();
+        create_dir_all(dir.path().join("task_logs")).unwrap();
+        let mut settings = get_settings();
+        settings.shared.pueue_directory = dir.path().to_path_buf();
+        settings.daemon.archive_logs = true;
+
+        archive_stub_task(0, b"first\n", &settings);
+        let size = get_archived_tasks(dir.path())[0].size;
+        archive_stub_task(1, b"second\n", &settings);
+        apply_archive_retention(dir.path(), None, Some(size * 3 / 2));
+
+        let archived = get_archived_tasks(dir.path());
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].id, 1);
+    }
+}
@@ -10,6 +10,10 @@ use crate::network::response_helper::task_response_helper;
 /// Invoked when calling `pueue kill`.
 /// Forward the kill message to the task handler, which then kills the process.
 pub fn kill(message: KillMessage, sender: &Sender<Message>, state: &SharedState) -> Message {
+    if let Some(array_id) = message.array_id {
+        return kill_array(message, array_id, sender, state);
+    }
+
     sender
         .send(Message::Kill(message.clone()))
         .expect(SENDER_ERR);
@@ -34,3 +38,35 @@ pub fn kill(message: KillMessage, sender: &Sender<Message>, state: &SharedState)
         ))
     }
 }
+
+/// Kill all running tasks of a task array.
+/// Its queued and stashed tasks are marked as killed by the task handler as well,
+/// so they won't be started anymore.
+fn kill_array(
+    message: KillMessage,
+    array_id: usize,
+    sender: &Sender<Message>,
+    state: &SharedState,
+) -> Message {
+    let state = state.lock().unwrap();
+    let task_ids = state.array_task_ids(array_id);
+    if task_ids.is_empty() {
+        return create_failure_message(format!("There's no task array {}", array_id));
+    }
+
+    let response = task_response_helper(
+        "Tasks are being killed",
+        task_ids.clone(),
+        vec![
+            TaskStatus::Running,
+            TaskStatus::Paused,
+            TaskStatus::Queued,
+            TaskStatus::Stashed,
+        ],
+        &state,
+    );
+
+    sender.send(Message::Kill(message)).expect(SENDER_ERR);
+
+    create_success_message(response)
+}
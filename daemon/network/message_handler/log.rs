@@ -1,7 +1,11 @@
 use std::collections::BTreeMap;
 
-use pueue_lib::log::read_and_compress_log_files;
+use pueue_lib::archive::{
+    get_archived_tasks, read_archived_combined_log, read_archived_logs, read_archived_task,
+};
+use pueue_lib::log::{read_and_compress_combined_log, read_and_compress_log_files};
 use pueue_lib::network::message::*;
+use pueue_lib::settings::Settings;
 use pueue_lib::state::SharedState;
 
 /// Invoked when calling `pueue log`.
@@ -9,10 +13,15 @@ use pueue_lib::state::SharedState;
 pub fn get_log(message: LogRequestMessage, state: &SharedState) -> Message {
     let state = state.lock().unwrap().clone();
     // Return all logs, if no specific task id is specified.
-    let task_ids = if message.task_ids.is_empty() {
-        state.tasks.keys().cloned().collect()
+    let task_ids = if !message.task_ids.is_empty() {
+        message.task_ids.clone()
+    } else if message.archived {
+        get_archived_tasks(&state.settings.shared.pueue_directory)
+            .iter()
+            .map(|archived| archived.id)
+            .collect()
     } else {
-        message.task_ids
+        state.tasks.keys().cloned().collect()
     };
 
     // Determine, whether we should draw everything or only a part of the log output.
@@ -31,6 +40,10 @@ pub fn get_log(message: LogRequestMessage, state: &SharedState) -> Message {
         }
     };
 
+    if message.archived {
+        return get_archived_log(&message, task_ids, lines, &state.settings);
+    }
+
     let mut tasks = BTreeMap::new();
     for task_id in task_ids.iter() {
         if let Some(task) = state.tasks.get(task_id) {
@@ -56,13 +69,82 @@ pub fn get_log(message: LogRequestMessage, state: &SharedState) -> Message {
                 (None, None)
             };
 
+            // Tasks without a combined log fall back to their regular logs.
+            let combined = if message.send_logs && message.timestamps {
+                match read_and_compress_combined_log(
+                    *task_id,
+                    &state.settings.shared.pueue_directory,
+                    lines,
+                    message.stderr_only,
+                ) {
+                    Ok(combined) => combined,
+                    Err(err) => {
+                        return create_failure_message(format!(
+                            "Failed reading combined log file: {:?}",
+                            err
+                        ));
+                    }
+                }
+            } else {
+                None
+            };
+
             let task_log = TaskLogMessage {
                 task: task.clone(),
                 stdout,
                 stderr,
+                combined,
             };
             tasks.insert(*task_id, task_log);
         }
     }
     Message::LogResponse(tasks)
 }
+
+/// Return the logs of archived tasks, which are keyed by their archive id.
+fn get_archived_log(
+    message: &LogRequestMessage,
+    archive_ids: Vec<usize>,
+    lines: Option<usize>,
+    settings: &Settings,
+) -> Message {
+    let pueue_directory = &settings.shared.pueue_directory;
+    let mut tasks = BTreeMap::new();
+    for id in archive_ids {
+        let archived = match read_archived_task(id, pueue_directory) {
+            Ok(archived) => archived,
+            Err(err) => return create_failure_message(err.to_string()),
+        };
+        let (stdout, stderr) = match read_archived_logs(id, pueue_directory, lines) {
+            Ok(logs) => logs,
+            Err(err) => {
+                return create_failure_message(format!(
+                    "Failed reading archived output: {:?}",
+                    err
+                ));
+            }
+        };
+        let combined = if message.timestamps {
+            match read_archived_combined_log(id, pueue_directory, lines, message.stderr_only) {
+                Ok(combined) => combined,
+                Err(err) => {
+                    return create_failure_message(format!(
+                        "Failed reading archived combined log: {:?}",
+                        err
+                    ));
+                }
+            }
+        } else {
+            None
+        };
+
+        let task_log = TaskLogMessage {
+            task: archived.task,
+            stdout: Some(stdout),
+            stderr: Some(stderr),
+            combined,
+        };
+        tasks.insert(id, task_log);
+    }
+    Message::LogResponse(tasks)
+}
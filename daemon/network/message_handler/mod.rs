@@ -11,27 +11,32 @@ mod clean;
 mod edit;
 mod enqueue;
 mod group;
+mod history;
 mod kill;
 mod log;
 mod parallel;
 mod pause;
 mod remove;
 mod restart;
+mod schedule;
 mod send;
+mod signal;
 mod start;
 mod stash;
+mod submit;
 mod switch;
 
-static SENDER_ERR: &str = "Failed to send message to task handler thread";
+pub static SENDER_ERR: &str = "Failed to send message to task handler thread";
 
 pub fn handle_message(message: Message, sender: &Sender<Message>, state: &SharedState) -> Message {
     match message {
-        Message::Add(message) => add::add_task(message, sender, state),
+        Message::Add(message) => add::add_task(*message, sender, state),
         Message::Clean(message) => clean::clean(message, state),
         Message::Edit(message) => edit::edit(message, state),
         Message::EditRequest(task_id) => edit::edit_request(task_id, state),
         Message::Enqueue(message) => enqueue::enqueue(message, state),
         Message::Group(message) => group::group(message, state),
+        Message::History => history::history(state),
         Message::Kill(message) => kill::kill(message, sender, state),
         Message::Log(message) => log::get_log(message, state),
         Message::Parallel(message) => parallel::set_parallel_tasks(message, state),
@@ -39,9 +44,12 @@ pub fn handle_message(message: Message, sender: &Sender<Message>, state: &Shared
         Message::Remove(task_ids) => remove::remove(task_ids, state),
         Message::Reset(message) => reset(message, sender),
         Message::Restart(message) => restart::restart_multiple(message, sender, state),
+        Message::Schedule(message) => schedule::schedule(message, state),
         Message::Send(message) => send::send(message, sender, state),
+        Message::Signal(message) => signal::signal(message, sender, state),
         Message::Start(message) => start::start(message, sender, state),
         Message::Stash(task_ids) => stash::stash(task_ids, state),
+        Message::Submit(message) => submit::submit(message, state),
         Message::Switch(message) => switch::switch(message, state),
         Message::Status => get_status(state),
         Message::DaemonShutdown => shutdown(sender, state),
@@ -86,20 +94,14 @@ fn shutdown(sender: &Sender<Message>, state: &SharedState) -> Message {
 #[cfg(test)]
 mod fixtures {
     use std::collections::HashMap;
-    pub use std::sync::mpsc::Sender;
     use std::sync::{Arc, Mutex};
 
-    pub use pueue_lib::network::message::*;
-    pub use pueue_lib::network::protocol::socket_cleanup;
     pub use pueue_lib::settings::Settings;
     pub use pueue_lib::state::{SharedState, State};
     pub use pueue_lib::task::TaskResult;
 
     pub use pueue_lib::task::{Task, TaskStatus};
 
-    pub use super::*;
-    pub use crate::network::response_helper::*;
-
     pub fn get_settings() -> Settings {
         Settings::default_config()
             .expect("Failed to get default config")
@@ -116,7 +118,7 @@ mod fixtures {
     /// Create a new task with stub data
     pub fn get_stub_task(id: &str, status: TaskStatus) -> Task {
         Task::new(
-            format!("{}", id),
+            id.to_string(),
             "/tmp".to_string(),
             HashMap::new(),
             "default".to_string(),
@@ -1,8 +1,8 @@
-use pueue_lib::log::clean_log_handles;
 use pueue_lib::network::message::*;
 use pueue_lib::state::SharedState;
-use pueue_lib::task::TaskStatus;
+use pueue_lib::task::{Task, TaskStatus};
 
+use super::history::archive_and_clean_logs;
 use crate::network::response_helper::*;
 
 /// Invoked when calling `pueue remove`.
@@ -27,11 +27,15 @@ pub fn remove(task_ids: Vec<usize>, state: &SharedState) -> Message {
         };
     }
 
-    for task_id in &not_running {
-        state.tasks.remove(task_id);
+    let removed: Vec<Task> = not_running
+        .iter()
+        .filter_map(|task_id| state.tasks.remove(task_id))
+        .collect();
+    let settings = state.settings.clone();
+    drop(state);
 
-        clean_log_handles(*task_id, &state.settings.shared.pueue_directory);
-    }
+    // The logs are archived and removed in a separate thread, so the daemon isn't blocked.
+    archive_and_clean_logs(removed, settings);
 
     let text = "Tasks removed from list";
     let response = compile_task_response(text, not_running, running);
@@ -43,6 +47,9 @@ mod tests {
     use super::super::fixtures::*;
     use super::*;
 
+    use chrono::Local;
+    use pueue_lib::schedule::Schedule;
+
     #[test]
     fn normal_remove() {
         let state = get_stub_state();
@@ -123,4 +130,32 @@ mod tests {
             assert_eq!(state.tasks.len(), 4);
         }
     }
+
+    #[test]
+    fn removal_of_scheduled_dependencies() {
+        let state = get_stub_state();
+
+        {
+            // Add a schedule, whose tasks depend on a finished task.
+            let mut state = state.lock().unwrap();
+            let mut task = get_stub_task("5", TaskStatus::Queued);
+            task.dependencies = vec![1];
+            let schedule = Schedule::new("* * * * *".into(), task, Local::now());
+            state.schedules.insert(0, schedule);
+        }
+
+        // The dependency is needed by all future tasks of the schedule.
+        let message = remove(vec![1], &state);
+        assert!(matches!(message, Message::Success(_)));
+        if let Message::Success(text) = message {
+            assert_eq!(text, "The command failed for tasks: 1");
+        };
+
+        // Once the schedule is gone, the task can be removed.
+        state.lock().unwrap().schedules.clear();
+        let message = remove(vec![1], &state);
+        if let Message::Success(text) = message {
+            assert_eq!(text, "Tasks removed from list: 1");
+        };
+    }
 }
@@ -55,7 +55,9 @@ fn restart(state: &mut MutexGuard<State>, to_restart: &TasksToRestart, stashed:
     task.path = to_restart.path.clone();
 
     // Reset all variables of any previous run.
+    task.attempts = Vec::new();
     task.result = None;
     task.start = None;
     task.end = None;
+    task.resource_usage = None;
 }
@@ -0,0 +1,125 @@
+use chrono::prelude::*;
+
+use pueue_lib::network::message::*;
+use pueue_lib::schedule::Schedule;
+use pueue_lib::state::SharedState;
+use pueue_lib::task::{Task, TaskStatus};
+
+use crate::network::response_helper::*;
+use crate::schedule::next_run;
+
+/// Invoked when calling `pueue schedule`.
+/// Manage recurring tasks.
+/// - Add schedule
+/// - Remove schedules
+/// - Pause/resume schedules
+pub fn schedule(message: ScheduleMessage, state: &SharedState) -> Message {
+    match message {
+        ScheduleMessage::Add(message) => add(*message, state),
+        ScheduleMessage::Remove(schedule_ids) => remove(schedule_ids, state),
+        ScheduleMessage::Pause(schedule_ids) => set_paused(schedule_ids, true, state),
+        ScheduleMessage::Resume(schedule_ids) => set_paused(schedule_ids, false, state),
+    }
+}
+
+/// Validate the cron expression and add a new schedule to the state.
+fn add(message: ScheduleAddMessage, state: &SharedState) -> Message {
+    let mut state = state.lock().unwrap();
+    if let Err(message) = ensure_group_exists(&state, &message.group) {
+        return message;
+    }
+
+    let next = match next_run(&message.expression, &Local::now()) {
+        Ok(next) => next,
+        Err(error) => return create_failure_message(format!("{:#}", error)),
+    };
+
+    // Ensure that specified dependencies actually exist.
+    let not_found: Vec<_> = message
+        .dependencies
+        .iter()
+        .filter(|id| !state.tasks.contains_key(id))
+        .collect();
+    if !not_found.is_empty() {
+        return create_failure_message(format!(
+            "Unable to setup dependencies : task(s) {:?} not found",
+            not_found
+        ));
+    }
+
+    // Create the template for all tasks spawned by this schedule.
+    let mut task = Task::new(
+        message.command,
+        message.path,
+        message.envs,
+        message.group,
+        TaskStatus::Queued,
+        None,
+        message.dependencies,
+        message.label,
+    );
+    task.timeout = message.timeout;
+    task.retries = message.retries;
+    task.retry_delay = message.retry_delay;
+    task.priority = message.priority;
+    task.dependencies.sort_unstable();
+    task.dependencies.dedup();
+
+    let schedule = Schedule::new(message.expression, task, next);
+    let schedule_id = state.add_schedule(schedule);
+
+    create_success_message(format!(
+        "New schedule added (id {}). It will fire next at {}",
+        schedule_id,
+        next.format("%Y-%m-%d %H:%M:%S")
+    ))
+}
+
+/// Remove schedules. Tasks that have already been spawned aren't touched.
+fn remove(schedule_ids: Vec<usize>, state: &SharedState) -> Message {
+    let mut state = state.lock().unwrap();
+    let (removed, not_found): (Vec<usize>, Vec<usize>) = schedule_ids
+        .into_iter()
+        .partition(|id| state.schedules.remove(id).is_some());
+    state.save();
+
+    let response = compile_task_response("Schedules removed", removed, not_found);
+    create_success_message(response)
+}
+
+/// Pause or resume schedules.
+/// Resumed schedules calculate their next run from now on, missed runs aren't caught up.
+fn set_paused(schedule_ids: Vec<usize>, paused: bool, state: &SharedState) -> Message {
+    let mut state = state.lock().unwrap();
+    let now = Local::now();
+
+    let mut changed = Vec::new();
+    let mut not_found = Vec::new();
+    for id in schedule_ids {
+        let schedule = match state.schedules.get_mut(&id) {
+            Some(schedule) => schedule,
+            None => {
+                not_found.push(id);
+                continue;
+            }
+        };
+
+        if !paused && schedule.paused {
+            match next_run(&schedule.expression, &now) {
+                Ok(next) => schedule.next_run = next,
+                Err(error) => return create_failure_message(format!("{:#}", error)),
+            }
+        }
+        schedule.paused = paused;
+        changed.push(id);
+    }
+    state.save();
+
+    let text = if paused {
+        "Schedules paused"
+    } else {
+        "Schedules resumed"
+    };
+    let response = compile_task_response(text, changed, not_found);
+    create_success_message(response)
+}
@@ -0,0 +1,77 @@
+use std::sync::mpsc::Sender;
+
+use pueue_lib::network::message::*;
+use pueue_lib::signal::Signal;
+use pueue_lib::state::SharedState;
+
+use super::SENDER_ERR;
+
+/// Invoked when calling `pueue signal`.
+/// The message will be forwarded to the task handler, which then sends the signal to the process.
+/// In here we only do some error handling.
+pub fn signal(message: SignalMessage, sender: &Sender<Message>, state: &SharedState) -> Message {
+    // Stopped tasks would still be shown as running and keep their slots.
+    match message.signal {
+        Signal::Stop | Signal::Tstp => {
+            return create_failure_message("Use `pueue pause` to stop tasks");
+        }
+        Signal::Cont => return create_failure_message("Use `pueue start` to resume tasks"),
+        _ => (),
+    }
+
+    // Check whether the task exists and has a process. Abort if that's not the case.
+    {
+        let state = state.lock().unwrap();
+        match state.tasks.get(&message.task_id) {
+            Some(task) => {
+                if !task.is_running() {
+                    return create_failure_message(
+                        "You can only send signals to running or paused tasks",
+                    );
+                }
+            }
+            None => return create_failure_message("No task with this id."),
+        }
+    }
+
+    let text = format!(
+        "{} is being sent to task {}.",
+        message.signal, message.task_id
+    );
+    sender.send(Message::Signal(message)).expect(SENDER_ERR);
+
+    create_success_message(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use super::super::fixtures::*;
+    use super::*;
+
+    fn get_message(signal: Signal) -> SignalMessage {
+        SignalMessage {
+            task_id: 3,
+            signal,
+            children: false,
+        }
+    }
+
+    #[test]
+    /// Signals that stop or resume tasks are rejected, since they'd bypass the pause handling.
+    fn reject_stop_and_continue() {
+        let state = get_stub_state();
+        let (sender, receiver) = channel();
+
+        for rejected in [Signal::Stop, Signal::Tstp, Signal::Cont].iter() {
+            let message = signal(get_message(*rejected), &sender, &state);
+            assert!(matches!(message, Message::Failure(_)));
+        }
+        assert!(receiver.try_recv().is_err());
+
+        let message = signal(get_message(Signal::Hup), &sender, &state);
+        assert!(matches!(message, Message::Success(_)));
+        assert!(matches!(receiver.try_recv(), Ok(Message::Signal(_))));
+    }
+}
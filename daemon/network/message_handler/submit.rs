@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use pueue_lib::network::message::*;
+use pueue_lib::state::SharedState;
+use pueue_lib::task::{DependencyCondition, Task, TaskStatus};
+
+use crate::network::response_helper::*;
+
+/// Invoked when calling `pueue submit`.
+/// Validate a workflow and add all of its steps as tasks.
+/// Either all steps are added or none at all.
+pub fn submit(message: SubmitMessage, state: &SharedState) -> Message {
+    let mut state = state.lock().unwrap();
+    let steps = message.workflow.steps;
+    if steps.is_empty() {
+        return create_failure_message("The workflow doesn't contain any steps");
+    }
+
+    // Ensure that all step names are unique and all groups exist.
+    let mut indices: HashMap<&str, usize> = HashMap::new();
+    for (index, step) in steps.iter().enumerate() {
+        if step.name.is_empty() {
+            return create_failure_message(format!("Step {} doesn't have a name", index + 1));
+        }
+        if indices.insert(&step.name, index).is_some() {
+            return create_failure_message(format!(
+                "The step name \"{}\" is used more than once",
+                step.name
+            ));
+        }
+        if let Some(group) = &step.group {
+            if let Err(message) = ensure_group_exists(&state, group) {
+                return message;
+            }
+        }
+    }
+
+    // Resolve the dependencies of all steps by their name.
+    let mut dependencies: Vec<Vec<(usize, DependencyCondition)>> = Vec::new();
+    for step in steps.iter() {
+        let conditions = [
+            (&step.after, DependencyCondition::Success),
+            (&step.after_failure, DependencyCondition::Failure),
+            (&step.after_any, DependencyCondition::Any),
+        ];
+
+        let mut step_dependencies = Vec::new();
+        for (names, condition) in conditions.iter() {
+            for name in names.iter() {
+                match indices.get(name.as_str()) {
+                    Some(index) => step_dependencies.push((*index, *condition)),
+                    None => {
+                        return create_failure_message(format!(
+                            "Step \"{}\" depends on the unknown step \"{}\"",
+                            step.name, name
+                        ))
+                    }
+                }
+            }
+        }
+        dependencies.push(step_dependencies);
+    }
+
+    let order = match sort_steps(&dependencies) {
+        Ok(order) => order,
+        Err(cycle) => {
+            let names: Vec<&str> = cycle
+                .iter()
+                .map(|index| steps[*index].name.as_str())
+                .collect();
+            return create_failure_message(format!(
+                "The dependencies of these steps contain a cycle: {}",
+                names.join(", ")
+            ));
+        }
+    };
+
+    // Add the tasks in their topological order, so each task is added after its dependencies.
+    let mut task_ids: Vec<usize> = vec![0; steps.len()];
+    for index in order {
+        let step = &steps[index];
+        let path = match &step.path {
+            Some(path) => Path::new(&message.path).join(path),
+            None => Path::new(&message.path).to_path_buf(),
+        };
+        let group = step.group.clone().unwrap_or_else(|| "default".to_string());
+
+        let mut task = Task::new(
+            step.command.clone(),
+            path.to_string_lossy().to_string(),
+            message.envs.clone(),
+            group,
+            TaskStatus::Queued,
+            None,
+            Vec::new(),
+            step.label.clone(),
+        );
+
+        for (dependency, condition) in dependencies[index].iter() {
+            let dependency_id = task_ids[*dependency];
+            task.dependencies.push(dependency_id);
+            if *condition != DependencyCondition::Success {
+                task.dependency_conditions.insert(dependency_id, *condition);
+            }
+        }
+        task.dependencies.sort_unstable();
+        task.dependencies.dedup();
+
+        task_ids[index] = state.insert_task(task);
+    }
+    state.save();
+
+    let response: BTreeMap<String, usize> = steps
+        .into_iter()
+        .zip(task_ids)
+        .map(|(step, id)| (step.name, id))
+        .collect();
+    Message::SubmitResponse(response)
+}
+
+/// Sort the steps of a workflow topologically.
+/// Steps without any order between them keep the order in which they've been specified.
+///
+/// If the dependencies contain a cycle, all steps that couldn't be sorted are returned as error.
+/// Those are the steps of the cycle and all steps that depend on them.
+fn sort_steps(
+    dependencies: &[Vec<(usize, DependencyCondition)>],
+) -> Result<Vec<usize>, Vec<usize>> {
+    let mut sorted = Vec::new();
+    let mut remaining: Vec<usize> = (0..dependencies.len()).collect();
+
+    while !remaining.is_empty() {
+        // Get the first step, whose dependencies have all been sorted already.
+        let next = remaining.iter().position(|index| {
+            dependencies[*index]
+                .iter()
+                .all(|(dependency, _)| sorted.contains(dependency))
+        });
+
+        match next {
+            Some(position) => sorted.push(remaining.remove(position)),
+            None => return Err(remaining),
+        }
+    }
+
+    Ok(sorted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::*;
+    use super::*;
+
+    fn get_step(name: &str, after: &[&str]) -> WorkflowStep {
+        WorkflowStep {
+            name: name.to_string(),
+            command: format!("echo {}", name),
+            path: None,
+            group: None,
+            label: None,
+            after: after.iter().map(|name| name.to_string()).collect(),
+            after_failure: Vec::new(),
+            after_any: Vec::new(),
+        }
+    }
+
+    fn get_message(steps: Vec<WorkflowStep>) -> SubmitMessage {
+        SubmitMessage {
+            workflow: Workflow { steps },
+            path: "/tmp".to_string(),
+            envs: HashMap::new(),
+        }
+    }
+
+    #[test]
+    /// Steps are added after their dependencies and their dependencies are resolved by name.
+    fn submit_workflow() {
+        let state = get_state();
+        let mut cleanup = get_step("cleanup", &[]);
+        cleanup.after_failure = vec!["build".to_string()];
+        let steps = vec![
+            get_step("test", &["build"]),
+            get_step("build", &["fetch"]),
+            get_step("fetch", &[]),
+            cleanup,
+        ];
+
+        let message = submit(get_message(steps), &state);
+
+        let task_ids = match message {
+            Message::SubmitResponse(task_ids) => task_ids,
+            _ => panic!("Expected a submit response, got {:?}", message),
+        };
+        assert_eq!(task_ids["fetch"], 0);
+        assert_eq!(task_ids["build"], 1);
+        assert_eq!(task_ids["test"], 2);
+        assert_eq!(task_ids["cleanup"], 3);
+
+        let state = state.lock().unwrap();
+        assert_eq!(state.tasks[&2].dependencies, vec![1]);
+        let cleanup = &state.tasks[&3];
+        assert_eq!(cleanup.dependencies, vec![1]);
+        assert_eq!(
+            cleanup.dependency_condition(1),
+            DependencyCondition::Failure
+        );
+    }
+
+    #[test]
+    /// Workflows with unknown dependencies are rejected as a whole.
+    fn submit_unknown_dependency() {
+        let state = get_state();
+        let steps = vec![get_step("fetch", &[]), get_step("build", &["fecth"])];
+
+        let message = submit(get_message(steps), &state);
+
+        assert!(matches!(message, Message::Failure(_)));
+        assert!(state.lock().unwrap().tasks.is_empty());
+    }
+
+    #[test]
+    /// Workflows with dependency cycles are rejected as a whole.
+    fn submit_cycle() {
+        let state = get_state();
+        let steps = vec![
+            get_step("fetch", &[]),
+            get_step("build", &["fetch", "test"]),
+            get_step("test", &["build"]),
+        ];
+
+        let message = submit(get_message(steps), &state);
+
+        if let Message::Failure(text) = message {
+            assert_eq!(
+                text,
+                "The dependencies of these steps contain a cycle: build, test"
+            );
+        } else {
+            panic!("Expected a failure, got {:?}", message);
+        }
+        assert!(state.lock().unwrap().tasks.is_empty());
+    }
+}
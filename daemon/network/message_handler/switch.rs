@@ -6,7 +6,7 @@ use pueue_lib::task::TaskStatus;
 /// Switch the position of two tasks in the upcoming queue.
 /// We have to ensure that those tasks are either `Queued` or `Stashed`
 pub fn switch(message: SwitchMessage, state: &SharedState) -> Message {
-    let task_ids = vec![message.task_id_1, message.task_id_2];
+    let task_ids = [message.task_id_1, message.task_id_2];
     let statuses = vec![TaskStatus::Queued, TaskStatus::Stashed];
     let mut state = state.lock().unwrap();
     let (_, mismatching) = state.tasks_in_statuses(statuses, Some(task_ids.to_vec()));
@@ -29,6 +29,16 @@ pub fn switch(message: SwitchMessage, state: &SharedState) -> Message {
     state.tasks.insert(second_task.id, second_task);
 
     for (_, task) in state.tasks.iter_mut() {
+        // Dependency conditions always follow their dependency.
+        let first_condition = task.dependency_conditions.remove(&first_id);
+        let second_condition = task.dependency_conditions.remove(&second_id);
+        if let Some(condition) = first_condition {
+            task.dependency_conditions.insert(second_id, condition);
+        }
+        if let Some(condition) = second_condition {
+            task.dependency_conditions.insert(first_id, condition);
+        }
+
         // If the task depends on both, we can just keep it as it is.
         if task.dependencies.contains(&first_id) && task.dependencies.contains(&second_id) {
             continue;
@@ -49,6 +59,8 @@ pub fn switch(message: SwitchMessage, state: &SharedState) -> Message {
 
 #[cfg(test)]
 mod tests {
+    use pueue_lib::task::DependencyCondition;
+
     use super::super::fixtures::*;
     use super::*;
 
@@ -135,6 +147,26 @@ mod tests {
         assert_eq!(state.tasks.get(&6).unwrap().dependencies, vec![1, 3]);
     }
 
+    #[test]
+    /// Dependency conditions need to follow their dependency, when it's switched.
+    fn switch_dependency_conditions() {
+        let state = get_test_state();
+        {
+            let mut state = state.lock().unwrap();
+            let task = state.tasks.get_mut(&6).unwrap();
+            task.dependency_conditions
+                .insert(2, DependencyCondition::Failure);
+        }
+
+        switch(get_message(1, 2), &state);
+
+        let state = state.lock().unwrap();
+        let task = state.tasks.get(&6).unwrap();
+        assert_eq!(task.dependencies, vec![1, 3]);
+        assert_eq!(task.dependency_condition(1), DependencyCondition::Failure);
+        assert_eq!(task.dependency_condition(3), DependencyCondition::Success);
+    }
+
     #[test]
     /// You can only switch tasks that are either stashed or queued.
     /// Everything else should result in an error message.
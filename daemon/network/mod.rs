@@ -1,3 +1,4 @@
+pub mod attach;
 pub mod follow_log;
 pub mod message_handler;
 pub mod response_helper;
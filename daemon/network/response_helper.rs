@@ -5,6 +5,7 @@ use pueue_lib::state::State;
 use pueue_lib::task::TaskStatus;
 
 /// Check whether the given group exists. Return an failure message if it doesn't.
+#[allow(clippy::result_large_err)]
 pub fn ensure_group_exists(state: &MutexGuard<State>, group: &str) -> Result<(), Message> {
     if !state.groups.contains_key(group) {
         return Err(create_failure_message(format!(
@@ -10,6 +10,7 @@ use pueue_lib::network::protocol::*;
 use pueue_lib::network::secret::read_shared_secret;
 use pueue_lib::state::SharedState;
 
+use crate::network::attach::handle_attach;
 use crate::network::follow_log::handle_follow;
 use crate::network::message_handler::handle_message;
 
@@ -96,13 +97,19 @@ async fn handle_incoming(
         let message = receive_message(&mut stream).await?;
         debug!("Received instruction: {:?}", message);
 
-        let response = if let Message::StreamRequest(message) = message {
+        let response = match message {
             // The client requested the output of a task.
             // Since we allow streaming, this needs to be handled seperately.
-            handle_follow(&pueue_directory, &mut stream, &state, message).await?
-        } else {
+            Message::StreamRequest(message) => {
+                handle_follow(&pueue_directory, &mut stream, &state, message).await?
+            }
+            // The client wants to interact with a task.
+            // Input and output are exchanged until the client detaches.
+            Message::AttachRequest(task_id) => {
+                handle_attach(&pueue_directory, &mut stream, &state, &sender, task_id).await?
+            }
             // Process a normal message.
-            handle_message(message, &sender, &state)
+            message => handle_message(message, &sender, &state),
         };
 
         // Respond to the client.
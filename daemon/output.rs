@@ -0,0 +1,316 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::prelude::*;
+use log::warn;
+
+use pueue_lib::log::{rotate_log, write_combined_line, LogStream};
+use pueue_lib::log_rotation::LogRotation;
+
+/// How long the daemon waits for the remaining output of a finished task.
+/// Processes that left the task's process group might still keep its output open.
+const OUTPUT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// The combined log of a task, which is shared by the output threads of both of its streams.
+pub type CombinedLog = Arc<Mutex<TaskLog>>;
+
+/// A log of a task, which is rotated once it exceeds its maximum size.
+pub struct TaskLog {
+    file: File,
+    path: PathBuf,
+    /// The size of the current log file.
+    size: u64,
+    /// The size at which the log is rotated, how many of its segments are kept
+    /// and whether they're compressed.
+    rotation: Option<(u64, usize, bool)>,
+}
+
+impl TaskLog {
+    /// The log is only rotated, if it has a maximum size.
+    pub fn new(file: File, path: PathBuf, limit: Option<(u64, LogRotation)>) -> Self {
+        TaskLog {
+            file,
+            path,
+            size: 0,
+            rotation: limit.map(|(max_size, rotation)| rotation.segments(max_size)),
+        }
+    }
+}
+
+impl Write for TaskLog {
+    /// Output is only written up to the size at which the log is rotated.
+    /// The log is rotated right before the next output is written.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (rotate_at, keep, compress) = match self.rotation {
+            Some(rotation) => rotation,
+            None => return self.file.write(buf),
+        };
+
+        if self.size >= rotate_at {
+            match rotate_log(&self.path, keep, compress) {
+                Ok(file) => {
+                    self.file = file;
+                    self.size = 0;
+                }
+                // The output of the task is kept, even if the log can't be rotated.
+                Err(err) => {
+                    warn!("Failed to rotate log {:?}: {}", self.path, err);
+                    self.rotation = None;
+                    return self.file.write(buf);
+                }
+            }
+        }
+
+        let count = (rotate_at - self.size).min(buf.len() as u64) as usize;
+        let count = self.file.write(&buf[..count])?;
+        self.size += count as u64;
+
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// A thread, which copies the output of a stream of a task into its log.
+pub struct OutputThread {
+    /// Gets a message once the task closed the stream.
+    done: Receiver<()>,
+    /// Once set, the thread stops writing to the log and exits with the next read.
+    detached: Arc<AtomicBool>,
+}
+
+/// Copy all output of a stream of a task into its log in a separate thread.
+///
+/// If the task has a combined log, each line is additionally written to it, together with the
+/// time at which the task started writing it and the stream it originates from.
+pub fn spawn_output_thread<R: Read + Send + 'static>(
+    reader: R,
+    log: TaskLog,
+    combined: Option<(CombinedLog, LogStream)>,
+) -> OutputThread {
+    let (sender, done) = channel();
+    let detached = Arc::new(AtomicBool::new(false));
+    let thread_detached = detached.clone();
+    thread::spawn(move || {
+        copy_output(reader, log, combined, &thread_detached);
+        let _ = sender.send(());
+    });
+
+    OutputThread { done, detached }
+}
+
+/// Give the output threads of finished tasks a moment to write their remaining output.
+///
+/// Threads, whose stream is still kept open by some leftover process, are detached.
+/// Otherwise, they would keep writing to the old log, once the task is restarted.
+pub fn wait_for_output(threads: Vec<OutputThread>) {
+    let deadline = Instant::now() + OUTPUT_TIMEOUT;
+    for thread in threads {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        if thread.done.recv_timeout(timeout).is_err() {
+            thread.detached.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Copy the output until the stream is closed or the thread is detached.
+/// Reading from the master of a pseudo terminal fails with `EIO`, once all processes closed it.
+fn copy_output<R: Read>(
+    mut reader: R,
+    mut log: TaskLog,
+    combined: Option<(CombinedLog, LogStream)>,
+    detached: &AtomicBool,
+) {
+    // The incomplete last line and the point in time at which it has been started.
+    let mut line = Vec::new();
+    let mut line_start = Local::now();
+
+    let mut buffer = [0; 4096];
+    loop {
+        let count = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(count) => count,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        if detached.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Err(err) = log.write_all(&buffer[..count]) {
+            warn!("Failed to write task output to log: {}", err);
+            return;
+        }
+
+        let (combined, stream) = match &combined {
+            Some((combined, stream)) => (combined, *stream),
+            None => continue,
+        };
+        for byte in buffer[..count].iter() {
+            if line.is_empty() {
+                line_start = Local::now();
+            }
+            if *byte != b'\n' {
+                line.push(*byte);
+                continue;
+            }
+
+            // Terminals end their lines with a carriage return as well.
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            write_line(combined, line_start, stream, &line);
+            line.clear();
+        }
+    }
+
+    if let Some((combined, stream)) = combined {
+        if !line.is_empty() {
+            write_line(&combined, line_start, stream, &line);
+        }
+    }
+}
+
+/// Write a single line to the combined log.
+fn write_line(combined: &CombinedLog, time: DateTime<Local>, stream: LogStream, line: &[u8]) {
+    let mut log = combined.lock().unwrap();
+    if let Err(err) = write_combined_line(&mut *log, time, stream, line) {
+        warn!("Failed to write task output to combined log: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+    #[cfg(unix)]
+    use std::os::unix::net::UnixStream;
+    use std::path::Path;
+
+    use tempfile::tempdir;
+
+    use pueue_lib::log::{get_rotated_segments, open_log, parse_combined_line};
+
+    use super::*;
+
+    fn create_log(path: &Path, limit: Option<(u64, LogRotation)>) -> TaskLog {
+        TaskLog::new(File::create(path).unwrap(), path.to_path_buf(), limit)
+    }
+
+    fn read_log(path: &Path) -> String {
+        let mut output = String::new();
+        open_log(path).unwrap().read_to_string(&mut output).unwrap();
+        output
+    }
+
+    #[test]
+    /// Lines of both streams end up in the combined log, while each stream keeps its own log.
+    fn test_combined_log() {
+        let dir = tempdir().unwrap();
+        let combined = Arc::new(Mutex::new(create_log(&dir.path().join("combined"), None)));
+        let stdout = create_log(&dir.path().join("stdout"), None);
+        let stderr = create_log(&dir.path().join("stderr"), None);
+
+        let thread = spawn_output_thread(
+            &b"first\r\nsecond"[..],
+            stdout,
+            Some((combined.clone(), LogStream::Stdout)),
+        );
+        thread.done.recv().unwrap();
+        let thread =
+            spawn_output_thread(&b"error\n"[..], stderr, Some((combined, LogStream::Stderr)));
+        thread.done.recv().unwrap();
+
+        let combined = read_to_string(dir.path().join("combined")).unwrap();
+        let lines: Vec<(LogStream, String)> = combined
+            .lines()
+            .map(|line| parse_combined_line(line).unwrap())
+            .map(|line| (line.stream, line.text))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (LogStream::Stdout, "first".to_string()),
+                (LogStream::Stdout, "second".to_string()),
+                (LogStream::Stderr, "error".to_string()),
+            ]
+        );
+        assert_eq!(
+            read_to_string(dir.path().join("stdout")).unwrap(),
+            "first\r\nsecond"
+        );
+    }
+
+    #[test]
+    /// Rotated logs only keep their latest segments, which are read as a whole.
+    fn test_log_rotation() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("log");
+        let mut log = create_log(&path, Some((4, LogRotation::Rotate(2))));
+        log.write_all(b"aaaabbbbccccdd").unwrap();
+
+        assert_eq!(get_rotated_segments(&path).len(), 2);
+        assert_eq!(read_to_string(&path).unwrap(), "dd");
+        assert_eq!(read_log(&path), "bbbbccccdd");
+    }
+
+    #[test]
+    /// Compressed segments are transparently decompressed, when the log is read.
+    fn test_compressed_log_rotation() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("log");
+        let mut log = create_log(&path, Some((4, LogRotation::Compress(1))));
+        log.write_all(b"aaaabbbbcc").unwrap();
+
+        let segments = get_rotated_segments(&path);
+        assert_eq!(segments, vec![dir.path().join("log.1.sz")]);
+        assert_eq!(read_log(&path), "bbbbcc");
+    }
+
+    #[test]
+    /// Truncated logs only keep the latest output and never exceed their maximum size.
+    fn test_log_truncation() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("log");
+        let mut log = create_log(&path, Some((8, LogRotation::Truncate)));
+        log.write_all(b"aaaabbbbccccdd").unwrap();
+
+        assert_eq!(read_log(&path), "ccccdd");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Threads, whose stream is kept open after the task finished, are detached.
+    fn test_wait_for_open_output() {
+        let dir = tempdir().unwrap();
+        let (reader, _writer) = UnixStream::pair().unwrap();
+        let thread = spawn_output_thread(reader, create_log(&dir.path().join("log"), None), None);
+        let detached = thread.detached.clone();
+
+        wait_for_output(vec![thread]);
+        assert!(detached.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Detached threads don't write any further output to the log.
+    fn test_detached_output() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("log");
+        let (reader, mut writer) = UnixStream::pair().unwrap();
+        let thread = spawn_output_thread(reader, create_log(&path, None), None);
+
+        thread.detached.store(true, Ordering::SeqCst);
+        writer.write_all(b"leftover").unwrap();
+        drop(writer);
+        thread.done.recv().unwrap();
+
+        assert_eq!(read_to_string(&path).unwrap(), "");
+    }
+}
@@ -0,0 +1,250 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::ErrorKind;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread::sleep;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use log::{debug, info, warn};
+use nix::{
+    sys::signal::{self, Signal},
+    unistd::{write, Pid},
+};
+
+/// The controllers that are needed to limit the resources of tasks.
+const CONTROLLERS: [&str; 2] = ["memory", "cpu"];
+
+/// How often the removal of a cgroup is tried, while its processes are still exiting.
+const REMOVE_ATTEMPTS: usize = 10;
+
+/// The period of the `cpu.max` quota in microseconds.
+const CPU_PERIOD: u64 = 100_000;
+
+/// Prepare the delegated cgroup v2 directory, in which the cgroups of all tasks are created.
+///
+/// cgroups with enabled controllers mustn't contain any processes themselves.
+/// Hence, all processes inside the delegated cgroup (usually only the daemon) are moved into
+/// the `daemon` leaf cgroup, before the controllers are enabled for the children.
+pub fn init_cgroup(base: &Path) -> Result<()> {
+    if !base.join("cgroup.procs").exists() {
+        bail!("{:?} isn't a cgroup v2 directory", base);
+    }
+
+    let daemon_cgroup = base.join("daemon");
+    if !daemon_cgroup.exists() {
+        fs::create_dir(&daemon_cgroup)
+            .context(format!("Failed to create cgroup {:?}", daemon_cgroup))?;
+    }
+    let pids = fs::read_to_string(base.join("cgroup.procs"))?;
+    for pid in pids.lines() {
+        fs::write(daemon_cgroup.join("cgroup.procs"), pid).context(format!(
+            "Failed to move process {} into {:?}",
+            pid, daemon_cgroup
+        ))?;
+    }
+
+    let available = fs::read_to_string(base.join("cgroup.controllers"))?;
+    for controller in CONTROLLERS.iter() {
+        if !available.split_whitespace().any(|name| name == *controller) {
+            warn!(
+                "The {} controller isn't available in cgroup {:?}. Limits that need it will fail.",
+                controller, base
+            );
+            continue;
+        }
+        fs::write(
+            base.join("cgroup.subtree_control"),
+            format!("+{}", controller),
+        )
+        .context(format!("Failed to enable the {} controller", controller))?;
+    }
+
+    info!("Tasks are placed into cgroups below {:?}", base);
+    Ok(())
+}
+
+/// Get the path of the cgroup of a specific task.
+pub fn get_task_cgroup(base: &Path, task_id: usize) -> PathBuf {
+    base.join(format!("task-{}", task_id))
+}
+
+/// Create the cgroup of a task and apply its memory (in bytes) and CPU limits.
+/// A leftover cgroup of a previous task with the same id is reused.
+pub fn create_task_cgroup(
+    base: &Path,
+    task_id: usize,
+    memory_limit: Option<u64>,
+    cpu_limit: Option<f64>,
+) -> Result<PathBuf> {
+    let cgroup = get_task_cgroup(base, task_id);
+    if !cgroup.exists() {
+        fs::create_dir(&cgroup).context(format!("Failed to create cgroup {:?}", cgroup))?;
+    }
+
+    let result = apply_limits(&cgroup, memory_limit, cpu_limit);
+    if result.is_err() {
+        remove_task_cgroup(&cgroup);
+    }
+    result.map(|_| cgroup)
+}
+
+fn apply_limits(cgroup: &Path, memory_limit: Option<u64>, cpu_limit: Option<f64>) -> Result<()> {
+    // Only change files of controllers that are actually needed.
+    // This allows tasks without limits to run, even if no controller is available.
+    let has_memory = cgroup.join("memory.max").exists();
+    if memory_limit.is_some() && !has_memory {
+        bail!(
+            "The memory controller isn't enabled for cgroup {:?}",
+            cgroup
+        );
+    }
+    if has_memory {
+        let memory_max = match memory_limit {
+            Some(bytes) => bytes.to_string(),
+            None => "max".to_string(),
+        };
+        fs::write(cgroup.join("memory.max"), memory_max)
+            .context("Failed to set the memory limit")?;
+        // Don't let the task evade its memory limit by swapping.
+        // Kill all of its processes at once, if the limit is exceeded.
+        // Both files don't exist on all systems, which is why errors are ignored.
+        let _ = fs::write(cgroup.join("memory.swap.max"), "0");
+        let _ = fs::write(cgroup.join("memory.oom.group"), "1");
+    }
+
+    let has_cpu = cgroup.join("cpu.max").exists();
+    if cpu_limit.is_some() && !has_cpu {
+        bail!("The cpu controller isn't enabled for cgroup {:?}", cgroup);
+    }
+    if has_cpu {
+        let cpu_max = match cpu_limit {
+            Some(cpus) => format!(
+                "{} {}",
+                (cpus * CPU_PERIOD as f64).round() as u64,
+                CPU_PERIOD
+            ),
+            None => "max".to_string(),
+        };
+        fs::write(cgroup.join("cpu.max"), cpu_max).context("Failed to set the CPU limit")?;
+    }
+
+    Ok(())
+}
+
+/// Let the process of the command join the given cgroup, right before it executes the command.
+/// Since this happens before exec, all children of the task end up in the cgroup as well.
+pub fn join_cgroup(command: &mut Command, cgroup: &Path) -> Result<()> {
+    let procs: File = OpenOptions::new()
+        .write(true)
+        .open(cgroup.join("cgroup.procs"))
+        .context(format!("Failed to open {:?}", cgroup))?;
+
+    // Safety: `write` is async-signal-safe and thereby allowed between fork and exec.
+    // Writing "0" to `cgroup.procs` moves the writing process into that cgroup.
+    unsafe {
+        command.pre_exec(move || {
+            write(procs.as_raw_fd(), b"0").map_err(|_| std::io::Error::last_os_error())?;
+            Ok(())
+        });
+    }
+
+    Ok(())
+}
+
+/// Check whether any process of the cgroup has been killed by the OOM killer.
+pub fn was_oom_killed(cgroup: &Path) -> bool {
+    let events = match fs::read_to_string(cgroup.join("memory.events")) {
+        Ok(events) => events,
+        Err(_) => return false,
+    };
+
+    events.lines().any(|line| match line.split_once(' ') {
+        Some(("oom_kill", count)) => count.trim().parse::<u64>().unwrap_or(0) > 0,
+        _ => false,
+    })
+}
+
+/// Kill all processes in a cgroup.
+/// This includes processes that left the task's process group, e.g. by starting a new session.
+pub fn kill_task_cgroup(cgroup: &Path) {
+    if !cgroup.exists() {
+        return;
+    }
+
+    // `cgroup.kill` only exists since Linux 5.14.
+    if fs::write(cgroup.join("cgroup.kill"), "1").is_ok() {
+        return;
+    }
+
+    let pids = match fs::read_to_string(cgroup.join("cgroup.procs")) {
+        Ok(pids) => pids,
+        Err(error) => {
+            warn!("Failed to read processes of cgroup {:?}: {}", cgroup, error);
+            return;
+        }
+    };
+    for pid in pids.lines().filter_map(|pid| pid.parse::<i32>().ok()) {
+        if let Err(error) = signal::kill(Pid::from_raw(pid), Signal::SIGKILL) {
+            debug!("Failed to kill process {}: {:?}", pid, error);
+        }
+    }
+}
+
+/// Remove the cgroup of a finished task.
+/// This fails, as long as processes of the task are still alive.
+/// Since killed processes need a moment to actually exit, the removal is retried a few times.
+pub fn remove_task_cgroup(cgroup: &Path) {
+    let mut attempts = 0;
+    while let Err(error) = fs::remove_dir(cgroup) {
+        if error.kind() == ErrorKind::NotFound {
+            return;
+        }
+        attempts += 1;
+        if attempts >= REMOVE_ATTEMPTS {
+            warn!("Failed to remove cgroup {:?}: {}", cgroup, error);
+            return;
+        }
+        sleep(Duration::from_millis(10));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    /// Limits are only written to the files of available controllers.
+    fn test_apply_limits() {
+        let cgroup = tempdir().unwrap();
+        let path = cgroup.path();
+        fs::write(path.join("cpu.max"), "max 100000").unwrap();
+
+        apply_limits(path, None, Some(1.5)).unwrap();
+        assert_eq!(
+            fs::read_to_string(path.join("cpu.max")).unwrap(),
+            "150000 100000"
+        );
+
+        // The memory controller isn't available.
+        assert!(apply_limits(path, Some(1024), None).is_err());
+        assert!(!path.join("memory.max").exists());
+    }
+
+    #[test]
+    fn test_was_oom_killed() {
+        let cgroup = tempdir().unwrap();
+        let path = cgroup.path();
+        assert!(!was_oom_killed(path));
+
+        fs::write(path.join("memory.events"), "oom 0\noom_kill 0\n").unwrap();
+        assert!(!was_oom_killed(path));
+
+        fs::write(path.join("memory.events"), "oom 1\noom_kill 2\n").unwrap();
+        assert!(was_oom_killed(path));
+    }
+}
@@ -0,0 +1,95 @@
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_io::Async;
+use async_std::future::timeout;
+use nix::{
+    sys::inotify::{AddWatchFlags, InitFlags, Inotify},
+    unistd::close,
+};
+
+/// Watches a file or directory for changes via inotify.
+/// Directories also report changes of the files inside of them.
+pub struct FileWatcher {
+    inotify: Async<InotifyHandle>,
+}
+
+/// Closes the inotify instance, once it's no longer needed.
+struct InotifyHandle(Inotify);
+
+impl AsRawFd for InotifyHandle {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl Drop for InotifyHandle {
+    fn drop(&mut self) {
+        let _ = close(self.0.as_raw_fd());
+    }
+}
+
+impl FileWatcher {
+    pub fn new(path: &Path) -> Result<FileWatcher> {
+        let handle = InotifyHandle(
+            Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)
+                .context("Failed to initialize inotify")?,
+        );
+        // Removing the file changes its link count, which is reported as `IN_ATTRIB`.
+        let flags = AddWatchFlags::IN_MODIFY
+            | AddWatchFlags::IN_CREATE
+            | AddWatchFlags::IN_CLOSE_WRITE
+            | AddWatchFlags::IN_ATTRIB
+            | AddWatchFlags::IN_MOVE_SELF
+            | AddWatchFlags::IN_DELETE_SELF;
+        handle
+            .0
+            .add_watch(path, flags)
+            .context(format!("Failed to watch {:?}", path))?;
+
+        Ok(FileWatcher {
+            inotify: Async::new(handle)?,
+        })
+    }
+
+    /// Wait until the file changes or the timeout passes.
+    pub async fn wait(&self, duration: Duration) {
+        if timeout(duration, self.inotify.readable()).await.is_ok() {
+            // The events themselves don't matter, they only tell us that something changed.
+            let _ = self.inotify.get_ref().0.read_events();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::time::Instant;
+
+    use async_std::task::block_on;
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    #[test]
+    /// Waiting ends as soon as the file is changed.
+    fn test_wait_for_change() {
+        let file = NamedTempFile::new().unwrap();
+        let watcher = FileWatcher::new(file.path()).unwrap();
+
+        let mut writer = OpenOptions::new().append(true).open(file.path()).unwrap();
+        writer.write_all(b"output").unwrap();
+
+        let start = Instant::now();
+        block_on(watcher.wait(Duration::from_secs(10)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // All events have been consumed, so we wait for the timeout this time.
+        let start = Instant::now();
+        block_on(watcher.wait(Duration::from_millis(200)));
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
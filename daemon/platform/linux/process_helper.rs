@@ -1,25 +1,154 @@
-use std::convert::TryInto;
-use std::process::{Child, Command};
+use std::convert::{TryFrom, TryInto};
+use std::ffi::OsStr;
+use std::io;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, ExitStatus};
+use std::str::FromStr;
 
 use anyhow::{bail, Result};
 use log::{debug, info, warn};
 use nix::{
+    libc,
     sys::signal::{self, Signal},
-    unistd::Pid,
+    unistd::{getpgid, setsid, Pid},
 };
 use procfs::process::{all_processes, Process};
 
-use crate::task_handler::ProcessAction;
+use pueue_lib::io_priority::IoPriority;
+use pueue_lib::task::ResourceUsage;
 
+use crate::task_handler::{FinishedProcess, ProcessAction, ProcessLimits};
+
+// The constants of the `ioprio_set` syscall, which aren't provided by libc.
+const IOPRIO_WHO_PROCESS: i32 = 1;
+const IOPRIO_CLASS_SHIFT: i32 = 13;
+const IOPRIO_CLASS_RT: i32 = 1;
+const IOPRIO_CLASS_BE: i32 = 2;
+const IOPRIO_CLASS_IDLE: i32 = 3;
+
+/// Create the command of a task, which is executed by the posix shell `sh`.
+///
+/// The task is spawned in a new session and thereby in its own process group.
+/// This allows us to send signals to the whole process tree of a task at once,
+/// including processes that have been reparented, e.g. by double-forking daemons.
 pub fn compile_shell_command(command_string: &str) -> Command {
-    let mut command = Command::new("sh");
-    command.arg("-c").arg(command_string);
+    compile_command(&["sh", "-c", command_string])
+}
+
+/// Create a command from the program and its arguments, which is executed without any shell.
+/// Just like commands executed by a shell, it's spawned in a new session.
+pub fn compile_command<S: AsRef<OsStr>>(arguments: &[S]) -> Command {
+    let mut command = Command::new(&arguments[0]);
+    command.args(&arguments[1..]);
+
+    // Safety: `setsid` is async-signal-safe and thereby allowed between fork and exec.
+    unsafe {
+        command.pre_exec(|| {
+            setsid().map_err(|_| io::Error::last_os_error())?;
+            Ok(())
+        });
+    }
 
     command
 }
 
+/// Apply the scheduling priorities and resource limits of a task to its process.
+/// They're applied right before the command is executed and are inherited by all its children.
+/// Errors of the underlying syscalls are thereby returned when the command is spawned.
+pub fn set_process_limits(command: &mut Command, limits: ProcessLimits) -> Result<()> {
+    if let Some(ionice) = limits.ionice.filter(|ionice| !ionice.is_valid()) {
+        bail!("Invalid I/O priority {}", ionice);
+    }
+
+    // Safety: `setpriority`, `setrlimit` and raw syscalls are async-signal-safe and thereby
+    // allowed between fork and exec.
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(nice) = limits.nice {
+                if libc::setpriority(libc::PRIO_PROCESS as _, 0, nice) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if let Some(priority) = limits.ionice {
+                let value = match priority {
+                    IoPriority::Realtime(level) => {
+                        IOPRIO_CLASS_RT << IOPRIO_CLASS_SHIFT | level as i32
+                    }
+                    IoPriority::BestEffort(level) => {
+                        IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT | level as i32
+                    }
+                    IoPriority::Idle => IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+                };
+                if libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, value) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if let Some(files) = limits.max_open_files {
+                set_rlimit(libc::RLIMIT_NOFILE as _, files, files)?;
+            }
+            if let Some(seconds) = limits.max_cpu_time {
+                // The hard limit is a bit higher, so the process receives SIGXCPU before SIGKILL.
+                set_rlimit(libc::RLIMIT_CPU as _, seconds, seconds.saturating_add(1))?;
+            }
+            Ok(())
+        });
+    }
+
+    Ok(())
+}
+
+/// Set the soft and the hard limit of a resource of the current process.
+fn set_rlimit(resource: i32, soft: u64, hard: u64) -> io::Result<()> {
+    let rlimit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    if unsafe { libc::setrlimit(resource as _, &rlimit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Check whether a child process finished without blocking and reap it.
+/// Returns its exit status and the resources used by it and all of its awaited children.
+pub fn try_wait_with_usage(child: &mut Child) -> io::Result<Option<FinishedProcess>> {
+    let pid: libc::pid_t = child.id().try_into().unwrap();
+    let mut status: libc::c_int = 0;
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+
+    match unsafe { libc::wait4(pid, &mut status, libc::WNOHANG, &mut usage) } {
+        0 => Ok(None),
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(Some((
+            ExitStatus::from_raw(status),
+            Some(get_resource_usage(&usage)),
+        ))),
+    }
+}
+
+/// Convert the `rusage` of a process into Pueue's representation.
+fn get_resource_usage(usage: &libc::rusage) -> ResourceUsage {
+    let seconds = |time: libc::timeval| time.tv_sec as f64 + time.tv_usec as f64 / 1_000_000.0;
+
+    ResourceUsage {
+        user_time: seconds(usage.ru_utime),
+        system_time: seconds(usage.ru_stime),
+        // Linux reports the peak resident set size in KiB.
+        max_rss: usage.ru_maxrss as u64 * 1024,
+        block_input: usage.ru_inblock as u64,
+        block_output: usage.ru_oublock as u64,
+        voluntary_context_switches: usage.ru_nvcsw as u64,
+        involuntary_context_switches: usage.ru_nivcsw as u64,
+    }
+}
+
 /// Send a signal to one of Pueue's child process handles.
 ///
+/// Tasks are spawned in their own process group (see `compile_shell_command`).
+/// In that case, the signal is simply sent to the whole process group.
+/// Since all processes of the task receive the signal anyway, `send_to_children` is ignored.
+///
+/// Processes that don't lead their own process group are handled as described below.
 /// There are two scenarios:
 ///
 /// **Normal case**
@@ -29,7 +158,7 @@ pub fn compile_shell_command(command_string: &str) -> Command {
 /// Since the posix shell doesn't propagate any process signals to its children, we have to:
 /// 1. Send the signal to the shell.
 /// 2. Send the signal directly to the children.
-///     In our case this would be the `sleep 60` child process.
+///    In our case this would be the `sleep 60` child process.
 ///
 /// If the user also want's to send the signal to all child processes of the task,
 /// we have to get all child-processes of the child process.
@@ -57,6 +186,12 @@ pub fn send_signal_to_child(
 ) -> Result<bool> {
     let signal = get_signal_from_action(action);
     let pid: i32 = child.id().try_into().unwrap();
+    if is_process_group_leader(pid) {
+        debug!("Sending signal {} to process group {}", signal, pid);
+        signal::killpg(Pid::from_raw(pid), signal)?;
+        return Ok(true);
+    }
+
     // Check whether this process actually spawned a shell.
     let is_shell = if let Ok(is_shell) = did_process_spawn_shell(pid) {
         is_shell
@@ -91,13 +226,24 @@ pub fn send_signal_to_child(
 /// This is a helper function to safely kill a child process.
 /// Its purpose is to properly kill all processes and prevent any dangling processes.
 ///
-/// Sadly, this needs some extra handling. Check the docstring of `send_signal_to_child` for
+/// Processes that lead their own process group are killed together with their whole group.
+/// Other processes need some extra handling. Check the docstring of `send_signal_to_child` for
 /// additional information on why this needs to be done.
 ///
 /// Returns `true`, if everything went alright
 /// Returns `false`, if the process went away while we tried to send the signal.
 pub fn kill_child(task_id: usize, child: &mut Child, kill_children: bool) -> bool {
     let pid: i32 = child.id().try_into().unwrap();
+    if is_process_group_leader(pid) {
+        if let Err(error) = signal::killpg(Pid::from_raw(pid), Signal::SIGKILL) {
+            info!(
+                "Task {} has already finished by itself: {:?}",
+                task_id, error
+            );
+            return false;
+        }
+        return true;
+    }
 
     // Check whether this process actually spawned a shell.
     let is_shell = if let Ok(is_shell) = did_process_spawn_shell(pid) {
@@ -151,7 +297,15 @@ pub fn kill_child(task_id: usize, child: &mut Child, kill_children: bool) -> boo
     true
 }
 
-/// Check whether a process's commandline string is actually a shell or not
+/// Check whether a process leads its own process group.
+/// This is the case for all tasks, since they're spawned in their own session.
+fn is_process_group_leader(pid: i32) -> bool {
+    let pid = Pid::from_raw(pid);
+    matches!(getpgid(Some(pid)), Ok(pgid) if pgid == pid)
+}
+
+/// Check whether a process's commandline string is actually a shell or not.
+/// This is only needed for processes that don't lead their own process group.
 fn did_process_spawn_shell(pid: i32) -> Result<bool> {
     // Get the /proc representation of the child, so we can do some checks
     let process = if let Ok(process) = Process::new(pid) {
@@ -254,9 +408,26 @@ fn get_signal_from_action(action: &ProcessAction) -> Signal {
         ProcessAction::Kill => Signal::SIGKILL,
         ProcessAction::Pause => Signal::SIGSTOP,
         ProcessAction::Resume => Signal::SIGCONT,
+        ProcessAction::Signal(signal) => get_signal(signal),
     }
 }
 
+/// Convert Pueue's platform independent signal into the respective unix signal.
+fn get_signal(signal: &pueue_lib::signal::Signal) -> Signal {
+    Signal::from_str(signal.name()).expect("All of Pueue's signals exist on unix")
+}
+
+/// Get the name of the signal that ended a process, if it has been ended by one.
+pub fn get_exit_signal(status: &ExitStatus) -> Option<String> {
+    let signal = status.signal()?;
+    let name = match Signal::try_from(signal) {
+        Ok(signal) => signal.as_str().to_string(),
+        Err(_) => signal.to_string(),
+    };
+
+    Some(name)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -271,6 +442,15 @@ mod tests {
         }
     }
 
+    /// Get all living processes of a process group.
+    fn get_process_group(pgid: i32) -> Vec<Process> {
+        all_processes()
+            .unwrap()
+            .into_iter()
+            .filter(|process| process.stat.pgrp == pgid && process.is_alive())
+            .collect()
+    }
+
     #[test]
     fn test_spawn_command() {
         let mut child = compile_shell_command("echo 'this is a test'")
@@ -282,6 +462,55 @@ mod tests {
         assert!(ecode.success());
     }
 
+    #[test]
+    /// Ensure that finished processes are reaped together with their resource usage.
+    fn test_try_wait_with_usage() {
+        let mut child = compile_shell_command("head -c 10000000 /dev/zero | wc -c; exit 3")
+            .spawn()
+            .expect("Failed to spawn command");
+
+        let (status, usage) = loop {
+            if let Some(finished) = try_wait_with_usage(&mut child).unwrap() {
+                break finished;
+            }
+            sleep(Duration::from_millis(50));
+        };
+
+        assert_eq!(status.code(), Some(3));
+        let usage = usage.expect("No resource usage for finished process");
+        assert!(usage.max_rss > 0);
+        assert!(usage.user_time + usage.system_time > 0.0);
+    }
+
+    #[test]
+    /// Ensure that priorities and limits are applied to the process of a task.
+    fn test_process_limits() {
+        let mut command =
+            compile_shell_command("ulimit -n; ulimit -t; cut -d' ' -f19 /proc/$$/stat");
+        let limits = ProcessLimits {
+            nice: Some(10),
+            max_open_files: Some(64),
+            max_cpu_time: Some(60),
+            ..Default::default()
+        };
+        set_process_limits(&mut command, limits).unwrap();
+
+        let output = command.output().expect("Failed to spawn command");
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "64\n60\n10\n");
+    }
+
+    #[test]
+    /// Ensure that I/O priorities with an unsupported level are rejected.
+    fn test_invalid_io_priority() {
+        let mut command = compile_shell_command("true");
+        let limits = ProcessLimits {
+            ionice: Some(IoPriority::BestEffort(8)),
+            ..Default::default()
+        };
+        assert!(set_process_limits(&mut command, limits).is_err());
+    }
+
     #[test]
     /// Ensure a `sh -c` command will be properly killed without detached processes.
     fn test_shell_command_is_killed() {
@@ -419,4 +648,108 @@ mod tests {
             assert!(process_is_gone(child_process.stat.pid));
         }
     }
+
+    #[test]
+    /// Ensure that a command without a shell is executed directly in its own process group.
+    fn test_command_without_shell_is_killed() {
+        let mut child = compile_command(&["sleep", "60"])
+            .spawn()
+            .expect("Failed to spawn sleep");
+        let pid: i32 = child.id().try_into().unwrap();
+        // Sleep a little to give everything a chance to spawn.
+        sleep(Duration::from_millis(500));
+
+        // The task's process is the command itself.
+        assert!(!did_process_spawn_shell(pid).unwrap());
+        assert_eq!(Process::new(pid).unwrap().stat.pgrp, pid);
+
+        // Kill the process and make sure it'll be killed.
+        assert!(kill_child(0, &mut child, false));
+
+        // Sleep a little to give all processes time to shutdown.
+        sleep(Duration::from_millis(500));
+
+        assert!(process_is_gone(pid));
+    }
+
+    #[test]
+    /// Ensure that a shell command can be sent an arbitrary signal
+    /// and that the signal, which ended the process, is detected.
+    fn test_shell_command_is_signaled() {
+        let mut child = compile_shell_command("sleep 60 && echo 'this is a test'")
+            .spawn()
+            .expect("Failed to spawn echo");
+        // Sleep a little to give everything a chance to spawn.
+        sleep(Duration::from_millis(500));
+
+        let action = ProcessAction::Signal(pueue_lib::signal::Signal::Term);
+        assert!(send_signal_to_child(&child, &action, false).unwrap());
+
+        let status = child.wait().expect("Failed to wait on child");
+        assert_eq!(status.code(), None);
+        assert_eq!(get_exit_signal(&status), Some("SIGTERM".to_string()));
+    }
+
+    #[test]
+    /// Ensure that the processes of double-forking daemons are killed as well.
+    /// They have been reparented and are thereby no longer children of the task,
+    /// but they're still part of the task's process group.
+    fn test_double_forked_process_is_killed() {
+        let mut child = compile_shell_command("(sleep 60 &) && sleep 60")
+            .spawn()
+            .expect("Failed to spawn echo");
+        let pid: i32 = child.id().try_into().unwrap();
+        // Sleep a little to give everything a chance to spawn.
+        sleep(Duration::from_millis(500));
+
+        // The task leads its own process group.
+        assert!(is_process_group_leader(pid));
+
+        // The double-forked process can only be found via the process group.
+        assert_eq!(get_child_processes(pid).len(), 1);
+        let group_processes = get_process_group(pid);
+        assert_eq!(group_processes.len(), 3);
+
+        // Kill the process and make sure it'll be killed.
+        assert!(kill_child(0, &mut child, false));
+
+        // Sleep a little to give all processes time to shutdown.
+        sleep(Duration::from_millis(500));
+
+        // Assert that all processes of the group have been killed.
+        for process in group_processes {
+            assert!(process_is_gone(process.stat.pid));
+        }
+    }
+
+    #[test]
+    /// Ensure that pausing and resuming a task also affects the processes of double-forking daemons.
+    fn test_double_forked_process_is_paused() {
+        let mut child = compile_shell_command("(sleep 60 &) && sleep 60")
+            .spawn()
+            .expect("Failed to spawn echo");
+        let pid: i32 = child.id().try_into().unwrap();
+        // Sleep a little to give everything a chance to spawn.
+        sleep(Duration::from_millis(500));
+
+        // Pause the task and make sure all processes of its group have been stopped.
+        assert!(send_signal_to_child(&child, &ProcessAction::Pause, false).unwrap());
+        sleep(Duration::from_millis(500));
+        let group_processes = get_process_group(pid);
+        assert_eq!(group_processes.len(), 3);
+        for process in &group_processes {
+            let state = Process::new(process.stat.pid).unwrap().stat.state;
+            assert_eq!(state, 'T');
+        }
+
+        // Resume the task and make sure all processes are running again.
+        assert!(send_signal_to_child(&child, &ProcessAction::Resume, false).unwrap());
+        sleep(Duration::from_millis(500));
+        for process in &group_processes {
+            let state = Process::new(process.stat.pid).unwrap().stat.state;
+            assert_ne!(state, 'T');
+        }
+
+        assert!(kill_child(0, &mut child, false));
+    }
 }
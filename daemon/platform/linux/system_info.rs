@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use log::warn;
+use procfs::process::all_processes;
+use procfs::{page_size, ticks_per_second, LoadAverage, Meminfo};
+
+/// Get the system's load average over the last minute.
+pub fn get_load_average() -> Option<f64> {
+    match LoadAverage::new() {
+        Ok(load) => Some(load.one as f64),
+        Err(error) => {
+            warn!("Failed to read load average: {}", error);
+            None
+        }
+    }
+}
+
+/// Get the amount of memory in bytes, that's available for new processes.
+pub fn get_free_memory() -> Option<u64> {
+    match Meminfo::new() {
+        // `MemAvailable` only exists since Linux 3.14.
+        Ok(meminfo) => Some(meminfo.mem_available.unwrap_or(meminfo.mem_free)),
+        Err(error) => {
+            warn!("Failed to read memory info: {}", error);
+            None
+        }
+    }
+}
+
+/// Get the accumulated CPU time in seconds and the resident set size in bytes of a process
+/// and all of its descendants. Processes that left the tree but are still in the process'
+/// process group, such as double-forked daemons, are included as well.
+pub fn get_process_tree_usage(pid: u32) -> Option<(f64, u64)> {
+    let processes = match all_processes() {
+        Ok(processes) => processes,
+        Err(error) => {
+            warn!("Failed to read processes: {}", error);
+            return None;
+        }
+    };
+    let ticks_per_second = ticks_per_second().ok()? as f64;
+    let page_size = page_size().ok()? as u64;
+
+    let pid = pid as i32;
+    let parents: HashMap<i32, i32> = processes
+        .iter()
+        .map(|process| (process.stat.pid, process.stat.ppid))
+        .collect();
+    let is_descendant = |mut current: i32| {
+        while current > 1 {
+            if current == pid {
+                return true;
+            }
+            current = parents.get(&current).copied().unwrap_or(0);
+        }
+        false
+    };
+
+    let mut found = false;
+    let mut ticks = 0;
+    let mut rss = 0;
+    for process in processes.iter() {
+        if process.stat.pgrp != pid && !is_descendant(process.stat.pid) {
+            continue;
+        }
+        found = true;
+        ticks += process.stat.utime + process.stat.stime;
+        rss += process.stat.rss as u64 * page_size;
+    }
+
+    if !found {
+        return None;
+    }
+    Some((ticks as f64 / ticks_per_second, rss))
+}
@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Result};
+
+/// cgroups are only supported on Linux.
+pub fn init_cgroup(_base: &Path) -> Result<()> {
+    bail!("cgroups are only supported on Linux")
+}
+
+/// Get the path of the cgroup of a specific task.
+pub fn get_task_cgroup(base: &Path, task_id: usize) -> PathBuf {
+    base.join(format!("task-{}", task_id))
+}
+
+/// cgroups are only supported on Linux.
+pub fn create_task_cgroup(
+    _base: &Path,
+    _task_id: usize,
+    _memory_limit: Option<u64>,
+    _cpu_limit: Option<f64>,
+) -> Result<PathBuf> {
+    bail!("cgroups are only supported on Linux")
+}
+
+/// cgroups are only supported on Linux.
+pub fn join_cgroup(_command: &mut Command, _cgroup: &Path) -> Result<()> {
+    bail!("cgroups are only supported on Linux")
+}
+
+/// cgroups are only supported on Linux.
+pub fn was_oom_killed(_cgroup: &Path) -> bool {
+    false
+}
+
+/// cgroups are only supported on Linux.
+pub fn kill_task_cgroup(_cgroup: &Path) {}
+
+/// cgroups are only supported on Linux.
+pub fn remove_task_cgroup(_cgroup: &Path) {}
@@ -0,0 +1,17 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+/// Watching files is only supported on Linux. Callers fall back to polling.
+pub struct FileWatcher;
+
+impl FileWatcher {
+    /// Watching files is only supported on Linux.
+    pub fn new(_path: &Path) -> Result<FileWatcher> {
+        bail!("Watching files is only supported on Linux")
+    }
+
+    /// Watching files is only supported on Linux.
+    pub async fn wait(&self, _duration: Duration) {}
+}
@@ -1,24 +1,124 @@
-use std::convert::TryInto;
-use std::process::{Child, Command};
+use std::convert::{TryFrom, TryInto};
+use std::ffi::OsStr;
+use std::io;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, ExitStatus};
+use std::str::FromStr;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use log::debug;
 use nix::{
+    libc,
     sys::signal::{self, Signal},
-    unistd::Pid,
+    unistd::{getpgid, setsid, Pid},
 };
 
-use crate::task_handler::ProcessAction;
+use pueue_lib::task::ResourceUsage;
 
+use crate::task_handler::{FinishedProcess, ProcessAction, ProcessLimits};
+
+/// Create the command of a task, which is executed by the posix shell `sh`.
+/// The task is spawned in a new session and thereby in its own process group.
 pub fn compile_shell_command(command_string: &str) -> Command {
-    let mut command = Command::new("sh");
-    command.arg("-c").arg(command_string);
+    compile_command(&["sh", "-c", command_string])
+}
+
+/// Create a command from the program and its arguments, which is executed without any shell.
+/// Just like commands executed by a shell, it's spawned in a new session.
+pub fn compile_command<S: AsRef<OsStr>>(arguments: &[S]) -> Command {
+    let mut command = Command::new(&arguments[0]);
+    command.args(&arguments[1..]);
+
+    // Safety: `setsid` is async-signal-safe and thereby allowed between fork and exec.
+    unsafe {
+        command.pre_exec(|| {
+            setsid().map_err(|_| io::Error::last_os_error())?;
+            Ok(())
+        });
+    }
 
     command
 }
 
+/// Apply the scheduling priorities and resource limits of a task to its process.
+/// They're applied right before the command is executed and are inherited by all its children.
+/// I/O priorities aren't supported on this platform.
+pub fn set_process_limits(command: &mut Command, limits: ProcessLimits) -> Result<()> {
+    if limits.ionice.is_some() {
+        bail!("I/O priorities are only supported on Linux");
+    }
+
+    // Safety: `setpriority` and `setrlimit` are async-signal-safe and thereby allowed
+    // between fork and exec.
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(nice) = limits.nice {
+                if libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if let Some(files) = limits.max_open_files {
+                set_rlimit(libc::RLIMIT_NOFILE, files, files)?;
+            }
+            if let Some(seconds) = limits.max_cpu_time {
+                // The hard limit is a bit higher, so the process receives SIGXCPU before SIGKILL.
+                set_rlimit(libc::RLIMIT_CPU, seconds, seconds.saturating_add(1))?;
+            }
+            Ok(())
+        });
+    }
+
+    Ok(())
+}
+
+/// Set the soft and the hard limit of a resource of the current process.
+fn set_rlimit(resource: libc::c_int, soft: u64, hard: u64) -> io::Result<()> {
+    let rlimit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    if unsafe { libc::setrlimit(resource, &rlimit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Check whether a child process finished without blocking and reap it.
+/// Returns its exit status and the resources used by it and all of its awaited children.
+pub fn try_wait_with_usage(child: &mut Child) -> io::Result<Option<FinishedProcess>> {
+    let pid: libc::pid_t = child.id().try_into().unwrap();
+    let mut status: libc::c_int = 0;
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+
+    match unsafe { libc::wait4(pid, &mut status, libc::WNOHANG, &mut usage) } {
+        0 => Ok(None),
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(Some((
+            ExitStatus::from_raw(status),
+            Some(get_resource_usage(&usage)),
+        ))),
+    }
+}
+
+/// Convert the `rusage` of a process into Pueue's representation.
+fn get_resource_usage(usage: &libc::rusage) -> ResourceUsage {
+    let seconds = |time: libc::timeval| time.tv_sec as f64 + time.tv_usec as f64 / 1_000_000.0;
+
+    ResourceUsage {
+        user_time: seconds(usage.ru_utime),
+        system_time: seconds(usage.ru_stime),
+        // MacOS reports the peak resident set size in bytes.
+        max_rss: usage.ru_maxrss as u64,
+        block_input: usage.ru_inblock as u64,
+        block_output: usage.ru_oublock as u64,
+        voluntary_context_switches: usage.ru_nvcsw as u64,
+        involuntary_context_switches: usage.ru_nivcsw as u64,
+    }
+}
+
 /// Send a signal to one of Pueue's child process handles.
-/// We need a special since we assume that there's also a `sh -c` around the actuall process.
+/// Tasks lead their own process group, in which case the signal is sent to the whole group.
+/// Otherwise, we assume that there's also a `sh -c` around the actual process.
 pub fn send_signal_to_child(
     child: &Child,
     action: &ProcessAction,
@@ -26,6 +126,11 @@ pub fn send_signal_to_child(
 ) -> Result<bool> {
     let signal = get_signal_from_action(action);
     let pid = child.id();
+    if is_process_group_leader(pid) {
+        signal::killpg(Pid::from_raw(pid.try_into().unwrap()), signal)?;
+        return Ok(true);
+    }
+
     // Send the signal to the shell, don't propagate to its children yet.
     send_signal_to_process(pid, action, false)?;
 
@@ -36,6 +141,16 @@ pub fn send_signal_to_child(
 /// This is a helper function to safely kill a child process.
 /// Its purpose is to properly kill all processes and prevent any dangling processes.
 pub fn kill_child(task_id: usize, child: &mut Child, _kill_children: bool) -> bool {
+    let pid = child.id();
+    if is_process_group_leader(pid) {
+        let pid = Pid::from_raw(pid.try_into().unwrap());
+        if signal::killpg(pid, Signal::SIGKILL).is_err() {
+            debug!("Task {} has already finished by itself", task_id);
+            return false;
+        }
+        return true;
+    }
+
     match child.kill() {
         Err(_) => {
             debug!("Task {} has already finished by itself", task_id);
@@ -45,6 +160,13 @@ pub fn kill_child(task_id: usize, child: &mut Child, _kill_children: bool) -> bo
     }
 }
 
+/// Check whether a process leads its own process group.
+/// This is the case for all tasks, since they're spawned in their own session.
+fn is_process_group_leader(pid: u32) -> bool {
+    let pid = Pid::from_raw(pid.try_into().unwrap());
+    matches!(getpgid(Some(pid)), Ok(pgid) if pgid == pid)
+}
+
 /// Send a signal to a unix process.
 fn send_signal_to_process(
     pid: u32,
@@ -63,9 +185,26 @@ fn get_signal_from_action(action: &ProcessAction) -> Signal {
         ProcessAction::Kill => Signal::SIGKILL,
         ProcessAction::Pause => Signal::SIGSTOP,
         ProcessAction::Resume => Signal::SIGCONT,
+        ProcessAction::Signal(signal) => get_signal(signal),
     }
 }
 
+/// Convert Pueue's platform independent signal into the respective unix signal.
+fn get_signal(signal: &pueue_lib::signal::Signal) -> Signal {
+    Signal::from_str(signal.name()).expect("All of Pueue's signals exist on unix")
+}
+
+/// Get the name of the signal that ended a process, if it has been ended by one.
+pub fn get_exit_signal(status: &ExitStatus) -> Option<String> {
+    let signal = status.signal()?;
+    let name = match Signal::try_from(signal) {
+        Ok(signal) => signal.as_str().to_string(),
+        Err(_) => signal.to_string(),
+    };
+
+    Some(name)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
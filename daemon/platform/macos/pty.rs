@@ -0,0 +1,104 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use nix::{
+    fcntl::{fcntl, FcntlArg, FdFlag},
+    libc,
+    pty::{openpty, Winsize},
+};
+
+/// The size of the pseudo terminal of tasks.
+const ROWS: u16 = 24;
+const COLUMNS: u16 = 80;
+
+/// The master side of the pseudo terminal of a task.
+pub struct Pty {
+    /// Input of the user, e.g. via `pueue send`, is written to the master.
+    master: File,
+}
+
+impl Pty {
+    /// Write input to the task, just as if it was typed into its terminal.
+    pub fn write_input(&mut self, input: &[u8]) -> io::Result<()> {
+        self.master.write_all(input)
+    }
+}
+
+/// Let the command run on a new pseudo terminal, which is owned by the daemon.
+///
+/// The terminal becomes the controlling terminal of the task's session and is used as
+/// stdin, stdout and stderr of the task.
+/// Returns the terminal and a reader for all output of the task.
+pub fn create_pty(command: &mut Command) -> Result<(Pty, File)> {
+    let size = Winsize {
+        ws_row: ROWS,
+        ws_col: COLUMNS,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let pty = openpty(&size, None).context("Failed to open a pseudo terminal")?;
+    // Safety: Both file descriptors have just been opened and aren't owned by anything else.
+    let (master, slave) = unsafe { (File::from_raw_fd(pty.master), File::from_raw_fd(pty.slave)) };
+
+    // Neither side of the terminal should be leaked to the task or any other child.
+    // The slave is explicitly passed as the task's stdio, which keeps it open for the task.
+    for file in [&master, &slave].iter() {
+        fcntl(file.as_raw_fd(), FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+    }
+
+    command
+        .stdin(Stdio::from(slave.try_clone()?))
+        .stdout(Stdio::from(slave.try_clone()?))
+        .stderr(Stdio::from(slave));
+
+    // Safety: `ioctl` is async-signal-safe and thereby allowed between fork and exec.
+    // This runs after the task has been moved into its own session and after its stdio has
+    // been set up, which makes the terminal on stdin the controlling terminal of the session.
+    unsafe {
+        command.pre_exec(|| {
+            if libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+
+    let reader = master.try_clone()?;
+
+    Ok((Pty { master }, reader))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::output::{spawn_output_thread, wait_for_output, TaskLog};
+    use crate::platform::process_helper::compile_shell_command;
+
+    #[test]
+    /// Tasks run on a terminal and their output is written to the log.
+    fn test_pty_output() {
+        let dir = tempdir().unwrap();
+        let log_path = dir.path().join("log");
+        let log = File::create(&log_path).unwrap();
+
+        let mut command = compile_shell_command("test -t 0 && test -t 1 && echo terminal");
+        let (_pty, reader) = create_pty(&mut command).unwrap();
+        let log = TaskLog::new(log, log_path.clone(), None);
+        let thread = spawn_output_thread(reader, log, None);
+        let status = command.status().unwrap();
+        // Close the slave of the parent process, so the output thread finishes.
+        drop(command);
+        wait_for_output(vec![thread]);
+
+        assert!(status.success());
+        assert_eq!(read_to_string(log_path).unwrap(), "terminal\r\n");
+    }
+}
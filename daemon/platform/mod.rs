@@ -1,15 +1,39 @@
 #[cfg(any(target_os = "linux", target_os = "freebsd"))]
 mod linux;
-#[cfg(any(target_os = "macos"))]
+#[cfg(target_os = "macos")]
 mod macos;
-#[cfg(any(target_os = "windows"))]
+#[cfg(target_os = "windows")]
 mod windows;
 
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub use self::linux::cgroup;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub use self::linux::file_watcher;
 #[cfg(any(target_os = "linux", target_os = "freebsd"))]
 pub use self::linux::process_helper;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub use self::linux::pty;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub use self::linux::system_info;
 
+#[cfg(target_os = "macos")]
+pub use self::macos::cgroup;
+#[cfg(target_os = "macos")]
+pub use self::macos::file_watcher;
 #[cfg(target_os = "macos")]
 pub use self::macos::process_helper;
+#[cfg(target_os = "macos")]
+pub use self::macos::pty;
+#[cfg(target_os = "macos")]
+pub use self::macos::system_info;
 
+#[cfg(target_os = "windows")]
+pub use self::windows::cgroup;
+#[cfg(target_os = "windows")]
+pub use self::windows::file_watcher;
 #[cfg(target_os = "windows")]
 pub use self::windows::process_helper;
+#[cfg(target_os = "windows")]
+pub use self::windows::pty;
+#[cfg(target_os = "windows")]
+pub use self::windows::system_info;
@@ -1 +1,5 @@
+pub mod cgroup;
+pub mod file_watcher;
 pub mod process_helper;
+pub mod pty;
+pub mod system_info;
@@ -1,4 +1,5 @@
-use std::process::{Child, Command};
+use std::ffi::OsStr;
+use std::process::{Child, Command, ExitStatus};
 
 use anyhow::{bail, Result};
 use log::{error, info, warn};
@@ -15,7 +16,9 @@ use winapi::um::tlhelp32::{
 };
 use winapi::um::winnt::{PROCESS_TERMINATE, THREAD_SUSPEND_RESUME};
 
-use crate::task_handler::ProcessAction;
+use pueue_lib::signal::Signal;
+
+use crate::task_handler::{FinishedProcess, ProcessAction, ProcessLimits};
 
 pub fn compile_shell_command(command_string: &str) -> Command {
     // Chain two `powershell` commands, one that sets the output encoding to utf8 and then the user provided one.
@@ -28,6 +31,33 @@ pub fn compile_shell_command(command_string: &str) -> Command {
     command
 }
 
+/// Create a command from the program and its arguments, which is executed without any shell.
+pub fn compile_command<S: AsRef<OsStr>>(arguments: &[S]) -> Command {
+    let mut command = Command::new(&arguments[0]);
+    command.args(&arguments[1..]);
+
+    command
+}
+
+/// Scheduling priorities and resource limits aren't supported on this platform yet.
+pub fn set_process_limits(_command: &mut Command, limits: ProcessLimits) -> Result<()> {
+    if limits.nice.is_some()
+        || limits.ionice.is_some()
+        || limits.max_open_files.is_some()
+        || limits.max_cpu_time.is_some()
+    {
+        bail!("Priorities and resource limits aren't supported on Windows");
+    }
+
+    Ok(())
+}
+
+/// Check whether a child process finished without blocking.
+/// The resource usage of processes isn't supported on this platform yet.
+pub fn try_wait_with_usage(child: &mut Child) -> std::io::Result<Option<FinishedProcess>> {
+    Ok(child.try_wait()?.map(|status| (status, None)))
+}
+
 /// Send a signal to a windows process.
 pub fn send_signal_to_child(
     child: &Child,
@@ -54,16 +84,24 @@ pub fn send_signal_to_child(
                 }
             }
         }
-        ProcessAction::Kill => {
+        ProcessAction::Kill | ProcessAction::Signal(Signal::Kill) => {
             for pid in pids {
                 terminate_process(pid);
             }
         }
+        ProcessAction::Signal(signal) => {
+            bail!("Sending {} isn't supported on Windows", signal);
+        }
     }
 
     Ok(true)
 }
 
+/// Processes on Windows aren't ended by signals.
+pub fn get_exit_signal(_status: &ExitStatus) -> Option<String> {
+    None
+}
+
 /// Kill a child process
 pub fn kill_child(task_id: usize, child: &mut Child, _kill_children: bool) -> bool {
     match child.kill() {
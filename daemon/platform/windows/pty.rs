@@ -0,0 +1,20 @@
+use std::fs::File;
+use std::io;
+use std::process::Command;
+
+use anyhow::{bail, Result};
+
+/// Pseudo terminals aren't supported on Windows.
+pub struct Pty;
+
+impl Pty {
+    /// Pseudo terminals aren't supported on Windows.
+    pub fn write_input(&mut self, _input: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Pseudo terminals aren't supported on Windows.
+pub fn create_pty(_command: &mut Command) -> Result<(Pty, File)> {
+    bail!("Pseudo terminals aren't supported on Windows")
+}
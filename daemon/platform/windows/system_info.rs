@@ -0,0 +1,14 @@
+/// Reading the load average isn't supported on this platform yet.
+pub fn get_load_average() -> Option<f64> {
+    None
+}
+
+/// Reading the free memory isn't supported on this platform yet.
+pub fn get_free_memory() -> Option<u64> {
+    None
+}
+
+/// Reading the resource usage of processes isn't supported on this platform yet.
+pub fn get_process_tree_usage(_pid: u32) -> Option<(f64, u64)> {
+    None
+}
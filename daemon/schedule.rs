@@ -0,0 +1,103 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::prelude::*;
+
+/// Calculate the next point in time after `after`, at which the given cron expression fires.
+///
+/// The expression uses the usual crontab format `minute hour day month weekday`.
+/// An additional leading field can be used to specify the seconds.
+pub fn next_run(expression: &str, after: &DateTime<Local>) -> Result<DateTime<Local>> {
+    let schedule = parse_expression(expression)?;
+    schedule
+        .after(after)
+        .next()
+        .context("This cron expression never fires")
+}
+
+/// Convert a crontab expression into the format of the `cron` crate and parse it.
+///
+/// The `cron` crate always expects a seconds field and counts weekdays from 1 (Sunday)
+/// to 7 (Saturday), while crontab counts from 0 (Sunday) to 7 (Sunday).
+fn parse_expression(expression: &str) -> Result<cron::Schedule> {
+    let mut fields: Vec<String> = expression.split_whitespace().map(String::from).collect();
+    match fields.len() {
+        5 => fields.insert(0, "0".into()),
+        6 => (),
+        count => bail!(
+            "Expected 5 or 6 fields in cron expression \"{}\", got {}",
+            expression,
+            count
+        ),
+    }
+    fields[5] = convert_weekdays(&fields[5]);
+
+    cron::Schedule::from_str(&fields.join(" "))
+        .map_err(|err| anyhow!("Invalid cron expression \"{}\": {}", expression, err))
+}
+
+/// Shift all numeric weekdays of a crontab weekday field by one.
+/// Names such as `MON-FRI` and anything we don't understand are passed through as they are.
+fn convert_weekdays(field: &str) -> String {
+    let shift = |day: u8| (day % 7 + 1).to_string();
+
+    let items: Vec<String> = field
+        .split(',')
+        .map(|item| {
+            let (range, step) = match item.find('/') {
+                Some(index) => item.split_at(index),
+                None => (item, ""),
+            };
+
+            let bounds: Vec<Option<u8>> = range.split('-').map(|day| day.parse().ok()).collect();
+            match bounds.as_slice() {
+                [Some(day)] => format!("{}{}", shift(*day), step),
+                // Ranges that end on Sunday wrap around in the `cron` crate's numbering.
+                [Some(0), Some(7)] => format!("*{}", step),
+                [Some(start), Some(7)] if step.is_empty() => format!("{}-7,1", shift(*start)),
+                [Some(start), Some(end)] if *end < 7 => {
+                    format!("{}-{}{}", shift(*start), shift(*end), step)
+                }
+                _ => item.to_string(),
+            }
+        })
+        .collect();
+
+    items.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crontab_format() {
+        // Tuesday, 2021-04-20 12:00:00
+        let now = Local.ymd(2021, 4, 20).and_hms(12, 0, 0);
+
+        let next = next_run("0 3 * * *", &now).unwrap();
+        assert_eq!(next, Local.ymd(2021, 4, 21).and_hms(3, 0, 0));
+
+        // Mondays to Fridays, crontab style.
+        let next = next_run("30 9 * * 1-5", &Local.ymd(2021, 4, 23).and_hms(12, 0, 0)).unwrap();
+        assert_eq!(next, Local.ymd(2021, 4, 26).and_hms(9, 30, 0));
+
+        // Sundays, both as 0 and 7.
+        let next = next_run("0 0 * * 0", &now).unwrap();
+        assert_eq!(next, Local.ymd(2021, 4, 25).and_hms(0, 0, 0));
+        let next = next_run("0 0 * * 6-7", &now).unwrap();
+        assert_eq!(next, Local.ymd(2021, 4, 24).and_hms(0, 0, 0));
+
+        // With a leading seconds field.
+        let next = next_run("*/10 * * * * *", &now).unwrap();
+        assert_eq!(next, Local.ymd(2021, 4, 20).and_hms(12, 0, 10));
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        let now = Local::now();
+        assert!(next_run("* * *", &now).is_err());
+        assert!(next_run("61 * * * *", &now).is_err());
+        assert!(next_run("0 3 * * funday", &now).is_err());
+    }
+}
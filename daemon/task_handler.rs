@@ -1,33 +1,79 @@
-use std::collections::{BTreeMap, HashMap};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs::{remove_file, File};
 use std::io::Write;
 use std::path::PathBuf;
-use std::process::Child;
 use std::process::Stdio;
+use std::process::{Child, Command, ExitStatus};
 use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use chrono::prelude::*;
 use handlebars::Handlebars;
 use log::{debug, error, info, warn};
 
+use pueue_lib::io_priority::IoPriority;
 use pueue_lib::log::*;
 use pueue_lib::network::message::*;
+use pueue_lib::settings::GroupDefaults;
+use pueue_lib::signal::Signal;
 use pueue_lib::state::{GroupStatus, SharedState};
-use pueue_lib::task::{Task, TaskResult, TaskStatus};
+use pueue_lib::task::{CurrentUsage, ResourceUsage, Task, TaskResult, TaskStatus};
 
+use crate::output::{spawn_output_thread, wait_for_output, OutputThread, TaskLog};
+use crate::platform::cgroup::*;
 use crate::platform::process_helper::*;
+use crate::platform::pty::{create_pty, Pty};
+use crate::platform::system_info::{get_free_memory, get_load_average, get_process_tree_usage};
+use crate::schedule::next_run;
+
+/// How often a task may be overtaken by smaller tasks, before it reserves its group.
+const MAX_OVERTAKES: usize = 3;
+
+/// How often the resource usage of running tasks is sampled.
+const USAGE_SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the system's load and free memory are checked against the load limits of groups.
+const LOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The seconds a task gets to exit after a graceful kill, if no grace period is specified.
+const DEFAULT_KILL_GRACE: u64 = 10;
 
 pub struct TaskHandler {
     state: SharedState,
     receiver: Receiver<Message>,
     children: BTreeMap<usize, Child>,
+    /// The pseudo terminals of running tasks, which have been started with `--pty`.
+    ptys: HashMap<usize, Pty>,
+    /// The threads that copy the output of running tasks into their logs, which only exist
+    /// for tasks whose output is piped through the daemon.
+    output_threads: HashMap<usize, Vec<OutputThread>>,
     callbacks: Vec<Child>,
+    /// Tasks that have been killed by the daemon, since they exceeded their timeout.
+    timed_out: HashSet<usize>,
+    /// Tasks that have been sent a graceful kill signal.
+    /// Contains the point in time at which they're killed with SIGKILL and whether their
+    /// children are killed as well.
+    kill_deadlines: HashMap<usize, (DateTime<Local>, bool)>,
+    /// How often queued tasks have been overtaken by smaller tasks of the same group,
+    /// since they needed more slots than were free.
+    overtaken: HashMap<usize, usize>,
+    /// The accumulated CPU time of each running task at the time of its previous usage sample.
+    cpu_times: HashMap<usize, (f64, Instant)>,
+    last_usage_sample: Instant,
+    last_load_check: Option<Instant>,
     full_reset: bool,
     // Some static settings that are extracted from `state.settings` for convenience purposes.
     pueue_directory: PathBuf,
     callback: Option<String>,
+    cgroup: Option<PathBuf>,
+    shell: Option<Vec<String>>,
+    combined_logs: bool,
+    group_defaults: BTreeMap<String, GroupDefaults>,
 }
 
 /// Pueue directly interacts with processes.
@@ -39,17 +85,72 @@ pub enum ProcessAction {
     Pause,
     Resume,
     Kill,
+    /// Send an arbitrary signal. This isn't supported on all platforms.
+    Signal(Signal),
+}
+
+/// The exit status of a reaped process and its resource usage, if the platform supports it.
+pub type FinishedProcess = (ExitStatus, Option<ResourceUsage>);
+
+/// Scheduling priorities and resource limits, which are applied to the process of a task.
+#[derive(Debug, Default)]
+pub struct ProcessLimits {
+    pub nice: Option<i32>,
+    pub ionice: Option<IoPriority>,
+    pub max_open_files: Option<u64>,
+    /// The maximum CPU time in seconds.
+    pub max_cpu_time: Option<u64>,
+}
+
+/// Compile the command of a task or callback.
+///
+/// If a shell is given, e.g. `["bash", "-c"]`, the command string is passed as its last argument.
+/// Shells that consist of a single program get a `-c`, just like shells specified via the cli.
+/// If `no_shell` is set, the command string is split like a posix shell would do it
+/// and the resulting program is executed directly, without a shell process in between.
+/// Otherwise, the default shell of the platform is used.
+fn compile_task_command(
+    command_string: &str,
+    shell: Option<&[String]>,
+    no_shell: bool,
+) -> Result<Command> {
+    let arguments = if no_shell {
+        shell_words::split(command_string)
+            .context("Failed to split the command into its arguments")?
+    } else if let Some(shell) = shell {
+        if shell.is_empty() {
+            bail!("The configured shell is empty");
+        }
+        let mut arguments = shell.to_vec();
+        if arguments.len() == 1 {
+            arguments.push("-c".to_string());
+        }
+        arguments.push(command_string.to_string());
+        arguments
+    } else {
+        return Ok(compile_shell_command(command_string));
+    };
+
+    if arguments.is_empty() {
+        bail!("The command is empty");
+    }
+
+    Ok(compile_command(&arguments))
 }
 
 impl TaskHandler {
     pub fn new(state: SharedState, receiver: Receiver<Message>) -> Self {
         // Extract some static settings we often need.
         // This prevents locking the State all the time.
-        let (pueue_directory, callback) = {
+        let (pueue_directory, callback, cgroup, shell, combined_logs, group_defaults) = {
             let state = state.lock().unwrap();
             (
                 state.settings.shared.pueue_directory.clone(),
                 state.settings.daemon.callback.clone(),
+                state.settings.daemon.cgroup.clone(),
+                state.settings.daemon.shell.clone(),
+                state.settings.daemon.combined_logs,
+                state.settings.daemon.group_defaults.clone(),
             )
         };
 
@@ -57,10 +158,22 @@ impl TaskHandler {
             state,
             receiver,
             children: BTreeMap::new(),
+            ptys: HashMap::new(),
+            output_threads: HashMap::new(),
             callbacks: Vec::new(),
+            timed_out: HashSet::new(),
+            kill_deadlines: HashMap::new(),
+            overtaken: HashMap::new(),
+            cpu_times: HashMap::new(),
+            last_usage_sample: Instant::now(),
+            last_load_check: None,
             full_reset: false,
             pueue_directory,
             callback,
+            cgroup,
+            shell,
+            combined_logs,
+            group_defaults,
         }
     }
 }
@@ -71,6 +184,9 @@ impl TaskHandler {
     ///
     /// - Receive and handle instructions from the client.
     /// - Handle finished tasks, i.e. cleanup processes, update statuses.
+    /// - Kill tasks that exceeded their timeout.
+    /// - Kill tasks with SIGKILL, that outlived the grace period of their graceful kill.
+    /// - Sample the resource usage of running tasks.
     /// - If the client requested a reset: reset the state if all children have been killed and handled.
     /// - Callback handling logic. This is rather uncritical.
     /// - Enqueue any stashed processes which are ready for being queued.
@@ -80,9 +196,13 @@ impl TaskHandler {
         loop {
             self.receive_commands();
             self.handle_finished_tasks();
+            self.check_timeouts();
+            self.check_kill_deadlines();
+            self.sample_usage();
             self.handle_reset();
             self.check_callbacks();
             self.enqueue_delayed_tasks();
+            self.check_schedules();
             self.check_failed_dependencies();
             if !self.full_reset {
                 self.check_new();
@@ -93,71 +213,110 @@ impl TaskHandler {
     /// Search and return the next task that can be started.
     /// Precondition for a task to be started:
     /// - is in Queued state
-    /// - There are free slots in the task's group
+    /// - There are enough free slots in the task's group
     /// - The group is running
-    /// - has all its dependencies in `Done` state
+    /// - all its dependencies are `Done` and meet their condition (succeeded by default)
+    /// - There's enough free capacity of all its named resources
+    ///
+    /// If multiple tasks can be started, the one with the highest priority is picked.
+    /// Tasks with the same priority are started in the order of their ids.
+    ///
+    /// Tasks that need more slots than are currently free are skipped, so smaller tasks can
+    /// use the remaining slots. Once a task has been overtaken `MAX_OVERTAKES` times,
+    /// it reserves its group and no other tasks of that group are started until it fits.
     fn get_next_task_id(&mut self) -> Option<usize> {
         let state = self.state.lock().unwrap();
-        // Check how many tasks are running in each group
-        let mut running_tasks_per_group: HashMap<String, usize> = HashMap::new();
+        // Forget about overtaken tasks, which have been removed or aren't queued any longer.
+        self.overtaken.retain(
+            |id, _| matches!(state.tasks.get(id), Some(task) if task.status == TaskStatus::Queued),
+        );
 
-        // Create a default group for tasks without an explicit group
-        running_tasks_per_group.insert("default".into(), 0);
+        // Check how many slots are used by running tasks in each group
+        let mut used_slots_per_group: HashMap<String, usize> = HashMap::new();
 
-        // Walk through all tasks and save the number of running tasks by group
+        // Walk through all tasks and sum up the used slots by group
         for (_, task) in state.tasks.iter() {
             // We are only interested in currently running tasks.
             if ![TaskStatus::Running, TaskStatus::Paused].contains(&task.status) {
                 continue;
             }
 
-            match running_tasks_per_group.get_mut(&task.group) {
-                Some(count) => {
-                    *count += 1;
-                }
-                None => {
-                    running_tasks_per_group.insert(task.group.clone(), 1);
-                }
-            }
+            *used_slots_per_group.entry(task.group.clone()).or_insert(0) += task.slots;
         }
 
-        state
+        // Named resources are shared across all groups.
+        let used_resources = state.used_resources();
+
+        // Get all tasks that could be started, if there were enough free slots in their group.
+        let mut candidates: Vec<&Task> = state
             .tasks
-            .iter()
-            .filter(|(_, task)| task.status == TaskStatus::Queued)
-            .filter(|(_, task)| {
+            .values()
+            .filter(|task| task.status == TaskStatus::Queued)
+            .filter(|task| {
                 // The task is assigned to a group.
-                // First let's check if the group is running. If it isn't, simply return false.
-                if !matches!(state.groups.get(&task.group), Some(GroupStatus::Running)) {
-                    return false;
-                }
-
-                // If there's no running task for the group yet, we can safely return true
-                //
-                // If there are running tasks for this group, we have to ensure that there are
-                // fewer running tasks than allowed for this group.
-                match running_tasks_per_group.get(&task.group) {
-                    None => true,
-                    Some(count) => match state.settings.daemon.groups.get(&task.group) {
-                        Some(allowed) => count < allowed,
-                        None => {
-                            error!(
-                                "Got task with unknown group {}. Please report this!",
-                                &task.group
-                            );
-                            false
-                        }
-                    },
-                }
+                // Let's check if the group is running and not held back by its load limits.
+                matches!(state.groups.get(&task.group), Some(GroupStatus::Running))
+                    && !state.held_back_groups.contains_key(&task.group)
             })
-            .find(|(_, task)| {
+            .filter(|task| {
                 // Check whether all dependencies for this task are fulfilled.
                 task.dependencies
                     .iter()
                     .flat_map(|id| state.tasks.get(id))
-                    .all(|task| task.status == TaskStatus::Done && !task.failed())
+                    .all(|dependency| {
+                        let condition = task.dependency_condition(dependency.id);
+                        condition.is_met_by(dependency) == Some(true)
+                    })
             })
-            .map(|(id, _)| *id)
+            .filter(|task| {
+                // Check whether all named resources of this task are available.
+                state.blocking_resources(task, &used_resources).is_empty()
+            })
+            .collect();
+        candidates.sort_by_key(|task| (Reverse(task.priority), task.id));
+
+        // Pick the first task that fits into the free slots of its group.
+        let mut skipped = Vec::new();
+        let mut reserved_groups = HashSet::new();
+        let next = candidates.into_iter().find(|task| {
+            if reserved_groups.contains(&task.group) {
+                return false;
+            }
+
+            let allowed = match state.settings.daemon.groups.get(&task.group) {
+                Some(allowed) => *allowed,
+                None => {
+                    error!(
+                        "Got task with unknown group {}. Please report this!",
+                        &task.group
+                    );
+                    return false;
+                }
+            };
+
+            // Tasks that need more slots than the group has in total are started,
+            // as soon as there's nothing else running in the group.
+            let used = used_slots_per_group.get(&task.group).copied().unwrap_or(0);
+            if used == 0 || used + task.slots <= allowed {
+                return true;
+            }
+
+            skipped.push(task.id);
+            if self.overtaken.get(&task.id).copied().unwrap_or(0) >= MAX_OVERTAKES {
+                reserved_groups.insert(&task.group);
+            }
+            false
+        })?;
+
+        // Remember all skipped tasks that have been overtaken by the task we're about to start.
+        for id in skipped {
+            if state.tasks[&id].group == next.group {
+                *self.overtaken.entry(id).or_insert(0) += 1;
+            }
+        }
+        self.overtaken.remove(&next.id);
+
+        Some(next.id)
     }
 
     /// Users can issue to reset the daemon.
@@ -176,16 +335,207 @@ impl TaskHandler {
         }
     }
 
+    /// Tasks without an explicit timeout use the default timeout of their group.
+    /// Just like all other group defaults, it's applied once the task is started,
+    /// since the group of a task can no longer change from then on.
+    fn get_timeout(&self, task: &Task) -> Option<u64> {
+        task.timeout.or_else(|| {
+            self.group_defaults
+                .get(&task.group)
+                .and_then(|defaults| defaults.timeout)
+        })
+    }
+
+    /// Kill all tasks that have been running for longer than their timeout allows.
+    /// The killed tasks are remembered, so they get a `TimedOut` result once they're handled
+    /// as finished tasks.
+    fn check_timeouts(&mut self) {
+        let timed_out: Vec<usize> = {
+            let state = self.state.lock().unwrap();
+            let now = Local::now();
+            self.children
+                .keys()
+                .filter(|id| !self.timed_out.contains(id))
+                .filter_map(|id| state.tasks.get(id))
+                .filter(|task| match (task.start, self.get_timeout(task)) {
+                    // The runtime is compared in seconds, since huge timeouts don't fit into
+                    // a `chrono::Duration`. A negative runtime means that the clock went back.
+                    (Some(start), Some(timeout)) => matches!(
+                        u64::try_from((now - start).num_seconds()),
+                        Ok(runtime) if runtime >= timeout
+                    ),
+                    _ => false,
+                })
+                .map(|task| task.id)
+                .collect()
+        };
+
+        for task_id in timed_out {
+            info!("Task {} exceeded its timeout and is being killed", task_id);
+            self.timed_out.insert(task_id);
+            // Always kill the whole process tree, so no processes are left behind.
+            self.kill_task(task_id, true, None, None);
+        }
+    }
+
+    /// Kill all tasks with SIGKILL, that are still running after the grace period
+    /// of their graceful kill.
+    fn check_kill_deadlines(&mut self) {
+        let now = Local::now();
+        let expired: Vec<(usize, bool)> = self
+            .kill_deadlines
+            .iter()
+            .filter(|(_, (deadline, _))| *deadline <= now)
+            .map(|(task_id, (_, kill_children))| (*task_id, *kill_children))
+            .collect();
+
+        for (task_id, kill_children) in expired {
+            self.kill_deadlines.remove(&task_id);
+            if let Some(child) = self.children.get_mut(&task_id) {
+                info!(
+                    "Task {} is still running after its grace period and is being killed",
+                    task_id
+                );
+                kill_child(task_id, child, kill_children);
+                self.kill_cgroup(task_id);
+            }
+        }
+    }
+
+    /// Periodically sample the CPU and memory usage of the process trees of all running tasks.
+    /// The usage is only kept in memory and saved together with the next change of the state.
+    fn sample_usage(&mut self) {
+        let now = Instant::now();
+        if now.duration_since(self.last_usage_sample) < USAGE_SAMPLE_INTERVAL {
+            return;
+        }
+        self.last_usage_sample = now;
+
+        // Walking through all processes takes a while, so the state isn't locked in the meantime.
+        let usages: Vec<(usize, (f64, u64))> = self
+            .children
+            .iter()
+            .filter_map(|(task_id, child)| {
+                get_process_tree_usage(child.id()).map(|usage| (*task_id, usage))
+            })
+            .collect();
+
+        let mut state = self.state.lock().unwrap();
+        for (task_id, (cpu_time, memory)) in usages {
+            let task = match state.tasks.get_mut(&task_id) {
+                Some(task) => task,
+                None => continue,
+            };
+
+            // The CPU usage is calculated since the previous sample or the start of the task.
+            let (previous_time, since) = match self.cpu_times.get(&task_id) {
+                Some((previous_time, since)) => (*previous_time, now.duration_since(*since)),
+                None => {
+                    let runtime = task
+                        .start
+                        .map(|start| (Local::now() - start).to_std().unwrap_or_default())
+                        .unwrap_or_default();
+                    (0.0, runtime)
+                }
+            };
+            let cpu = if since.as_secs_f64() > 0.0 {
+                (cpu_time - previous_time).max(0.0) / since.as_secs_f64() * 100.0
+            } else {
+                0.0
+            };
+
+            task.current_usage = Some(CurrentUsage { cpu, memory });
+            self.cpu_times.insert(task_id, (cpu_time, now));
+        }
+    }
+
     /// See if we can start a new queued task.
     fn check_new(&mut self) {
+        // Hold back groups, for which the system is too busy right now.
+        self.check_load_limits();
+
         // Get the next task id that can be started
         if let Some(id) = self.get_next_task_id() {
             self.start_process(id);
         }
     }
 
+    /// Check the system's load and free memory against the load limits of all groups.
+    /// Groups that exceed any of their limits are held back and no new tasks are started.
+    /// The statistics change slowly, so they're only checked every `LOAD_CHECK_INTERVAL`.
+    fn check_load_limits(&mut self) {
+        let now = Instant::now();
+        if matches!(self.last_load_check, Some(last) if now.duration_since(last) < LOAD_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.last_load_check = Some(now);
+
+        let limits = {
+            let state = self.state.lock().unwrap();
+            if state.settings.daemon.group_limits.is_empty() && state.held_back_groups.is_empty() {
+                return;
+            }
+            state.settings.daemon.group_limits.clone()
+        };
+
+        // Only read the system's statistics, if they're actually needed.
+        // They're read without locking the state.
+        let load = if limits.values().any(|limits| limits.max_load.is_some()) {
+            get_load_average()
+        } else {
+            None
+        };
+        let free_memory = if limits
+            .values()
+            .any(|limits| limits.min_free_memory.is_some())
+        {
+            get_free_memory()
+        } else {
+            None
+        };
+
+        let mut held_back = BTreeMap::new();
+        for (group, limits) in limits.iter() {
+            if let (Some(max_load), Some(load)) = (limits.max_load, load) {
+                if load > max_load {
+                    let reason = format!("load {:.2} exceeds {:.2}", load, max_load);
+                    held_back.insert(group.clone(), reason);
+                    continue;
+                }
+            }
+
+            if let (Some(min_memory), Some(memory)) = (limits.min_free_memory, free_memory) {
+                if memory < min_memory {
+                    let reason = format!(
+                        "free memory {} MiB below {} MiB",
+                        memory / 1024 / 1024,
+                        min_memory / 1024 / 1024
+                    );
+                    held_back.insert(group.clone(), reason);
+                }
+            }
+        }
+
+        // Log whenever a group is being held back or released.
+        let mut state = self.state.lock().unwrap();
+        for (group, reason) in held_back.iter() {
+            if !state.held_back_groups.contains_key(group) {
+                info!("Holding back new tasks of group {}: {}", group, reason);
+            }
+        }
+        for group in state.held_back_groups.keys() {
+            if !held_back.contains_key(group) {
+                info!("Releasing group {}", group);
+            }
+        }
+        state.held_back_groups = held_back;
+    }
+
     /// Ensure that no `Queued` tasks have any failed dependencies.
-    /// Otherwise set their status to `Done` and result to `DependencyFailed`.
+    /// A dependency failed, if it finished without meeting its condition.
+    /// E.g. a dependency that has to fail, fails by succeeding.
+    /// If that's the case, set the task's status to `Done` and result to `DependencyFailed`.
     fn check_failed_dependencies(&mut self) {
         // Clone the state ref, so we don't have two mutable borrows later on.
         let state_ref = self.state.clone();
@@ -203,7 +553,10 @@ impl TaskHandler {
                     .dependencies
                     .iter()
                     .flat_map(|id| state.tasks.get(id))
-                    .filter(|task| task.failed())
+                    .filter(|dependency| {
+                        let condition = task.dependency_condition(dependency.id);
+                        condition.is_met_by(dependency) == Some(false)
+                    })
                     .map(|task| task.id)
                     .next();
 
@@ -232,7 +585,7 @@ impl TaskHandler {
             task.result = Some(TaskResult::DependencyFailed);
             task.start = Some(Local::now());
             task.end = Some(Local::now());
-            self.spawn_callback(&task);
+            self.spawn_callback(task);
         }
     }
 
@@ -248,7 +601,7 @@ impl TaskHandler {
         // Check if the task exists and can actually be spawned. Otherwise do an early return.
         match state.tasks.get(&task_id) {
             Some(task) => {
-                if !vec![TaskStatus::Stashed, TaskStatus::Queued, TaskStatus::Paused]
+                if ![TaskStatus::Stashed, TaskStatus::Queued, TaskStatus::Paused]
                     .contains(&task.status)
                 {
                     info!("Tried to start task with status: {}", task.status);
@@ -273,27 +626,125 @@ impl TaskHandler {
         };
 
         // Get all necessary info for starting the task
-        let (command, path, envs) = {
+        // Limits that aren't set on the task itself are taken from the task's group.
+        // The same goes for the shell, which falls back to the shell of the daemon.
+        let (command, path, envs, memory_limit, cpu_limit, limits, shell, no_shell, pty, log_limit) = {
             let task = state.tasks.get(&task_id).unwrap();
-            (task.command.clone(), task.path.clone(), task.envs.clone())
+            let defaults = state.settings.daemon.group_defaults.get(&task.group);
+            let limits = ProcessLimits {
+                nice: task
+                    .nice
+                    .or_else(|| defaults.and_then(|defaults| defaults.nice)),
+                ionice: task
+                    .ionice
+                    .or_else(|| defaults.and_then(|defaults| defaults.ionice)),
+                max_open_files: task
+                    .max_open_files
+                    .or_else(|| defaults.and_then(|defaults| defaults.max_open_files)),
+                max_cpu_time: task
+                    .max_cpu_time
+                    .or_else(|| defaults.and_then(|defaults| defaults.max_cpu_time)),
+            };
+            (
+                task.command.clone(),
+                task.path.clone(),
+                task.envs.clone(),
+                task.memory_limit
+                    .or_else(|| defaults.and_then(|defaults| defaults.memory_limit)),
+                task.cpu_limit
+                    .or_else(|| defaults.and_then(|defaults| defaults.cpu_limit)),
+                limits,
+                task.shell
+                    .clone()
+                    .or_else(|| defaults.and_then(|defaults| defaults.shell.clone()))
+                    .or_else(|| self.shell.clone()),
+                task.no_shell,
+                task.pty,
+                task.max_log_size
+                    .or_else(|| defaults.and_then(|defaults| defaults.max_log_size))
+                    .map(|max_size| {
+                        let rotation = task
+                            .log_rotation
+                            .or_else(|| defaults.and_then(|defaults| defaults.log_rotation));
+                        (max_size, rotation.unwrap_or_default())
+                    }),
+            )
         };
 
-        // Spawn the actual subprocess
-        let mut command = compile_shell_command(&command);
+        // Rotated segments of a previous run are removed.
+        // The same goes for its combined log, in case combined logs have been disabled since.
+        let (out_path, err_path) = get_log_paths(task_id, &self.pueue_directory);
+        let combined_log_path = get_combined_log_path(task_id, &self.pueue_directory);
+        for log_path in [&out_path, &err_path, &combined_log_path].iter() {
+            for segment in get_rotated_segments(log_path) {
+                let _ = remove_file(segment);
+            }
+        }
+        let combined_log = if self.combined_logs {
+            match File::create(&combined_log_path) {
+                Ok(file) => {
+                    let log = TaskLog::new(file, combined_log_path, log_limit);
+                    Some(Arc::new(Mutex::new(log)))
+                }
+                Err(err) => {
+                    error!("Failed to create combined log file: {:?}", err);
+                    return;
+                }
+            }
+        } else {
+            let _ = remove_file(combined_log_path);
+            None
+        };
 
-        let spawned_command = command
-            .current_dir(path)
-            .stdin(Stdio::piped())
-            .envs(envs)
-            .stdout(Stdio::from(stdout_log))
-            .stderr(Stdio::from(stderr_log))
-            .spawn();
+        // The output of tasks with a maximum log size or a combined log is piped through the
+        // daemon, just like the output of tasks on a pseudo terminal.
+        let pipe_output = combined_log.is_some() || log_limit.is_some();
+
+        // Spawn the actual subprocess
+        // Priorities and limits are applied right before the command is executed.
+        let prepared =
+            compile_task_command(&command, shell.as_deref(), no_shell).and_then(|mut command| {
+                set_process_limits(&mut command, limits).context("Failed to set process limits")?;
+                self.setup_cgroup(task_id, &mut command, memory_limit, cpu_limit)
+                    .context("Failed to set up cgroup")?;
+
+                let pty = if pty {
+                    let pty =
+                        create_pty(&mut command).context("Failed to set up pseudo terminal")?;
+                    Some(pty)
+                } else if pipe_output {
+                    command
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::piped())
+                        .stderr(Stdio::piped());
+                    None
+                } else {
+                    command
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::from(stdout_log.try_clone()?))
+                        .stderr(Stdio::from(stderr_log.try_clone()?));
+                    None
+                };
+                Ok((command, pty))
+            });
+
+        let spawned_command = match prepared {
+            Ok((mut command, pty)) => command
+                .current_dir(path)
+                .envs(envs)
+                .spawn()
+                .map(|child| (child, pty))
+                .map_err(|err| format!("Failed to spawn child {} with err: {:?}", task_id, err)),
+            Err(err) => Err(format!(
+                "Failed to spawn child {} with err: {:#}",
+                task_id, err
+            )),
+        };
 
         // Check if the task managed to spawn
-        let child = match spawned_command {
-            Ok(child) => child,
-            Err(err) => {
-                let error = format!("Failed to spawn child {} with err: {:?}", task_id, err);
+        let (mut child, pty) = match spawned_command {
+            Ok(spawned) => spawned,
+            Err(error) => {
                 error!("{}", error);
                 clean_log_handles(task_id, &self.pueue_directory);
 
@@ -305,7 +756,7 @@ impl TaskHandler {
                     task.start = Some(Local::now());
                     task.end = Some(Local::now());
                     task.enqueue_at = None;
-                    self.spawn_callback(&task);
+                    self.spawn_callback(task);
 
                     task.group.clone()
                 };
@@ -315,6 +766,29 @@ impl TaskHandler {
                 return;
             }
         };
+
+        // Tasks on a pseudo terminal write all of their output to the stdout log.
+        let combined_stream = |stream| combined_log.clone().map(|log| (log, stream));
+        let stdout_log = TaskLog::new(stdout_log, out_path, log_limit);
+        let stderr_log = TaskLog::new(stderr_log, err_path, log_limit);
+        let mut output_threads = Vec::new();
+        if let Some((pty, reader)) = pty {
+            let combined = combined_stream(LogStream::Stdout);
+            output_threads.push(spawn_output_thread(reader, stdout_log, combined));
+            self.ptys.insert(task_id, pty);
+        } else if pipe_output {
+            if let Some(stdout) = child.stdout.take() {
+                let combined = combined_stream(LogStream::Stdout);
+                output_threads.push(spawn_output_thread(stdout, stdout_log, combined));
+            }
+            if let Some(stderr) = child.stderr.take() {
+                let combined = combined_stream(LogStream::Stderr);
+                output_threads.push(spawn_output_thread(stderr, stderr_log, combined));
+            }
+        }
+        if !output_threads.is_empty() {
+            self.output_threads.insert(task_id, output_threads);
+        }
         self.children.insert(task_id, child);
 
         let task = state.tasks.get_mut(&task_id).unwrap();
@@ -327,6 +801,32 @@ impl TaskHandler {
         state.save();
     }
 
+    /// Place the process of a task into its own cgroup, if the daemon has a delegated cgroup.
+    /// The cgroup enforces the memory limit (in bytes) and CPU limit of the task.
+    fn setup_cgroup(
+        &self,
+        task_id: usize,
+        command: &mut Command,
+        memory_limit: Option<u64>,
+        cpu_limit: Option<f64>,
+    ) -> Result<()> {
+        let base = match &self.cgroup {
+            Some(base) => base,
+            None if memory_limit.is_none() && cpu_limit.is_none() => return Ok(()),
+            None => bail!("Memory and CPU limits need a delegated cgroup"),
+        };
+
+        let cgroup = create_task_cgroup(base, task_id, memory_limit, cpu_limit)?;
+        join_cgroup(command, &cgroup)
+    }
+
+    /// Kill all remaining processes in the cgroup of a task.
+    fn kill_cgroup(&self, task_id: usize) {
+        if let Some(base) = &self.cgroup {
+            kill_task_cgroup(&get_task_cgroup(base, task_id));
+        }
+    }
+
     /// As time passes, some delayed tasks may need to be enqueued.
     /// Gather all stashed tasks and enqueue them if it is after the task's enqueue_at
     fn enqueue_delayed_tasks(&mut self) {
@@ -354,8 +854,43 @@ impl TaskHandler {
         }
     }
 
+    /// Spawn a fresh queued task for every schedule, whose next run has been reached.
+    /// Runs that have been missed while the daemon was down are caught up once.
+    fn check_schedules(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        let now = Local::now();
+
+        let due: Vec<usize> = state
+            .schedules
+            .iter()
+            .filter(|(_, schedule)| !schedule.paused && schedule.next_run <= now)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for schedule_id in due.iter() {
+            let task = state.schedules[schedule_id].task.clone();
+            let task_id = state.add_task(task);
+            info!("Schedule {} spawned task {}", schedule_id, task_id);
+
+            let schedule = state.schedules.get_mut(schedule_id).unwrap();
+            schedule.last_task_id = Some(task_id);
+            match next_run(&schedule.expression, &now) {
+                Ok(next) => schedule.next_run = next,
+                Err(error) => {
+                    error!("Pausing schedule {}: {:#}", schedule_id, error);
+                    schedule.paused = true;
+                }
+            }
+        }
+
+        if !due.is_empty() {
+            state.save();
+        }
+    }
+
     /// Check whether there are any finished processes
     /// In case there are, handle them and update the shared state
+    #[allow(clippy::zombie_processes)]
     fn handle_finished_tasks(&mut self) {
         let finished = self.get_finished();
 
@@ -364,25 +899,40 @@ impl TaskHandler {
             return;
         }
 
+        // Give the tasks a moment to write their remaining output.
+        // This happens before the state is locked, as it might take a while.
+        let output_threads = finished
+            .iter()
+            .filter_map(|(task_id, _)| self.output_threads.remove(task_id))
+            .flatten()
+            .collect();
+        wait_for_output(output_threads);
+
         // Clone the state ref, so we don't have two mutable borrows later on.
         let state_ref = self.state.clone();
         let mut state = state_ref.lock().unwrap();
 
-        for (task_id, error) in finished.iter() {
+        for (task_id, finished) in finished.iter() {
             // Handle std::io errors on child processes.
             // I have never seen something like this, but it might happen.
-            if let Some(error) = error {
+            if let Err(error) = finished {
                 let _child = self
                     .children
                     .remove(task_id)
                     .expect("Errored child went missing while handling finished task.");
+                self.timed_out.remove(task_id);
+                self.kill_deadlines.remove(task_id);
+                self.remove_cgroup(*task_id);
+                self.cpu_times.remove(task_id);
+                self.ptys.remove(task_id);
 
                 let group = {
-                    let mut task = state.tasks.get_mut(&task_id).unwrap();
+                    let task = state.tasks.get_mut(task_id).unwrap();
+                    task.current_usage = None;
                     task.status = TaskStatus::Done;
                     task.end = Some(Local::now());
                     task.result = Some(TaskResult::Errored);
-                    self.spawn_callback(&task);
+                    self.spawn_callback(task);
 
                     task.group.clone()
                 };
@@ -393,47 +943,72 @@ impl TaskHandler {
             }
 
             // Handle any tasks that exited with some kind of exit code
-            let mut child = self
-                .children
+            let (exit_status, resource_usage) = finished.as_ref().unwrap();
+            self.children
                 .remove(task_id)
                 .expect("Child of task {} went away while handling finished task.");
-
-            // Get the exit code of the child.
-            // Errors really shouldn't happen in here, since we already checked if it's finished
-            // with try_wait() before.
-            let exit_code_result = child.wait();
-            let exit_code = exit_code_result
-                .context(format!(
-                    "Failed on wait() for finished task {} with error: {:?}",
-                    task_id, error
-                ))
-                .unwrap()
-                .code();
-
+            self.kill_deadlines.remove(task_id);
+            self.cpu_times.remove(task_id);
+            let out_of_memory = self.remove_cgroup(*task_id);
+            self.ptys.remove(task_id);
+
+            // Processes that exceeded their timeout have been killed by the daemon.
+            // Processes that failed after exceeding their memory limit have been killed by the OOM killer.
+            // Only some child of the process might have been killed, in which case it can still succeed.
             // Processes with exit code 0 exited successfully
             // Processes with `None` have been killed by a Signal
-            let result = match exit_code {
-                Some(0) => Some(TaskResult::Success),
-                Some(exit_code) => Some(TaskResult::Failed(exit_code)),
-                None => Some(TaskResult::Killed),
+            let result = if self.timed_out.remove(task_id) {
+                Some(TaskResult::TimedOut)
+            } else if out_of_memory && exit_status.code() != Some(0) {
+                Some(TaskResult::OutOfMemory)
+            } else {
+                match exit_status.code() {
+                    Some(0) => Some(TaskResult::Success),
+                    Some(exit_code) => Some(TaskResult::Failed(exit_code)),
+                    None => match get_exit_signal(exit_status) {
+                        Some(signal) if signal != "SIGKILL" => Some(TaskResult::Signaled(signal)),
+                        _ => Some(TaskResult::Killed),
+                    },
+                }
             };
 
             // Update all properties on the task and get the group for later
             let group = {
-                let mut task = state
+                let task = state
                     .tasks
-                    .get_mut(&task_id)
+                    .get_mut(task_id)
                     .expect("Task was removed before child process has finished!");
+                task.current_usage = None;
+
+                // Failed tasks are enqueued again, as long as they have some retries left.
+                // Neither dependants nor callbacks get to see these intermediate failures.
+                if let Some(failure) = result.clone().filter(|result| {
+                    matches!(
+                        result,
+                        TaskResult::Failed(_) | TaskResult::TimedOut | TaskResult::OutOfMemory
+                    )
+                }) {
+                    if task.attempts.len() < task.retries {
+                        self.retry_task(task, failure);
+                        continue;
+                    }
+                }
 
                 task.status = TaskStatus::Done;
                 task.end = Some(Local::now());
                 task.result = result.clone();
-                self.spawn_callback(&task);
+                task.resource_usage = resource_usage.clone();
+                self.spawn_callback(task);
 
                 task.group.clone()
             };
 
-            if let Some(TaskResult::Failed(_)) = result {
+            if matches!(
+                result,
+                Some(TaskResult::Failed(_))
+                    | Some(TaskResult::TimedOut)
+                    | Some(TaskResult::OutOfMemory)
+            ) {
                 state.handle_task_failure(group);
             }
 
@@ -446,21 +1021,64 @@ impl TaskHandler {
         state.save()
     }
 
-    /// Gather all finished tasks and sort them by finished and errored.
-    /// Returns a list of finished task ids and whether they errored or not.
-    fn get_finished(&mut self) -> Vec<(usize, Option<std::io::Error>)> {
+    /// Remove the cgroup of a finished task.
+    /// Returns whether any of the task's processes has been killed by the OOM killer.
+    fn remove_cgroup(&self, task_id: usize) -> bool {
+        let base = match &self.cgroup {
+            Some(base) => base,
+            None => return false,
+        };
+
+        let cgroup = get_task_cgroup(base, task_id);
+        let out_of_memory = was_oom_killed(&cgroup);
+        remove_task_cgroup(&cgroup);
+        out_of_memory
+    }
+
+    /// Enqueue a failed task once more and remember the result of the failed attempt.
+    ///
+    /// If the task has a retry delay, it's stashed and will be enqueued as soon as the delay
+    /// elapsed. The delay is doubled with every further attempt (exponential backoff).
+    fn retry_task(&self, task: &mut Task, failure: TaskResult) {
+        task.attempts.push(failure);
+        info!(
+            "Task {} failed. Starting retry {} of {}",
+            task.id,
+            task.attempts.len(),
+            task.retries
+        );
+
+        task.result = None;
+        task.start = None;
+        task.end = None;
+
+        match task.retry_delay {
+            Some(delay) => {
+                let factor = 2u64.saturating_pow(task.attempts.len() as u32 - 1);
+                let delay = delay.saturating_mul(factor).min(u32::MAX as u64);
+                task.status = TaskStatus::Stashed;
+                task.enqueue_at = Some(Local::now() + chrono::Duration::seconds(delay as i64));
+            }
+            None => task.status = TaskStatus::Queued,
+        }
+    }
+
+    /// Gather all finished tasks and reap their processes.
+    /// Returns a list of finished task ids with either their exit status and resource usage
+    /// or the error that occurred while waiting for them.
+    fn get_finished(&mut self) -> Vec<(usize, std::io::Result<FinishedProcess>)> {
         let mut finished = Vec::new();
         for (id, child) in self.children.iter_mut() {
-            match child.try_wait() {
+            match try_wait_with_usage(child) {
                 // Handle a child error.
                 Err(error) => {
-                    finished.push((*id, Some(error)));
+                    finished.push((*id, Err(error)));
                 }
                 // Child process did not exit yet
                 Ok(None) => continue,
-                Ok(_exit_status) => {
+                Ok(Some(process)) => {
                     info!("Task {} just finished", id);
-                    finished.push((*id, None));
+                    finished.push((*id, Ok(process)));
                 }
             }
         }
@@ -489,6 +1107,8 @@ impl TaskHandler {
             Message::Start(message) => self.start(message),
             Message::Kill(message) => self.kill(message),
             Message::Send(message) => self.send(message),
+            Message::AttachInput(message) => self.write_input(message.task_id, &message.input),
+            Message::Signal(message) => self.signal(message),
             Message::Reset(message) => self.reset(message),
             Message::DaemonShutdown => self.shutdown(),
             _ => info!("Received unhandled message {:?}", message),
@@ -660,7 +1280,9 @@ impl TaskHandler {
         // - The paused tasks of a group
         // - The paused tasks of the default queue
         // Only pause specific tasks
-        let task_ids: Vec<usize> = if !message.task_ids.is_empty() {
+        let task_ids: Vec<usize> = if let Some(array_id) = message.array_id {
+            self.kill_pending_array_tasks(array_id)
+        } else if !message.task_ids.is_empty() {
             message.task_ids
         } else if message.all {
             // Pause all running tasks
@@ -688,40 +1310,171 @@ impl TaskHandler {
         };
 
         for task_id in task_ids {
-            self.kill_task(task_id, message.children);
+            self.kill_task(task_id, message.children, message.signal, message.grace);
         }
     }
 
+    /// Mark the queued and stashed tasks of a task array as killed, so they won't be started.
+    /// Returns the running tasks of the array, which still have to be killed.
+    fn kill_pending_array_tasks(&mut self, array_id: usize) -> Vec<usize> {
+        let state_ref = self.state.clone();
+        let mut state = state_ref.lock().unwrap();
+        let task_ids = state.array_task_ids(array_id);
+
+        let (pending, _) = state.tasks_in_statuses(
+            vec![TaskStatus::Queued, TaskStatus::Stashed],
+            Some(task_ids.clone()),
+        );
+        for task_id in pending {
+            let task = state.tasks.get_mut(&task_id).unwrap();
+            task.status = TaskStatus::Done;
+            task.result = Some(TaskResult::Killed);
+            task.start = Some(Local::now());
+            task.end = Some(Local::now());
+            self.spawn_callback(task);
+        }
+        state.save();
+
+        let (running, _) = state.tasks_in_statuses(
+            vec![TaskStatus::Running, TaskStatus::Paused],
+            Some(task_ids),
+        );
+        running
+    }
+
     /// Kill a specific task and handle it accordingly.
-    /// Triggered on `reset` and `kill`.
-    fn kill_task(&mut self, task_id: usize, kill_children: bool) {
-        if let Some(mut child) = self.children.get_mut(&task_id) {
-            kill_child(task_id, &mut child, kill_children);
+    /// Triggered on `reset`, `kill` and timeouts.
+    ///
+    /// If neither a signal nor a default signal for the task's group is given, the task is killed
+    /// right away. Otherwise, the task is sent that signal and only killed with SIGKILL,
+    /// if it's still running after its grace period.
+    fn kill_task(
+        &mut self,
+        task_id: usize,
+        kill_children: bool,
+        signal: Option<Signal>,
+        grace: Option<u64>,
+    ) {
+        let (signal, grace) = {
+            let state = self.state.lock().unwrap();
+            let defaults = state
+                .tasks
+                .get(&task_id)
+                .and_then(|task| state.settings.daemon.group_defaults.get(&task.group));
+            (
+                signal.or_else(|| defaults.and_then(|defaults| defaults.kill_signal)),
+                grace
+                    .or_else(|| defaults.and_then(|defaults| defaults.kill_grace))
+                    .unwrap_or(DEFAULT_KILL_GRACE),
+            )
+        };
+
+        let child = if let Some(child) = self.children.get_mut(&task_id) {
+            child
         } else {
             warn!("Tried to kill non-existing child: {}", task_id);
+            return;
+        };
+
+        let signal = match signal {
+            Some(signal) if signal != Signal::Kill => signal,
+            _ => {
+                kill_child(task_id, child, kill_children);
+                self.kill_cgroup(task_id);
+                return;
+            }
+        };
+
+        match send_signal_to_child(child, &ProcessAction::Signal(signal), kill_children) {
+            Ok(_) => {
+                info!(
+                    "Sent {} to task {}. It'll be killed in {} seconds",
+                    signal, task_id, grace
+                );
+                // Grace periods, which reach beyond any representable point in time,
+                // never escalate to SIGKILL.
+                let deadline = chrono::Duration::from_std(Duration::from_secs(grace))
+                    .ok()
+                    .and_then(|grace| Local::now().checked_add_signed(grace));
+                let deadline = match deadline {
+                    Some(deadline) => deadline,
+                    None => {
+                        warn!("The grace period of task {} is too long", task_id);
+                        return;
+                    }
+                };
+                // Repeated kills don't postpone the escalation.
+                self.kill_deadlines
+                    .entry(task_id)
+                    .or_insert((deadline, kill_children));
+            }
+            Err(error) => {
+                warn!(
+                    "Failed to send {} to task {}, killing it instead: {:?}",
+                    signal, task_id, error
+                );
+                kill_child(task_id, child, kill_children);
+                self.kill_cgroup(task_id);
+            }
+        }
+    }
+
+    /// Send a signal to a task's process and, if requested, to all of its children.
+    fn signal(&mut self, message: SignalMessage) {
+        let task_id = message.task_id;
+        let action = ProcessAction::Signal(message.signal);
+        match self.perform_action(task_id, action, message.children) {
+            Err(err) => error!(
+                "Failed sending {} to task {}: {:?}",
+                message.signal, task_id, err
+            ),
+            Ok(success) => {
+                if success {
+                    info!("Sent {} to task {}", message.signal, task_id);
+                }
+            }
         }
     }
 
     /// Send some input to a child process.
     fn send(&mut self, message: SendMessage) {
-        let task_id = message.task_id;
-        let input = message.input;
+        self.write_input(message.task_id, message.input.as_bytes());
+    }
+
+    /// Write input to the terminal or stdin of a task.
+    /// Attached clients send their input as raw bytes, which is why it doesn't need to be text.
+    fn write_input(&mut self, task_id: usize, input: &[u8]) {
+        // Input for tasks on a pseudo terminal is typed into their terminal.
+        if let Some(pty) = self.ptys.get_mut(&task_id) {
+            if let Err(err) = pty.write_input(input) {
+                warn!(
+                    "Failed to send input to task {} with err {:?}: {}",
+                    task_id,
+                    err,
+                    String::from_utf8_lossy(input)
+                );
+            }
+            return;
+        }
         let child = match self.children.get_mut(&task_id) {
             Some(child) => child,
             None => {
                 warn!(
                     "Task {} finished before input could be sent: {}",
-                    task_id, input
+                    task_id,
+                    String::from_utf8_lossy(input)
                 );
                 return;
             }
         };
         {
             let child_stdin = child.stdin.as_mut().unwrap();
-            if let Err(err) = child_stdin.write_all(&input.clone().into_bytes()) {
+            if let Err(err) = child_stdin.write_all(input) {
                 warn!(
                     "Failed to send input to task {} with err {:?}: {}",
-                    task_id, err, input
+                    task_id,
+                    err,
+                    String::from_utf8_lossy(input)
                 );
             };
         }
@@ -767,12 +1520,14 @@ impl TaskHandler {
 
         let print_time = |time: Option<DateTime<Local>>| {
             time.map(|time| time.timestamp().to_string())
-                .unwrap_or_else(String::new)
+                .unwrap_or_default()
         };
         parameters.insert("enqueue", print_time(task.enqueue_at));
         parameters.insert("start", print_time(task.start));
         parameters.insert("end", print_time(task.end));
         parameters.insert("group", task.group.clone());
+        let timeout = self.get_timeout(task).map(|timeout| timeout.to_string());
+        parameters.insert("timeout", timeout.unwrap_or_default());
 
         if let Some(TaskResult::Success) = &task.result {
             parameters.insert("exit_code", "0".into());
@@ -782,7 +1537,7 @@ impl TaskHandler {
             parameters.insert("exit_code", "None".into());
         }
 
-        let callback_command = match handlebars.render_template(&callback, &parameters) {
+        let callback_command = match handlebars.render_template(callback, &parameters) {
             Ok(callback_command) => callback_command,
             Err(err) => {
                 error!(
@@ -793,7 +1548,14 @@ impl TaskHandler {
             }
         };
 
-        let mut command = compile_shell_command(&callback_command);
+        let mut command =
+            match compile_task_command(&callback_command, self.shell.as_deref(), false) {
+                Ok(command) => command,
+                Err(err) => {
+                    error!("Failed to create callback command with error: {:#}", err);
+                    return;
+                }
+            };
 
         // Spawn the callback subprocess and log if it fails.
         let spawn_result = command.spawn();
@@ -811,6 +1573,7 @@ impl TaskHandler {
 
     /// Look at all running callbacks and log any errors.
     /// If everything went smoothly, simply remove them from the list.
+    #[allow(clippy::zombie_processes)]
     fn check_callbacks(&mut self) {
         let mut finished = Vec::new();
         for (id, child) in self.callbacks.iter_mut().enumerate() {
@@ -849,6 +1612,7 @@ impl TaskHandler {
             if let Some(mut child) = child {
                 info!("Killing child {}", &task_id);
                 kill_child(task_id, &mut child, true);
+                self.kill_cgroup(task_id);
             } else {
                 error!("Fail to get child {} for killing", &task_id);
             }
@@ -858,3 +1622,226 @@ impl TaskHandler {
         std::process::exit(0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+
+    use pueue_lib::settings::Settings;
+    use pueue_lib::state::State;
+    use pueue_lib::task::DependencyCondition;
+
+    use super::*;
+
+    /// Create a task handler, whose default group has the given amount of parallel slots.
+    fn get_task_handler(parallel_tasks: usize) -> TaskHandler {
+        let mut settings: Settings = Settings::default_config()
+            .expect("Failed to get default config")
+            .try_into()
+            .expect("Failed to get test settings");
+        settings
+            .daemon
+            .groups
+            .insert("default".to_string(), parallel_tasks);
+        let state = Arc::new(Mutex::new(State::new(&settings, None)));
+        let (_sender, receiver) = channel();
+
+        TaskHandler::new(state, receiver)
+    }
+
+    /// Add a task with the given status and slots to the state of the task handler.
+    fn add_task(handler: &TaskHandler, status: TaskStatus, slots: usize) -> usize {
+        add_task_with_priority(handler, status, slots, 0)
+    }
+
+    fn add_task_with_priority(
+        handler: &TaskHandler,
+        status: TaskStatus,
+        slots: usize,
+        priority: i32,
+    ) -> usize {
+        let mut task = Task::new(
+            "sleep 60".to_string(),
+            "/tmp".to_string(),
+            HashMap::new(),
+            "default".to_string(),
+            status,
+            None,
+            Vec::new(),
+            None,
+        );
+        task.slots = slots;
+        task.priority = priority;
+        handler.state.lock().unwrap().insert_task(task)
+    }
+
+    fn set_status(handler: &TaskHandler, task_id: usize, status: TaskStatus) {
+        let mut state = handler.state.lock().unwrap();
+        state.tasks.get_mut(&task_id).unwrap().status = status;
+    }
+
+    fn get_arguments(command: &Command) -> Vec<&OsStr> {
+        let mut arguments = vec![command.get_program()];
+        arguments.extend(command.get_args());
+        arguments
+    }
+
+    #[test]
+    fn test_compile_task_command() {
+        let shell = vec![
+            "bash".to_string(),
+            "-o".to_string(),
+            "pipefail".to_string(),
+            "-c".to_string(),
+        ];
+        let command = compile_task_command("ls | wc", Some(&shell), false).unwrap();
+        assert_eq!(
+            get_arguments(&command),
+            vec!["bash", "-o", "pipefail", "-c", "ls | wc"]
+        );
+
+        // Shells from the config, which consist of a single program, are completed with `-c`.
+        let shell = vec!["bash".to_string()];
+        let command = compile_task_command("ls | wc", Some(&shell), false).unwrap();
+        assert_eq!(get_arguments(&command), vec!["bash", "-c", "ls | wc"]);
+
+        let command = compile_task_command("echo 'a b'", Some(&shell), true).unwrap();
+        assert_eq!(get_arguments(&command), vec!["echo", "a b"]);
+
+        assert!(compile_task_command("ls", Some(&[]), false).is_err());
+        assert!(compile_task_command("", None, true).is_err());
+    }
+
+    #[test]
+    /// Tasks that don't fit into the free slots are overtaken by smaller tasks,
+    /// until they reserve their group.
+    fn test_overtaken_task_reserves_group() {
+        let mut handler = get_task_handler(2);
+        let running = add_task(&handler, TaskStatus::Running, 1);
+        let big = add_task(&handler, TaskStatus::Queued, 2);
+        let small: Vec<usize> = (0..MAX_OVERTAKES + 1)
+            .map(|_| add_task(&handler, TaskStatus::Queued, 1))
+            .collect();
+
+        // Smaller tasks overtake the big task, as long as it didn't reserve its group.
+        for id in small.iter().take(MAX_OVERTAKES) {
+            assert_eq!(handler.get_next_task_id(), Some(*id));
+            set_status(&handler, *id, TaskStatus::Done);
+        }
+        assert_eq!(handler.overtaken.get(&big), Some(&MAX_OVERTAKES));
+
+        // The big task reserved its group, so the last small task has to wait.
+        assert_eq!(handler.get_next_task_id(), None);
+
+        // The big task starts once there are enough free slots.
+        set_status(&handler, running, TaskStatus::Done);
+        assert_eq!(handler.get_next_task_id(), Some(big));
+        assert!(handler.overtaken.is_empty());
+    }
+
+    #[test]
+    /// Overtaken tasks are forgotten, once they're removed or aren't queued any longer.
+    fn test_overtaken_tasks_are_pruned() {
+        let mut handler = get_task_handler(2);
+        add_task(&handler, TaskStatus::Running, 1);
+        let removed = add_task(&handler, TaskStatus::Queued, 2);
+        let stashed = add_task(&handler, TaskStatus::Queued, 2);
+        let small = add_task(&handler, TaskStatus::Queued, 1);
+
+        assert_eq!(handler.get_next_task_id(), Some(small));
+        assert_eq!(handler.overtaken.len(), 2);
+        set_status(&handler, small, TaskStatus::Running);
+
+        handler.state.lock().unwrap().tasks.remove(&removed);
+        set_status(&handler, stashed, TaskStatus::Stashed);
+        assert_eq!(handler.get_next_task_id(), None);
+        assert!(handler.overtaken.is_empty());
+    }
+
+    #[test]
+    /// Tasks without an explicit timeout fall back to the default timeout of their group.
+    fn test_group_default_timeout() {
+        let mut handler = get_task_handler(1);
+        let explicit = add_task(&handler, TaskStatus::Running, 1);
+        let inherited = add_task(&handler, TaskStatus::Running, 1);
+        handler
+            .state
+            .lock()
+            .unwrap()
+            .tasks
+            .get_mut(&explicit)
+            .unwrap()
+            .timeout = Some(5);
+
+        let state = handler.state.clone();
+        let state = state.lock().unwrap();
+        assert_eq!(handler.get_timeout(&state.tasks[&inherited]), None);
+
+        let defaults = GroupDefaults {
+            timeout: Some(60),
+            ..Default::default()
+        };
+        handler.group_defaults.insert("default".into(), defaults);
+        assert_eq!(handler.get_timeout(&state.tasks[&explicit]), Some(5));
+        assert_eq!(handler.get_timeout(&state.tasks[&inherited]), Some(60));
+    }
+
+    #[test]
+    /// Queued tasks with a higher priority are started first.
+    /// Tasks with the same priority are started in the order of their ids.
+    fn test_priority_ordering() {
+        let mut handler = get_task_handler(1);
+        let low = add_task_with_priority(&handler, TaskStatus::Queued, 1, -1);
+        let first = add_task_with_priority(&handler, TaskStatus::Queued, 1, 0);
+        let high = add_task_with_priority(&handler, TaskStatus::Queued, 1, 5);
+        let second = add_task_with_priority(&handler, TaskStatus::Queued, 1, 0);
+
+        for id in [high, first, second, low].iter() {
+            assert_eq!(handler.get_next_task_id(), Some(*id));
+            set_status(&handler, *id, TaskStatus::Done);
+        }
+        assert_eq!(handler.get_next_task_id(), None);
+    }
+
+    #[test]
+    /// Queued tasks fail, once one of their dependencies doesn't meet its condition.
+    /// Tasks in paused groups are left alone, so their dependencies can still be restarted.
+    fn test_check_failed_dependencies() {
+        let mut handler = get_task_handler(1);
+        let failed = add_task(&handler, TaskStatus::Done, 1);
+        let succeeded = add_task(&handler, TaskStatus::Done, 1);
+        let after_success = add_task(&handler, TaskStatus::Queued, 1);
+        let after_failure = add_task(&handler, TaskStatus::Queued, 1);
+        let after_succeeded = add_task(&handler, TaskStatus::Queued, 1);
+        {
+            let mut state = handler.state.lock().unwrap();
+            state.tasks.get_mut(&failed).unwrap().result = Some(TaskResult::Failed(1));
+            state.tasks.get_mut(&succeeded).unwrap().result = Some(TaskResult::Success);
+
+            state.tasks.get_mut(&after_success).unwrap().dependencies = vec![failed];
+            let task = state.tasks.get_mut(&after_failure).unwrap();
+            task.dependencies = vec![failed];
+            task.dependency_conditions
+                .insert(failed, DependencyCondition::Failure);
+            state.tasks.get_mut(&after_succeeded).unwrap().dependencies = vec![succeeded, failed];
+            state.groups.insert("default".into(), GroupStatus::Paused);
+        }
+
+        handler.check_failed_dependencies();
+        {
+            let mut state = handler.state.lock().unwrap();
+            assert_eq!(state.tasks[&after_success].status, TaskStatus::Queued);
+            state.groups.insert("default".into(), GroupStatus::Running);
+        }
+
+        handler.check_failed_dependencies();
+        let state = handler.state.lock().unwrap();
+        for id in [after_success, after_succeeded].iter() {
+            assert_eq!(state.tasks[id].status, TaskStatus::Done);
+            assert_eq!(state.tasks[id].result, Some(TaskResult::DependencyFailed));
+        }
+        assert_eq!(state.tasks[&after_failure].status, TaskStatus::Queued);
+    }
+}
@@ -0,0 +1,348 @@
+use std::fs::{self, create_dir, create_dir_all, read_dir, read_to_string, remove_dir_all, File};
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::prelude::*;
+use chrono::Duration;
+use log::error;
+use serde_derive::{Deserialize, Serialize};
+use snap::read::FrameDecoder;
+use snap::write::FrameEncoder;
+
+use crate::log::*;
+use crate::task::Task;
+
+/// The name of the file, which contains the metadata of an archived task.
+const METADATA_FILE: &str = "task.json";
+
+/// The name of the file, which contains the id of the next entry in the archive.
+const NEXT_ID_FILE: &str = "next_id";
+
+/// A task, whose logs have been archived, when it has been removed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ArchivedTask {
+    /// The id of the archive entry.
+    /// This is independent of the task's id, since task ids are reused after a reset.
+    pub id: usize,
+    pub task: Task,
+    pub archived_at: DateTime<Local>,
+    /// The size of the compressed logs in bytes.
+    pub size: u64,
+}
+
+/// Return the path to the archive, which contains a directory for each archived task.
+pub fn get_archive_path(path: &Path) -> PathBuf {
+    path.join("archive")
+}
+
+/// Return the path to the directory of an archived task.
+fn get_entry_path(id: usize, path: &Path) -> PathBuf {
+    get_archive_path(path).join(id.to_string())
+}
+
+/// Return the paths to the compressed `(stdout, stderr, combined)` logs of an archived task.
+fn get_archived_log_paths(id: usize, path: &Path) -> (PathBuf, PathBuf, PathBuf) {
+    let entry_path = get_entry_path(id, path);
+    (
+        entry_path.join("stdout.log.sz"),
+        entry_path.join("stderr.log.sz"),
+        entry_path.join("combined.log.sz"),
+    )
+}
+
+/// Archive the logs of a task, including the rotated segments of its logs.
+///
+/// The logs are compressed using [snap] and stored together with the task in a new entry.
+/// Entry ids are never reused, even if older entries have been removed from the archive.
+pub fn archive_task(task: &Task, path: &Path) -> Result<ArchivedTask> {
+    let archive_path = get_archive_path(path);
+    create_dir_all(&archive_path)?;
+    let mut id = read_next_id(&archive_path)?;
+    // Another task might be archived at the same time, in which case we take the next id.
+    loop {
+        match create_dir(get_entry_path(id, path)) {
+            Ok(()) => break,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => id += 1,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    fs::write(archive_path.join(NEXT_ID_FILE), (id + 1).to_string())?;
+
+    let (out_path, err_path) = get_log_paths(task.id, path);
+    let combined_path = get_combined_log_path(task.id, path);
+    let (archived_out, archived_err, archived_combined) = get_archived_log_paths(id, path);
+
+    let mut size = 0;
+    for (log_path, archived_path) in [
+        (out_path, archived_out),
+        (err_path, archived_err),
+        (combined_path, archived_combined),
+    ]
+    .iter()
+    {
+        if !log_path.exists() {
+            continue;
+        }
+        let mut compressor = FrameEncoder::new(File::create(archived_path)?);
+        io::copy(&mut open_log(log_path)?, &mut compressor)?;
+        compressor.flush()?;
+        drop(compressor);
+        size += archived_path.metadata()?.len();
+    }
+
+    // The metadata is written last, since entries without metadata are ignored.
+    let archived = ArchivedTask {
+        id,
+        task: task.clone(),
+        archived_at: Local::now(),
+        size,
+    };
+    let metadata = serde_json::to_string_pretty(&archived)?;
+    fs::write(get_entry_path(id, path).join(METADATA_FILE), metadata)?;
+
+    Ok(archived)
+}
+
+/// Return the id of the next entry in the archive. \
+/// Archives that don't know their next id yet continue after their highest id.
+fn read_next_id(archive_path: &Path) -> Result<usize> {
+    match read_to_string(archive_path.join(NEXT_ID_FILE)) {
+        Ok(id) => id
+            .trim()
+            .parse()
+            .context("Failed to parse the next id of the archive"),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(read_entry_ids(archive_path)?
+            .into_iter()
+            .max()
+            .map_or(0, |id| id + 1)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Return the ids of all entries in the archive.
+fn read_entry_ids(archive_path: &Path) -> io::Result<Vec<usize>> {
+    let mut ids = Vec::new();
+    for entry in read_dir(archive_path)?.flatten() {
+        if let Some(id) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse().ok())
+        {
+            ids.push(id);
+        }
+    }
+
+    Ok(ids)
+}
+
+/// Return all archived tasks, ordered by their archive id.
+pub fn get_archived_tasks(path: &Path) -> Vec<ArchivedTask> {
+    let mut ids = match read_entry_ids(&get_archive_path(path)) {
+        Ok(ids) => ids,
+        Err(_) => return Vec::new(),
+    };
+    ids.sort_unstable();
+
+    ids.into_iter()
+        .filter_map(|id| read_archived_task(id, path).ok())
+        .collect()
+}
+
+/// Return an archived task by its archive id.
+pub fn read_archived_task(id: usize, path: &Path) -> Result<ArchivedTask> {
+    let metadata_path = get_entry_path(id, path).join(METADATA_FILE);
+    let metadata = read_to_string(metadata_path)
+        .with_context(|| format!("There's no archived task with id {}", id))?;
+
+    Ok(serde_json::from_str(&metadata)?)
+}
+
+/// Return the `(stdout, stderr)` output of an archived task, compressed using [snap]. \
+/// If `lines` is given, only the last few lines of each log are returned.
+pub fn read_archived_logs(
+    id: usize,
+    path: &Path,
+    lines: Option<usize>,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let (out_path, err_path, _) = get_archived_log_paths(id, path);
+    let stdout = read_archived_log(&out_path, lines)?;
+    let stderr = read_archived_log(&err_path, lines)?;
+
+    Ok((stdout, stderr))
+}
+
+/// Read a compressed log of an archived task.
+/// Tasks without output might not have a log, which is the same as an empty log.
+fn read_archived_log(log_path: &Path, lines: Option<usize>) -> Result<Vec<u8>> {
+    if !log_path.exists() {
+        return Ok(Vec::new());
+    }
+    let compressed = fs::read(log_path)?;
+    let lines = match lines {
+        Some(lines) => lines,
+        None => return Ok(compressed),
+    };
+
+    let mut output = Vec::new();
+    FrameDecoder::new(compressed.as_slice()).read_to_end(&mut output)?;
+    let last_lines = take_last_lines(&String::from_utf8_lossy(&output), lines);
+
+    let mut compressed = Vec::new();
+    let mut compressor = FrameEncoder::new(&mut compressed);
+    io::copy(&mut Cursor::new(last_lines), &mut compressor)?;
+    compressor.flush()?;
+    drop(compressor);
+
+    Ok(compressed)
+}
+
+/// Return the combined log of an archived task, if it has one. \
+/// The lines are filtered just like in [read_combined_log] and compressed using [snap].
+pub fn read_archived_combined_log(
+    id: usize,
+    path: &Path,
+    lines: Option<usize>,
+    stderr_only: bool,
+) -> Result<Option<Vec<u8>>> {
+    let (_, _, combined_path) = get_archived_log_paths(id, path);
+    if !combined_path.exists() {
+        return Ok(None);
+    }
+
+    let log = FrameDecoder::new(File::open(combined_path)?);
+    let lines = read_combined_lines(log, lines, stderr_only)?;
+
+    Ok(Some(compress_combined_lines(&lines)?))
+}
+
+/// Remove old archived tasks.
+///
+/// max_age: Remove all tasks, which have been archived more than this many seconds ago.
+/// max_size: Remove the oldest tasks, until the logs in the archive don't exceed this many bytes.
+pub fn apply_archive_retention(path: &Path, max_age: Option<u64>, max_size: Option<u64>) {
+    let mut archived = get_archived_tasks(path);
+
+    if let Some(max_age) = max_age {
+        let oldest = Local::now() - Duration::seconds(max_age as i64);
+        archived.retain(|entry| {
+            if entry.archived_at >= oldest {
+                return true;
+            }
+            remove_archived_task(entry.id, path);
+            false
+        });
+    }
+
+    if let Some(max_size) = max_size {
+        let mut size: u64 = archived.iter().map(|entry| entry.size).sum();
+        for entry in archived.iter() {
+            if size <= max_size {
+                break;
+            }
+            remove_archived_task(entry.id, path);
+            size -= entry.size;
+        }
+    }
+}
+
+/// Remove an archived task including its logs.
+fn remove_archived_task(id: usize, path: &Path) {
+    if let Err(err) = remove_dir_all(get_entry_path(id, path)) {
+        error!("Failed to remove archived task {} with error {:?}", id, err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::task::TaskStatus;
+
+    /// Create the logs of a task with the given stdout output and archive it.
+    fn archive_stub_task(task_id: usize, output: Option<&[u8]>, path: &Path) -> ArchivedTask {
+        let mut task = Task::new(
+            "ls".into(),
+            "/tmp".into(),
+            HashMap::new(),
+            "default".into(),
+            TaskStatus::Done,
+            None,
+            Vec::new(),
+            None,
+        );
+        task.id = task_id;
+        create_dir_all(path.join("task_logs")).unwrap();
+        if let Some(output) = output {
+            let (mut stdout, _) = create_log_file_handles(task_id, path).unwrap();
+            stdout.write_all(output).unwrap();
+        }
+
+        archive_task(&task, path).unwrap()
+    }
+
+    #[test]
+    /// New entries follow the previous entry, even if lower ids are free.
+    fn test_next_archive_id() {
+        let dir = tempdir().unwrap();
+        for id in 0..3 {
+            assert_eq!(archive_stub_task(id, Some(b"output"), dir.path()).id, id);
+        }
+
+        remove_archived_task(0, dir.path());
+        assert_eq!(archive_stub_task(0, Some(b"output"), dir.path()).id, 3);
+
+        // Directories without metadata still block their id.
+        create_dir(get_entry_path(4, dir.path())).unwrap();
+        assert_eq!(archive_stub_task(0, Some(b"output"), dir.path()).id, 5);
+        let ids: Vec<usize> = get_archived_tasks(dir.path())
+            .iter()
+            .map(|archived| archived.id)
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3, 5]);
+
+        // Ids aren't reused, once all entries have been removed.
+        for id in ids {
+            remove_archived_task(id, dir.path());
+        }
+        assert_eq!(archive_stub_task(0, Some(b"output"), dir.path()).id, 6);
+    }
+
+    #[test]
+    /// Tasks without logs are archived with empty logs.
+    fn test_missing_logs() {
+        let dir = tempdir().unwrap();
+        let archived = archive_stub_task(0, None, dir.path());
+        assert_eq!(archived.size, 0);
+
+        let (stdout, stderr) = read_archived_logs(archived.id, dir.path(), Some(10)).unwrap();
+        assert!(stdout.is_empty());
+        assert!(stderr.is_empty());
+        let combined = read_archived_combined_log(archived.id, dir.path(), None, false).unwrap();
+        assert!(combined.is_none());
+    }
+
+    #[test]
+    /// Only entries that exceed the maximum age are removed.
+    fn test_max_age() {
+        let dir = tempdir().unwrap();
+        let mut old = archive_stub_task(0, Some(b"old"), dir.path());
+        archive_stub_task(1, Some(b"new"), dir.path());
+
+        old.archived_at = Local::now() - Duration::hours(2);
+        let metadata = serde_json::to_string(&old).unwrap();
+        fs::write(
+            get_entry_path(old.id, dir.path()).join(METADATA_FILE),
+            metadata,
+        )
+        .unwrap();
+
+        apply_archive_retention(dir.path(), Some(3600), None);
+        let archived = get_archived_tasks(dir.path());
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].task.id, 1);
+    }
+}
@@ -0,0 +1,120 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use serde_derive::{Deserialize, Serialize};
+
+/// The I/O scheduling class and priority of a task, as known from `ionice`.
+///
+/// Priorities are parsed from the name of their class, optionally followed by a level,
+/// e.g. `idle`, `best-effort` or `best-effort:7`.
+/// Levels range from 0 (highest) to 7 (lowest) and default to 4.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum IoPriority {
+    /// The task always gets first access to the disk. This needs root privileges.
+    Realtime(u8),
+    /// The default class of all processes.
+    BestEffort(u8),
+    /// The task only gets disk time, if no other process needs it.
+    Idle,
+}
+
+/// The level that's used, if no level is specified.
+const DEFAULT_LEVEL: u8 = 4;
+
+/// The lowest priority level, which is still supported by the kernel.
+pub const MAX_LEVEL: u8 = 7;
+
+impl IoPriority {
+    /// Check whether the level of the priority is supported by the kernel.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            IoPriority::Realtime(level) | IoPriority::BestEffort(level) => *level <= MAX_LEVEL,
+            IoPriority::Idle => true,
+        }
+    }
+}
+
+impl fmt::Display for IoPriority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IoPriority::Realtime(level) => write!(f, "realtime:{}", level),
+            IoPriority::BestEffort(level) => write!(f, "best-effort:{}", level),
+            IoPriority::Idle => write!(f, "idle"),
+        }
+    }
+}
+
+impl FromStr for IoPriority {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let lowercase = name.trim().to_lowercase();
+        let (class, level) = match lowercase.split_once(':') {
+            Some((class, level)) => {
+                let level = level
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|level| *level <= MAX_LEVEL)
+                    .ok_or_else(|| format!("Invalid I/O priority level \"{}\"", level))?;
+                (class, Some(level))
+            }
+            None => (lowercase.as_str(), None),
+        };
+
+        match (class, level) {
+            ("realtime", level) => Ok(IoPriority::Realtime(level.unwrap_or(DEFAULT_LEVEL))),
+            ("best-effort", level) => Ok(IoPriority::BestEffort(level.unwrap_or(DEFAULT_LEVEL))),
+            ("idle", None) => Ok(IoPriority::Idle),
+            ("idle", Some(_)) => Err(String::from("The idle I/O class doesn't have levels")),
+            _ => Err(format!(
+                "Unknown I/O class \"{}\". Use realtime, best-effort or idle",
+                class
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for IoPriority {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        IoPriority::from_str(&name)
+    }
+}
+
+impl From<IoPriority> for String {
+    fn from(priority: IoPriority) -> Self {
+        priority.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_io_priority() {
+        assert_eq!(IoPriority::from_str("idle"), Ok(IoPriority::Idle));
+        assert_eq!(
+            IoPriority::from_str("best-effort"),
+            Ok(IoPriority::BestEffort(DEFAULT_LEVEL))
+        );
+        assert_eq!(
+            IoPriority::from_str("Realtime:7"),
+            Ok(IoPriority::Realtime(7))
+        );
+
+        assert!(IoPriority::from_str("best-effort:8").is_err());
+        assert!(IoPriority::from_str("idle:1").is_err());
+        assert!(IoPriority::from_str("fast").is_err());
+    }
+
+    #[test]
+    fn test_io_priority_level_range() {
+        assert!(IoPriority::BestEffort(0).is_valid());
+        assert!(IoPriority::Realtime(MAX_LEVEL).is_valid());
+        assert!(!IoPriority::BestEffort(MAX_LEVEL + 1).is_valid());
+    }
+}
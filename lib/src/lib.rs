@@ -0,0 +1,38 @@
+//! Pueue-lib is a shared library used by the `pueue` and `pueued` binary.
+//!
+//! It contains common components such as:
+//!
+//! - Everything about the [Task](task::Task), [TaskResult](task::TaskResult) etc.
+//! - The [State](state::State), which represents the current state of the daemon.
+//! - Network code. Everything you need to communicate with the daemon.
+//! - Other helper code and structs.
+//!
+//! Pueue-lib is a stand-alone crate, so it can be used by third-party applications to either
+//! manipulate or monitor the daemon or to simply write your own front-end for the daemon.
+
+/// Contains helper for command aliasing. This will most likely be not interesting for you.
+pub mod aliasing;
+/// The archive, which keeps the logs of removed tasks.
+pub mod archive;
+/// Platform independent representation of the I/O scheduling priority of tasks.
+pub mod io_priority;
+/// Helper classes to read and write log files of Pueue's tasks.
+pub mod log;
+/// Platform independent representation of what happens to logs, which exceed their maximum size.
+pub mod log_rotation;
+/// Everything you need to communicate with either the daemon or the client.
+pub mod network;
+/// Platform specific code, mainly used to get platform specific working directories.
+mod platform;
+/// Recurring tasks, which are spawned by the daemon according to a cron expression.
+pub mod schedule;
+/// Pueue's representation of configuration and their default settings.
+pub mod settings;
+/// Platform independent representation of the process signals, which can be sent to tasks.
+pub mod signal;
+/// Parsing of sizes such as "512M", which are used for memory and log limits.
+pub mod size;
+/// The main struct used to represent the daemon's current state.
+pub mod state;
+/// Everything regarding Pueue's task
+pub mod task;
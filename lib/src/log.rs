@@ -0,0 +1,466 @@
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::fs::{self, hard_link, read_dir, remove_file, rename, File};
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use chrono::prelude::*;
+use log::error;
+use snap::read::FrameDecoder;
+use snap::write::FrameEncoder;
+
+/// The stream of a task, from which a line of its combined log originates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+impl LogStream {
+    /// The name of the stream in the combined log.
+    fn name(self) -> &'static str {
+        match self {
+            LogStream::Stdout => "out",
+            LogStream::Stderr => "err",
+        }
+    }
+}
+
+/// A single line of the combined log of a task.
+#[derive(Clone, Debug)]
+pub struct CombinedLine {
+    /// The point in time, at which the task started to write the line.
+    pub time: DateTime<Local>,
+    pub stream: LogStream,
+    pub text: String,
+}
+
+/// Return the paths to the `(stdout, stderr)` log files of a task.
+pub fn get_log_paths(task_id: usize, path: &Path) -> (PathBuf, PathBuf) {
+    let task_log_dir = path.join("task_logs");
+    let out_path = task_log_dir.join(format!("{}_stdout.log", task_id));
+    let err_path = task_log_dir.join(format!("{}_stderr.log", task_id));
+    (out_path, err_path)
+}
+
+/// Return the path to the combined log of a task. \
+/// This log only exists, if the task has been started while `combined_logs` was enabled.
+pub fn get_combined_log_path(task_id: usize, path: &Path) -> PathBuf {
+    path.join("task_logs")
+        .join(format!("{}_combined.log", task_id))
+}
+
+/// Create and return the file handle for the `(stdout, stderr)` log files of a task.
+pub fn create_log_file_handles(task_id: usize, path: &Path) -> Result<(File, File)> {
+    let (out_path, err_path) = get_log_paths(task_id, path);
+    let stdout = File::create(out_path)?;
+    let stderr = File::create(err_path)?;
+
+    Ok((stdout, stderr))
+}
+
+/// Return the file handle for the `(stdout, stderr)` log files of a task.
+pub fn get_log_file_handles(task_id: usize, path: &Path) -> Result<(File, File)> {
+    let (out_path, err_path) = get_log_paths(task_id, path);
+    let stdout = File::open(out_path)?;
+    let stderr = File::open(err_path)?;
+
+    Ok((stdout, stderr))
+}
+
+/// Remove the the log files of a task, including the rotated segments of its logs.
+pub fn clean_log_handles(task_id: usize, path: &Path) {
+    let (out_path, err_path) = get_log_paths(task_id, path);
+    let combined_path = get_combined_log_path(task_id, path);
+    for log_path in [&out_path, &err_path, &combined_path].iter() {
+        for segment in get_rotated_segments(log_path) {
+            if let Err(err) = remove_file(&segment) {
+                error!(
+                    "Failed to remove log segment {:?} of task {} with error {:?}",
+                    segment, task_id, err
+                );
+            }
+        }
+    }
+
+    if let Err(err) = remove_file(out_path) {
+        error!(
+            "Failed to remove stdout file for task {} with error {:?}",
+            task_id, err
+        );
+    };
+    if let Err(err) = remove_file(err_path) {
+        error!(
+            "Failed to remove stderr file for task {} with error {:?}",
+            task_id, err
+        );
+    };
+
+    if combined_path.exists() {
+        if let Err(err) = remove_file(combined_path) {
+            error!(
+                "Failed to remove combined log file for task {} with error {:?}",
+                task_id, err
+            );
+        }
+    }
+}
+
+/// Return the `(stdout, stderr)` output of a task, including the rotated segments of its logs. \
+/// Task output is compressed using [snap] to save some memory and bandwidth.
+pub fn read_and_compress_log_files(
+    task_id: usize,
+    path: &Path,
+    lines: Option<usize>,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let (out_path, err_path) = get_log_paths(task_id, path);
+    let (mut stdout_log, mut stderr_log) = match (open_log(&out_path), open_log(&err_path)) {
+        (Ok(stdout), Ok(stderr)) => (stdout, stderr),
+        (Err(err), _) | (_, Err(err)) => {
+            bail!("Error while opening the output files: {}", err);
+        }
+    };
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+
+    if let Some(lines) = lines {
+        // Get the last few lines of both files
+        let stdout_bytes = read_last_log_lines(&out_path, lines)?.into_bytes();
+        let stderr_bytes = read_last_log_lines(&err_path, lines)?.into_bytes();
+        let mut stdout_cursor = Cursor::new(stdout_bytes);
+        let mut stderr_cursor = Cursor::new(stderr_bytes);
+
+        // Compress the partial log input and pipe it into the snappy compressor
+        let mut stdout_compressor = FrameEncoder::new(&mut stdout);
+        io::copy(&mut stdout_cursor, &mut stdout_compressor)?;
+        let mut stderr_compressor = FrameEncoder::new(&mut stderr);
+        io::copy(&mut stderr_cursor, &mut stderr_compressor)?;
+    } else {
+        // Compress the full log input and pipe it into the snappy compressor
+        let mut stdout_compressor = FrameEncoder::new(&mut stdout);
+        io::copy(&mut stdout_log, &mut stdout_compressor)?;
+        let mut stderr_compressor = FrameEncoder::new(&mut stderr);
+        io::copy(&mut stderr_log, &mut stderr_compressor)?;
+    }
+
+    Ok((stdout, stderr))
+}
+
+/// Write a line to a combined log. \
+/// Each line consists of its timestamp, the name of its stream and the text itself,
+/// e.g. `2021-03-01T12:00:00.123+01:00 err Some error`.
+pub fn write_combined_line<W: Write>(
+    writer: &mut W,
+    time: DateTime<Local>,
+    stream: LogStream,
+    text: &[u8],
+) -> io::Result<()> {
+    let mut line = format!(
+        "{} {} ",
+        time.to_rfc3339_opts(SecondsFormat::Millis, false),
+        stream.name()
+    )
+    .into_bytes();
+    line.extend_from_slice(text);
+    line.push(b'\n');
+
+    // Write the whole line at once, so lines never get mixed up.
+    writer.write_all(&line)
+}
+
+/// Parse a line of a combined log. Returns `None` for malformed lines.
+pub fn parse_combined_line(line: &str) -> Option<CombinedLine> {
+    let mut parts = line.splitn(3, ' ');
+    let time = DateTime::parse_from_rfc3339(parts.next()?)
+        .ok()?
+        .with_timezone(&Local);
+    let stream = match parts.next()? {
+        "out" => LogStream::Stdout,
+        "err" => LogStream::Stderr,
+        _ => return None,
+    };
+    let text = parts.next().unwrap_or_default().to_string();
+
+    Some(CombinedLine { time, stream, text })
+}
+
+/// Read the combined log of a task.
+///
+/// stderr_only: Only return the lines that have been written to stderr.
+/// lines: Only return the last few lines. This is applied after filtering for stderr.
+pub fn read_combined_log(
+    task_id: usize,
+    path: &Path,
+    lines: Option<usize>,
+    stderr_only: bool,
+) -> Result<Vec<CombinedLine>> {
+    let log = open_log(&get_combined_log_path(task_id, path))?;
+    read_combined_lines(log, lines, stderr_only)
+}
+
+/// Read and filter the lines of a combined log, just like [read_combined_log].
+pub fn read_combined_lines<R: Read>(
+    log: R,
+    lines: Option<usize>,
+    stderr_only: bool,
+) -> Result<Vec<CombinedLine>> {
+    let mut result = VecDeque::new();
+    for line in BufReader::new(log).split(b'\n') {
+        let line = match parse_combined_line(&String::from_utf8_lossy(&line?)) {
+            Some(line) => line,
+            None => continue,
+        };
+        if stderr_only && line.stream != LogStream::Stderr {
+            continue;
+        }
+
+        result.push_back(line);
+        if matches!(lines, Some(lines) if result.len() > lines) {
+            result.pop_front();
+        }
+    }
+
+    Ok(result.into_iter().collect())
+}
+
+/// Return the combined log of a task, if it has one. \
+/// The lines are filtered just like in [read_combined_log] and compressed using [snap].
+pub fn read_and_compress_combined_log(
+    task_id: usize,
+    path: &Path,
+    lines: Option<usize>,
+    stderr_only: bool,
+) -> Result<Option<Vec<u8>>> {
+    if !get_combined_log_path(task_id, path).exists() {
+        return Ok(None);
+    }
+
+    let lines = read_combined_log(task_id, path, lines, stderr_only)?;
+    Ok(Some(compress_combined_lines(&lines)?))
+}
+
+/// Compress the lines of a combined log using [snap].
+pub fn compress_combined_lines(lines: &[CombinedLine]) -> io::Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+    let mut compressor = FrameEncoder::new(&mut compressed);
+    for line in lines {
+        write_combined_line(
+            &mut compressor,
+            line.time,
+            line.stream,
+            line.text.as_bytes(),
+        )?;
+    }
+    compressor.flush()?;
+    drop(compressor);
+
+    Ok(compressed)
+}
+
+/// Remove all files in the log directory.
+pub fn reset_task_log_directory(path: &Path) {
+    let task_log_dir = path.join("task_logs");
+
+    let files = read_dir(task_log_dir).expect("Failed to open pueue's task_logs directory");
+
+    for file in files.flatten() {
+        if let Err(err) = remove_file(file.path()) {
+            error!("Failed to delete log file: {}", err);
+        }
+    }
+}
+
+/// Read the last `amount` lines of a log to a string, including its rotated segments.
+pub fn read_last_log_lines(log_path: &Path, amount: usize) -> io::Result<String> {
+    if get_rotated_segments(log_path).is_empty() {
+        return Ok(read_last_lines(&mut File::open(log_path)?, amount));
+    }
+
+    // Rotated logs are limited by their maximum size, which is why they're simply read completely.
+    let mut output = Vec::new();
+    open_log(log_path)?.read_to_end(&mut output)?;
+
+    Ok(take_last_lines(&String::from_utf8_lossy(&output), amount))
+}
+
+/// Return the last `amount` lines of a text.
+pub fn take_last_lines(text: &str, amount: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+
+    lines[lines.len().saturating_sub(amount)..].join("\n")
+}
+
+/// Read the last `amount` lines of a file to a string.
+///
+/// TODO: This is super imperformant, but works as long as we don't use the last
+/// 1000 lines. It would be cleaner to seek to the beginning of the requested
+/// position and simply stream the content.
+pub fn read_last_lines(file: &mut File, amount: usize) -> String {
+    let last_lines: Vec<String> = rev_lines::RevLines::new(BufReader::new(file))
+        .expect("Failed to read last lines of file")
+        .take(amount)
+        .collect();
+
+    last_lines
+        .into_iter()
+        .rev()
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Return the path of a rotated segment of a log. The most recent segment has the number 1.
+fn get_segment_path(log_path: &Path, number: usize, compressed: bool) -> PathBuf {
+    let mut path = log_path.as_os_str().to_owned();
+    path.push(format!(".{}", number));
+    if compressed {
+        path.push(".sz");
+    }
+
+    PathBuf::from(path)
+}
+
+/// Compressed segments are recognized by their `.sz` extension.
+fn is_compressed(segment: &Path) -> bool {
+    segment.extension() == Some(OsStr::new("sz"))
+}
+
+/// Return the paths of all rotated segments of a log, from the oldest to the most recent one.
+pub fn get_rotated_segments(log_path: &Path) -> Vec<PathBuf> {
+    let mut segments = Vec::new();
+    for number in 1.. {
+        let segment = [false, true]
+            .iter()
+            .map(|compressed| get_segment_path(log_path, number, *compressed))
+            .find(|segment| segment.exists());
+        match segment {
+            Some(segment) => segments.push(segment),
+            None => break,
+        }
+    }
+    segments.reverse();
+
+    segments
+}
+
+/// Open a log for reading, including its rotated segments. \
+/// The output is read in the order it has been written, from the oldest segment to the log itself.
+pub fn open_log(log_path: &Path) -> io::Result<Box<dyn Read + Send>> {
+    let mut reader: Box<dyn Read + Send> = Box::new(io::empty());
+    for segment in get_rotated_segments(log_path) {
+        // The segment might have been removed by a rotation in the meantime.
+        let file = match File::open(&segment) {
+            Ok(file) => file,
+            Err(_) => continue,
+        };
+        reader = if is_compressed(&segment) {
+            Box::new(reader.chain(FrameDecoder::new(file)))
+        } else {
+            Box::new(reader.chain(file))
+        };
+    }
+
+    Ok(Box::new(reader.chain(File::open(log_path)?)))
+}
+
+/// Rotate a log and return the newly created file, in which the log continues.
+///
+/// The log becomes the most recent segment, while older segments are moved back by one.
+/// Only the latest `keep` segments are kept. If `compress` is set, the new segment is compressed.
+/// The log is atomically replaced by the new file, so readers never find it missing.
+pub fn rotate_log(log_path: &Path, keep: usize, compress: bool) -> io::Result<File> {
+    let segments = get_rotated_segments(log_path);
+    let count = segments.len();
+    for (index, segment) in segments.iter().enumerate() {
+        let number = count - index;
+        if number >= keep {
+            remove_file(segment)?;
+        } else {
+            rename(
+                segment,
+                get_segment_path(log_path, number + 1, is_compressed(segment)),
+            )?;
+        }
+    }
+
+    let mut new_path = log_path.as_os_str().to_owned();
+    new_path.push(".new");
+    let file = File::create(&new_path)?;
+
+    if keep > 0 {
+        let segment = get_segment_path(log_path, 1, compress);
+        if compress {
+            let mut compressor = FrameEncoder::new(File::create(segment)?);
+            io::copy(&mut File::open(log_path)?, &mut compressor)?;
+            compressor.flush()?;
+        } else {
+            hard_link(log_path, segment)?;
+        }
+    }
+    rename(new_path, log_path)?;
+
+    Ok(file)
+}
+
+/// Continuously reads the new output of a log, while its task is running. \
+/// Rotations of the log are followed, so no output is lost, when the log is moved to a segment.
+pub struct LogTail {
+    path: PathBuf,
+    handle: File,
+    rotated: bool,
+}
+
+impl LogTail {
+    /// Continue reading the log at the given path from the current position of the handle.
+    pub fn new(path: PathBuf, handle: File) -> Self {
+        LogTail {
+            path,
+            handle,
+            rotated: false,
+        }
+    }
+
+    /// Returns whether the log has been rotated since the last call.
+    pub fn was_rotated(&mut self) -> bool {
+        std::mem::replace(&mut self.rotated, false)
+    }
+
+    /// Check whether the log at our path has been replaced by a new file.
+    fn is_rotated(&self) -> bool {
+        let (current, ours) = match (fs::metadata(&self.path), self.handle.metadata()) {
+            (Ok(current), Ok(ours)) => (current, ours),
+            _ => return false,
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            current.dev() != ours.dev() || current.ino() != ours.ino()
+        }
+        #[cfg(not(unix))]
+        {
+            current.created().ok() != ours.created().ok()
+        }
+    }
+}
+
+impl Read for LogTail {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.handle.read(buf)?;
+        if count > 0 || buf.is_empty() || !self.is_rotated() {
+            return Ok(count);
+        }
+
+        // The old file doesn't get any new output after the rotation.
+        // Anything that has been written right before the rotation is read first.
+        let count = self.handle.read(buf)?;
+        if count > 0 {
+            return Ok(count);
+        }
+        self.handle = File::open(&self.path)?;
+        self.rotated = true;
+
+        self.handle.read(buf)
+    }
+}
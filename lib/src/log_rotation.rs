@@ -0,0 +1,95 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use serde_derive::{Deserialize, Serialize};
+
+/// What happens, once a log of a task exceeds its maximum size.
+///
+/// Rotations are parsed from their name, optionally followed by the amount of rotated segments
+/// that are kept, e.g. `truncate`, `rotate` or `compress:5`. The amount defaults to 3.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum LogRotation {
+    /// Only the latest output is kept, just like in a ring buffer.
+    /// The log never exceeds its maximum size.
+    #[default]
+    Truncate,
+    /// The log is moved to a numbered segment and a new log is started.
+    /// Only the latest segments are kept.
+    Rotate(usize),
+    /// Just like `Rotate`, but the segments are compressed.
+    Compress(usize),
+}
+
+/// The amount of segments that are kept, if no amount is specified.
+const DEFAULT_SEGMENTS: usize = 3;
+
+impl LogRotation {
+    /// Return the size at which a log with this maximum size is rotated,
+    /// how many of its rotated segments are kept and whether they're compressed.
+    ///
+    /// Truncated logs are rotated at half of their maximum size and keep a single segment.
+    /// This way, there's always at least half of the maximum size of the latest output.
+    pub fn segments(&self, max_size: u64) -> (u64, usize, bool) {
+        match self {
+            LogRotation::Truncate => ((max_size / 2).max(1), 1, false),
+            LogRotation::Rotate(segments) => (max_size, *segments, false),
+            LogRotation::Compress(segments) => (max_size, *segments, true),
+        }
+    }
+}
+
+impl fmt::Display for LogRotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogRotation::Truncate => write!(f, "truncate"),
+            LogRotation::Rotate(segments) => write!(f, "rotate:{}", segments),
+            LogRotation::Compress(segments) => write!(f, "compress:{}", segments),
+        }
+    }
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let lowercase = name.trim().to_lowercase();
+        let (kind, segments) = match lowercase.split_once(':') {
+            Some((kind, segments)) => {
+                let segments = segments
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid amount of log segments \"{}\"", segments))?;
+                (kind, Some(segments))
+            }
+            None => (lowercase.as_str(), None),
+        };
+
+        match (kind, segments) {
+            ("truncate", None) => Ok(LogRotation::Truncate),
+            ("truncate", Some(_)) => Err(String::from("Truncated logs don't keep segments")),
+            ("rotate", segments) => Ok(LogRotation::Rotate(segments.unwrap_or(DEFAULT_SEGMENTS))),
+            ("compress", segments) => {
+                Ok(LogRotation::Compress(segments.unwrap_or(DEFAULT_SEGMENTS)))
+            }
+            _ => Err(format!(
+                "Unknown log rotation \"{}\". Use truncate, rotate or compress",
+                kind
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for LogRotation {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        LogRotation::from_str(&name)
+    }
+}
+
+impl From<LogRotation> for String {
+    fn from(rotation: LogRotation) -> Self {
+        rotation.to_string()
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::archive::ArchivedTask;
+use crate::io_priority::IoPriority;
+use crate::log_rotation::LogRotation;
+use crate::signal::Signal;
+use crate::state::{GroupStatus, State};
+use crate::task::{DependencyCondition, Task, TaskResult};
+
+/// This is the main message enum. \
+/// Everything that's communicated in Pueue can be serialized as this enum.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum Message {
+    Add(Box<AddMessage>),
+    Remove(Vec<usize>),
+    Switch(SwitchMessage),
+    Stash(Vec<usize>),
+    Enqueue(EnqueueMessage),
+
+    Start(StartMessage),
+    Restart(RestartMessage),
+    Pause(PauseMessage),
+    Kill(KillMessage),
+
+    Send(SendMessage),
+    Signal(SignalMessage),
+    EditRequest(usize),
+    EditResponse(EditResponseMessage),
+    Edit(EditMessage),
+    Group(GroupMessage),
+    GroupResponse(GroupResponseMessage),
+    Schedule(ScheduleMessage),
+    Submit(SubmitMessage),
+    /// The ids of all tasks of a submitted workflow by their step name.
+    SubmitResponse(BTreeMap<String, usize>),
+
+    Status,
+    StatusResponse(Box<State>),
+    Log(LogRequestMessage),
+    LogResponse(BTreeMap<usize, TaskLogMessage>),
+    /// List all archived tasks.
+    History,
+    HistoryResponse(Vec<ArchivedTask>),
+    Stream(String),
+    StreamRequest(StreamRequestMessage),
+    TaskStream(TaskStreamMessage),
+    /// The last message of a stream, which is sent once the followed task finished.
+    StreamEnd(StreamEndMessage),
+    /// Attach to the running task with this id.
+    /// While attached, the client sends its input via `AttachInput`, whenever the user types
+    /// something. Meanwhile, the daemon sends any new output of the task as `Stream`,
+    /// until the client sends `Detach` or the task finishes.
+    AttachRequest(usize),
+    AttachInput(AttachInputMessage),
+    Detach,
+    /// The boolean decides, whether the children should be get a SIGTERM as well.
+    Reset(ResetMessage),
+    Clean(CleanMessage),
+    DaemonShutdown,
+
+    Success(String),
+    Failure(String),
+
+    Parallel(ParallelMessage),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AddMessage {
+    pub command: String,
+    pub path: String,
+    pub envs: HashMap<String, String>,
+    pub start_immediately: bool,
+    pub stashed: bool,
+    pub group: String,
+    pub enqueue_at: Option<DateTime<Local>>,
+    pub dependencies: Vec<usize>,
+    /// The conditions of all dependencies, which don't need to finish successfully.
+    pub dependency_conditions: BTreeMap<usize, DependencyCondition>,
+    pub label: Option<String>,
+    /// The maximum runtime of the task in seconds.
+    pub timeout: Option<u64>,
+    pub retries: usize,
+    /// The delay in seconds before the first retry of a failed task.
+    pub retry_delay: Option<u64>,
+    pub priority: i32,
+    /// Named resources and the amount of each resource, that's needed by the task.
+    pub resources: BTreeMap<String, usize>,
+    /// How many of its group's parallel slots the task occupies.
+    pub slots: usize,
+    /// The maximum amount of memory in bytes, which the task may use.
+    pub memory_limit: Option<u64>,
+    /// The maximum amount of CPUs, which the task may use.
+    pub cpu_limit: Option<f64>,
+    /// The niceness of the task's process.
+    pub nice: Option<i32>,
+    /// The I/O scheduling class and priority of the task's process.
+    pub ionice: Option<IoPriority>,
+    /// The maximum amount of file descriptors, which the task's process may open.
+    pub max_open_files: Option<u64>,
+    /// The maximum CPU time in seconds, which the task's process may use.
+    pub max_cpu_time: Option<u64>,
+    /// The shell that executes the command, e.g. `["bash", "-c"]`.
+    pub shell: Option<Vec<String>>,
+    /// Execute the command directly instead of passing it to a shell.
+    pub no_shell: bool,
+    /// Run the task on a pseudo terminal.
+    pub pty: bool,
+    /// The maximum size of each log of the task in bytes.
+    pub max_log_size: Option<u64>,
+    pub log_rotation: Option<LogRotation>,
+    /// The inputs of a new task array.
+    /// If there are any, a task is added for each input and the placeholders in the command
+    /// are replaced by the respective input.
+    pub each: Vec<String>,
+    /// Add the task to an existing task array. This is used when restarting tasks.
+    pub array_id: Option<usize>,
+    pub print_task_id: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SwitchMessage {
+    pub task_id_1: usize,
+    pub task_id_2: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EnqueueMessage {
+    pub task_ids: Vec<usize>,
+    pub enqueue_at: Option<DateTime<Local>>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct StartMessage {
+    pub task_ids: Vec<usize>,
+    pub group: String,
+    pub all: bool,
+    pub children: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RestartMessage {
+    pub tasks: Vec<TasksToRestart>,
+    pub start_immediately: bool,
+    pub stashed: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct TasksToRestart {
+    pub task_id: usize,
+    pub command: String,
+    pub path: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PauseMessage {
+    pub task_ids: Vec<usize>,
+    pub group: String,
+    pub wait: bool,
+    pub all: bool,
+    pub children: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct KillMessage {
+    pub task_ids: Vec<usize>,
+    pub group: String,
+    pub all: bool,
+    /// Kill all tasks of this task array.
+    pub array_id: Option<usize>,
+    /// The signal that's sent instead of SIGKILL.
+    pub signal: Option<Signal>,
+    /// The seconds before a task, that's still running after receiving the signal,
+    /// is killed with SIGKILL.
+    pub grace: Option<u64>,
+    pub children: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SendMessage {
+    pub task_id: usize,
+    pub input: String,
+}
+
+/// The input of an attached client, which is passed to the task as it is.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AttachInputMessage {
+    pub task_id: usize,
+    pub input: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SignalMessage {
+    pub task_id: usize,
+    pub signal: Signal,
+    pub children: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EditMessage {
+    pub task_id: usize,
+    pub command: String,
+    pub path: String,
+    pub timeout: Option<u64>,
+    pub priority: i32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EditResponseMessage {
+    pub task_id: usize,
+    pub command: String,
+    pub path: String,
+    pub timeout: Option<u64>,
+    pub priority: i32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GroupMessage {
+    pub add: Option<String>,
+    pub remove: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GroupResponseMessage {
+    pub groups: BTreeMap<String, GroupStatus>,
+    pub settings: BTreeMap<String, usize>,
+}
+
+/// Manage recurring tasks.
+/// Listing schedules is done via the normal status request.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ScheduleMessage {
+    Add(Box<ScheduleAddMessage>),
+    Remove(Vec<usize>),
+    Pause(Vec<usize>),
+    Resume(Vec<usize>),
+}
+
+/// The template of the task is specified the same way as in [AddMessage].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScheduleAddMessage {
+    /// A cron expression, either with five (minute precision) or six (second precision) fields.
+    pub expression: String,
+    pub command: String,
+    pub path: String,
+    pub envs: HashMap<String, String>,
+    pub group: String,
+    pub dependencies: Vec<usize>,
+    pub label: Option<String>,
+    pub timeout: Option<u64>,
+    pub retries: usize,
+    pub retry_delay: Option<u64>,
+    pub priority: i32,
+}
+
+/// Submit a workflow, whose steps are added as tasks all at once.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SubmitMessage {
+    pub workflow: Workflow,
+    /// The path of all steps, that don't specify their own path.
+    /// Relative step paths are relative to this path.
+    pub path: String,
+    pub envs: HashMap<String, String>,
+}
+
+/// The content of a workflow file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workflow {
+    pub steps: Vec<WorkflowStep>,
+}
+
+/// A single named step of a workflow.
+/// Dependencies reference other steps of the same workflow by their name.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowStep {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Steps that have to succeed before this step is started.
+    #[serde(default)]
+    pub after: Vec<String>,
+    /// Steps that have to fail before this step is started.
+    #[serde(default)]
+    pub after_failure: Vec<String>,
+    /// Steps that have to finish before this step is started.
+    #[serde(default)]
+    pub after_any: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ResetMessage {
+    pub children: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CleanMessage {
+    pub successful_only: bool,
+}
+
+/// `err` decides, whether you should stream stderr or stdout.
+/// If several tasks or a group are followed, their output is streamed line by line.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StreamRequestMessage {
+    pub task_ids: Vec<usize>,
+    pub group: Option<String>,
+    pub err: bool,
+}
+
+/// New output of one of several followed tasks. Only contains complete lines.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TaskStreamMessage {
+    pub task_id: usize,
+    pub text: String,
+}
+
+/// The result is missing, if the task has been removed while it was followed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StreamEndMessage {
+    pub task_id: usize,
+    pub result: Option<TaskResult>,
+}
+
+/// Request logs for specific tasks.
+/// An empty task_id vector will return logs of all tasks.
+/// If send_logs is false, the daemon won't send the logs
+/// and the client will read logs from the local disk.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LogRequestMessage {
+    pub task_ids: Vec<usize>,
+    pub send_logs: bool,
+    pub lines: Option<usize>,
+    pub full: bool,
+    /// Send the combined log of each task instead, if it has one.
+    pub timestamps: bool,
+    /// Only send the lines of stderr. This only affects combined logs.
+    pub stderr_only: bool,
+    /// The ids are ids of archived tasks. Logs of archived tasks are always sent.
+    pub archived: bool,
+}
+
+/// Helper struct for sending tasks and their log output to the client.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TaskLogMessage {
+    pub task: Task,
+    pub stdout: Option<Vec<u8>>,
+    pub stderr: Option<Vec<u8>>,
+    /// The compressed combined log of the task, if it has been requested and the task has one.
+    pub combined: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ParallelMessage {
+    pub parallel_tasks: usize,
+    pub group: String,
+}
+
+pub fn create_success_message<T: ToString>(text: T) -> Message {
+    Message::Success(text.to_string())
+}
+
+pub fn create_failure_message<T: ToString>(text: T) -> Message {
+    Message::Failure(text.to_string())
+}
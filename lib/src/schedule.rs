@@ -0,0 +1,36 @@
+use chrono::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::task::Task;
+
+/// A recurring task.
+///
+/// Each time the cron expression fires, the daemon adds a fresh copy of the template `task`
+/// to the queue. The spawned tasks are completely independent of each other and of the schedule.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Schedule {
+    pub id: usize,
+    /// The cron expression, as it has been specified by the user.
+    pub expression: String,
+    /// The template, which is copied every time the schedule fires.
+    pub task: Task,
+    /// Paused schedules don't spawn any tasks.
+    pub paused: bool,
+    /// The next point in time this schedule will fire.
+    pub next_run: DateTime<Local>,
+    /// The id of the task that has been spawned most recently.
+    pub last_task_id: Option<usize>,
+}
+
+impl Schedule {
+    pub fn new(expression: String, task: Task, next_run: DateTime<Local>) -> Schedule {
+        Schedule {
+            id: 0,
+            expression,
+            task,
+            paused: false,
+            next_run,
+            last_task_id: None,
+        }
+    }
+}
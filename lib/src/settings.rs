@@ -0,0 +1,344 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{create_dir_all, File};
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Result};
+use config::Config;
+use log::info;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::io_priority::IoPriority;
+use crate::log_rotation::LogRotation;
+use crate::platform::directories::*;
+use crate::signal::Signal;
+use crate::size::deserialize_size;
+
+/// All settings which are used by both, the client and the daemon
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Shared {
+    /// The directory that is used for all runtime information. \
+    /// I.e. task logs, sockets, state dumps, etc.
+    pub pueue_directory: PathBuf,
+    /// If this is set to true, unix sockets will be used.
+    /// Otherwise we default to TCP+TLS
+    #[cfg(not(target_os = "windows"))]
+    pub use_unix_socket: bool,
+    /// The path to the unix socket.
+    #[cfg(not(target_os = "windows"))]
+    pub unix_socket_path: PathBuf,
+
+    /// The TCP hostname/ip address.
+    pub host: String,
+    /// The TCP port.
+    pub port: String,
+    /// The path to the TLS certificate used by the daemon. \
+    /// This is also used by the client to verify the daemon's identity.
+    pub daemon_cert: PathBuf,
+    /// The path to the TLS key used by the daemon.
+    pub daemon_key: PathBuf,
+    /// The path to the file containing the shared secret used to authenticate the client.
+    pub shared_secret_path: PathBuf,
+}
+
+/// All settings which are used by the client
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Client {
+    /// Whether the client should read the logs directly from disk or whether it should
+    /// request the data from the daemon via socket.
+    pub read_local_logs: bool,
+    /// Whether the client should show a confirmation question on potential dangerous actions.
+    pub show_confirmation_questions: bool,
+    /// Whether aliases specified in `pueue_aliases.yml` should be expanded in the `pueue status`
+    /// or shown in their short form.
+    pub show_expanded_aliases: bool,
+    /// Whether the client should use dark shades instead of regular colors.
+    #[serde(default = "default_dark_mode")]
+    pub dark_mode: bool,
+    /// The max amount of lines each task get's in the `pueue status` view.
+    pub max_status_lines: Option<usize>,
+}
+
+/// All settings which are used by the daemon
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Daemon {
+    /// How many parallel tasks a group should have by default
+    pub default_parallel_tasks: usize,
+    /// Whether a group should be paused as soon as a single task fails
+    pub pause_group_on_failure: bool,
+    /// Whether the daemon (and all groups) should be paused as soon as a single task fails
+    pub pause_all_on_failure: bool,
+    /// The callback that's called whenever a task finishes.
+    pub callback: Option<String>,
+    /// This shouldn't be manipulated manually if the daemon is running.
+    /// This represents all known groups and their amount of parallel tasks.
+    pub groups: BTreeMap<String, usize>,
+    /// Default values for tasks of specific groups.
+    #[serde(default)]
+    pub group_defaults: BTreeMap<String, GroupDefaults>,
+    /// System load thresholds of specific groups.
+    #[serde(default)]
+    pub group_limits: BTreeMap<String, GroupLimits>,
+    /// The capacity of named resources, which can be shared by tasks across all groups.
+    /// Resources that aren't listed here have a capacity of 1 and thereby act as a simple lock.
+    #[serde(default)]
+    pub resources: BTreeMap<String, usize>,
+    /// A delegated cgroup v2 directory, which is managed by the daemon.
+    /// If specified, each task is placed into its own cgroup below this directory.
+    /// This is necessary to limit the memory and CPU usage of tasks.
+    #[serde(default)]
+    pub cgroup: Option<PathBuf>,
+    /// The shell that executes the commands of tasks and callbacks, e.g. `["bash", "-c"]`.
+    /// The command is passed as the last argument. Defaults to `sh -c`.
+    /// A shell that only consists of a program, e.g. `["bash"]`, is completed with `-c`.
+    #[serde(default)]
+    pub shell: Option<Vec<String>>,
+    /// Pipe the output of tasks through the daemon, which additionally writes a combined log of
+    /// stdout and stderr. Each line of that log has a timestamp, see `pueue log --timestamps`.
+    #[serde(default)]
+    pub combined_logs: bool,
+    /// Keep the compressed logs of tasks in an archive, when they're cleaned or removed.
+    /// Archived tasks are listed by `pueue history`.
+    #[serde(default)]
+    pub archive_logs: bool,
+    /// Archived tasks are removed from the archive after this many seconds.
+    #[serde(default)]
+    pub archive_max_age: Option<u64>,
+    /// The maximum size of the archive in bytes, e.g. `4096` or `512M`.
+    /// The oldest archived tasks are removed first.
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub archive_max_size: Option<u64>,
+}
+
+/// Default values, which are used for all tasks of a group. \
+/// They're applied once a task is started. Values that are explicitly specified for a task
+/// always take precedence.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct GroupDefaults {
+    /// The maximum runtime of a task in seconds.
+    pub timeout: Option<u64>,
+    /// The signal that's sent to tasks, when they're killed or exceed their timeout.
+    /// Defaults to SIGKILL.
+    #[serde(default)]
+    pub kill_signal: Option<Signal>,
+    /// How many seconds tasks get to exit after receiving the kill signal,
+    /// before they're killed with SIGKILL.
+    #[serde(default)]
+    pub kill_grace: Option<u64>,
+    /// The maximum amount of memory in bytes, which a task may use, e.g. `4096` or `512M`.
+    /// Needs a delegated cgroup.
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub memory_limit: Option<u64>,
+    /// The maximum amount of CPUs, which a task may use. Needs a delegated cgroup.
+    #[serde(default)]
+    pub cpu_limit: Option<f64>,
+    /// The niceness of tasks, from -20 (highest priority) to 19 (lowest priority).
+    #[serde(default)]
+    pub nice: Option<i32>,
+    /// The I/O scheduling class and priority of tasks, e.g. `idle` or `best-effort:7`.
+    #[serde(default)]
+    pub ionice: Option<IoPriority>,
+    /// The maximum amount of file descriptors, which the process of a task may open.
+    #[serde(default)]
+    pub max_open_files: Option<u64>,
+    /// The maximum CPU time in seconds, which the process of a task may use.
+    #[serde(default)]
+    pub max_cpu_time: Option<u64>,
+    /// The shell that executes the commands of tasks, e.g. `["zsh", "-c"]`.
+    /// Just like `shell` of the daemon, `-c` is appended to a single program.
+    #[serde(default)]
+    pub shell: Option<Vec<String>>,
+    /// The maximum size of each log of a task in bytes, e.g. `4096` or `512M`.
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub max_log_size: Option<u64>,
+    /// What happens, once a log exceeds its maximum size, e.g. `truncate` or `compress:5`.
+    /// Defaults to `truncate`.
+    #[serde(default)]
+    pub log_rotation: Option<LogRotation>,
+}
+
+/// No new tasks of a group are started, while the system exceeds any of these thresholds. \
+/// Already running tasks aren't affected. This is currently only supported on Linux.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct GroupLimits {
+    /// The maximum load average over the last minute.
+    pub max_load: Option<f64>,
+    /// The minimum amount of free memory in bytes, e.g. `536870912` or `512M`.
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub min_free_memory: Option<u64>,
+}
+
+/// The parent settings struct. \
+/// This contains all other setting structs.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Settings {
+    pub client: Client,
+    pub daemon: Daemon,
+    pub shared: Shared,
+}
+
+impl Settings {
+    /// This function creates a new configuration instance and
+    /// populates it with default values for every option. \
+    /// If a local config file already exists, it is parsed and
+    /// overrules the default option values.
+    /// The default local config is located at "~/.config/pueue.yml".
+    ///
+    /// If `require_config` is `true`, an error will be thrown, if no configuration file can be found.
+    /// This is utilized by the client, since only the daemon is allowed to touch the configuration
+    /// file.
+    pub fn new(require_config: bool, from_file: &Option<PathBuf>) -> Result<Settings> {
+        let mut config = Settings::default_config()?;
+
+        // Load the config from a very specific file path
+        if let Some(path) = from_file {
+            if !path.exists() || !path.is_file() {
+                bail!("Couldn't find config at path {:?}", path);
+            }
+            info!("Using config file at: {:?}", path);
+            let config_file = config::File::with_name(path.to_str().unwrap());
+            config.merge(config_file)?;
+        } else {
+            // Load settings from the default config paths.
+            parse_config(&mut config, require_config)?;
+        }
+
+        // Try to can deserialize the entire configuration
+        Ok(config.try_into()?)
+    }
+
+    pub fn default_config() -> Result<Config> {
+        let mut config = Config::new();
+        let pueue_path = default_pueue_path()?;
+        config.set_default("shared.pueue_directory", pueue_path.clone())?;
+        #[cfg(not(target_os = "windows"))]
+        config.set_default("shared.use_unix_socket", true)?;
+        #[cfg(not(target_os = "windows"))]
+        config.set_default("shared.unix_socket_path", get_unix_socket_path()?)?;
+
+        config.set_default("shared.host", "127.0.0.1")?;
+        config.set_default("shared.port", "6924")?;
+        config.set_default("shared.tls_enabled", true)?;
+        config.set_default(
+            "shared.daemon_key",
+            pueue_path.clone() + "/certs/daemon.key",
+        )?;
+        config.set_default(
+            "shared.daemon_cert",
+            pueue_path.clone() + "/certs/daemon.cert",
+        )?;
+        config.set_default("shared.shared_secret_path", pueue_path + "/shared_secret")?;
+
+        // Client specific config
+        config.set_default("client.read_local_logs", true)?;
+        config.set_default("client.show_expanded_aliases", false)?;
+        config.set_default("client.show_confirmation_questions", false)?;
+        config.set_default("client.dark_mode", false)?;
+        config.set_default("client.max_status_lines", None::<i64>)?;
+
+        // Daemon specific config
+        config.set_default("daemon.default_parallel_tasks", 1)?;
+        config.set_default("daemon.pause_group_on_failure", false)?;
+        config.set_default("daemon.pause_all_on_failure", false)?;
+        config.set_default("daemon.callback", None::<String>)?;
+        config.set_default("daemon.groups", HashMap::<String, i64>::new())?;
+        config.set_default(
+            "daemon.group_defaults",
+            HashMap::<String, HashMap<String, i64>>::new(),
+        )?;
+        config.set_default(
+            "daemon.group_limits",
+            HashMap::<String, HashMap<String, i64>>::new(),
+        )?;
+        config.set_default("daemon.resources", HashMap::<String, i64>::new())?;
+        config.set_default("daemon.cgroup", None::<String>)?;
+        config.set_default("daemon.shell", None::<String>)?;
+        config.set_default("daemon.combined_logs", false)?;
+        config.set_default("daemon.archive_logs", false)?;
+        config.set_default("daemon.archive_max_age", None::<i64>)?;
+        config.set_default("daemon.archive_max_size", None::<i64>)?;
+
+        Ok(config)
+    }
+
+    /// Try to read the config file without any default values.
+    /// This is done by the daemon on startup.
+    /// If the file can be read without any need for defaults, we don't have to persist it
+    /// afterwards.
+    pub fn read(require_config: bool, from_file: &Option<PathBuf>) -> Result<Settings> {
+        let mut config = Config::new();
+
+        // Load the config from a very specific file path
+        if let Some(path) = from_file {
+            if !path.exists() {
+                bail!("Couldn't find config at path {:?}", path);
+            }
+            info!("Using config file at: {:?}", path);
+            let config_file = config::File::with_name(path.to_str().unwrap());
+            config.merge(config_file)?;
+        } else {
+            // Load settings from the default config paths.
+            parse_config(&mut config, require_config)?;
+        }
+
+        // Try to can deserialize the entire configuration
+        Ok(config.try_into()?)
+    }
+
+    /// Save the current configuration as a file to the given path. \
+    /// If no path is given, the default configuration path will be used. \
+    /// The file is then written to the main configuration directory of the respective OS.
+    pub fn save(&self, path: &Option<PathBuf>) -> Result<()> {
+        let config_path = if let Some(path) = path {
+            path.clone()
+        } else {
+            default_config_directory()?.join("pueue.yml")
+        };
+        let config_dir = config_path
+            .parent()
+            .ok_or_else(|| anyhow!("Couldn't resolve config dir"))?;
+
+        // Create the config dir, if it doesn't exist yet
+        if !config_dir.exists() {
+            create_dir_all(config_dir)?;
+        }
+
+        let content = serde_yaml::to_string(self)?;
+        let mut file = File::create(config_path)?;
+        file.write_all(content.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// Get all possible configuration paths and check if there are
+/// configuration files at those locations.
+/// All configs will be merged by importance.
+///
+/// If `require_config` is `true`, an error will be thrown, if no configuration file can be found.
+fn parse_config(settings: &mut Config, require_config: bool) -> Result<()> {
+    let mut config_found = false;
+    info!("Parsing config files");
+    for directory in get_config_directories()?.into_iter() {
+        let path = directory.join("pueue.yml");
+        info!("Checking path: {:?}", &path);
+        if path.exists() && path.is_file() {
+            info!("Found config file at: {:?}", path);
+            config_found = true;
+            let config_file = config::File::with_name(path.to_str().unwrap());
+            settings.merge(config_file)?;
+        }
+    }
+
+    if require_config && !config_found {
+        bail!("Couldn't find a configuration file. Did you start the daemon yet?");
+    }
+
+    Ok(())
+}
+
+/// The default value for the `dark_mode` client settings.
+/// Needed to keep backward compatibility between v0.11 and v0.12
+fn default_dark_mode() -> bool {
+    false
+}
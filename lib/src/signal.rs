@@ -0,0 +1,100 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use serde_derive::{Deserialize, Serialize};
+
+/// The process signals, which can be sent to tasks.
+///
+/// Signals are parsed case-insensitively from their names with or without the `SIG` prefix,
+/// e.g. `TERM`, `sigterm` or `SIGTERM`. Their numbers aren't accepted, since they differ
+/// between platforms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Signal {
+    Hup,
+    Int,
+    Quit,
+    Kill,
+    Usr1,
+    Usr2,
+    Alrm,
+    Term,
+    Cont,
+    Stop,
+    Tstp,
+    Winch,
+}
+
+/// All supported signals.
+const SIGNALS: [Signal; 12] = [
+    Signal::Hup,
+    Signal::Int,
+    Signal::Quit,
+    Signal::Kill,
+    Signal::Usr1,
+    Signal::Usr2,
+    Signal::Alrm,
+    Signal::Term,
+    Signal::Cont,
+    Signal::Stop,
+    Signal::Tstp,
+    Signal::Winch,
+];
+
+impl Signal {
+    /// The full name of the signal, e.g. `SIGTERM`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Signal::Hup => "SIGHUP",
+            Signal::Int => "SIGINT",
+            Signal::Quit => "SIGQUIT",
+            Signal::Kill => "SIGKILL",
+            Signal::Usr1 => "SIGUSR1",
+            Signal::Usr2 => "SIGUSR2",
+            Signal::Alrm => "SIGALRM",
+            Signal::Term => "SIGTERM",
+            Signal::Cont => "SIGCONT",
+            Signal::Stop => "SIGSTOP",
+            Signal::Tstp => "SIGTSTP",
+            Signal::Winch => "SIGWINCH",
+        }
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Signal {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let mut full_name = name.trim().to_uppercase();
+        if !full_name.starts_with("SIG") {
+            full_name.insert_str(0, "SIG");
+        }
+
+        SIGNALS
+            .iter()
+            .find(|signal| signal.name() == full_name)
+            .copied()
+            .ok_or_else(|| format!("Unknown signal \"{}\"", name))
+    }
+}
+
+impl TryFrom<String> for Signal {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Signal::from_str(&name)
+    }
+}
+
+impl From<Signal> for String {
+    fn from(signal: Signal) -> Self {
+        signal.name().to_string()
+    }
+}
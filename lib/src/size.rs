@@ -0,0 +1,105 @@
+use serde::de::{Deserialize, Deserializer, Error};
+use serde_derive::Deserialize;
+
+/// Parse a size such as "4096", "512K", "512M", "4G" or "1T" into bytes.
+/// The suffixes are binary, i.e. "1K" are 1024 bytes. Plain numbers are interpreted as bytes.
+pub fn parse_size(src: &str) -> Result<u64, String> {
+    let src = src.trim();
+    let (number, factor) = match src.char_indices().last() {
+        Some((index, unit)) if unit.is_ascii_alphabetic() => {
+            let exponent = match unit.to_ascii_uppercase() {
+                'K' => 1,
+                'M' => 2,
+                'G' => 3,
+                'T' => 4,
+                _ => return Err(format!("unknown size unit '{}'", unit)),
+            };
+            (&src[..index], 1024u64.pow(exponent))
+        }
+        _ => (src, 1),
+    };
+
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| format!("could not parse '{}' as size", src))?;
+    let bytes = (number * factor as f64).round();
+    if !bytes.is_finite() || bytes < 1.0 || bytes >= u64::MAX as f64 {
+        return Err(String::from("the size must be bigger than 0"));
+    }
+
+    Ok(bytes as u64)
+}
+
+/// Sizes in the config are either a plain amount of bytes or a size such as "512M".
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ConfigSize {
+    Bytes(u64),
+    Text(String),
+}
+
+/// Deserialize an optional size in bytes with [parse_size].
+/// This way, sizes in the config have the same format as the sizes given to the client.
+pub fn deserialize_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // Binary formats, such as the one of messages between the daemon and the client,
+    // don't support untagged enums. Sizes are serialized as plain amounts of bytes anyway.
+    if !deserializer.is_human_readable() {
+        return Option::<u64>::deserialize(deserializer);
+    }
+
+    match Option::<ConfigSize>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(ConfigSize::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(ConfigSize::Text(text)) => parse_size(&text).map(Some).map_err(D::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_derive::Serialize;
+
+    #[derive(Deserialize, Serialize)]
+    struct Limits {
+        #[serde(default, deserialize_with = "deserialize_size")]
+        size: Option<u64>,
+    }
+
+    fn deserialize(yaml: &str) -> Result<Option<u64>, serde_yaml::Error> {
+        serde_yaml::from_str::<Limits>(yaml).map(|limits| limits.size)
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_size("1.5m"), Ok(3 * 512 * 1024));
+        assert_eq!(parse_size("4G"), Ok(4 * 1024 * 1024 * 1024));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("4X").is_err());
+        assert!(parse_size("G").is_err());
+    }
+
+    #[test]
+    /// Sizes in the config are given in bytes or with the same units as in the client.
+    fn test_deserialize_size() {
+        assert_eq!(deserialize("size: 4096").unwrap(), Some(4096));
+        assert_eq!(deserialize("size: 512M").unwrap(), Some(512 * 1024 * 1024));
+        assert_eq!(deserialize("size: ~").unwrap(), None);
+        assert_eq!(deserialize("{}").unwrap(), None);
+        assert!(deserialize("size: 4X").is_err());
+    }
+
+    #[test]
+    /// Sizes are sent as plain bytes between the daemon and the client.
+    fn test_deserialize_binary_size() {
+        let limits = Limits { size: Some(4096) };
+        let bytes = bincode::serialize(&limits).unwrap();
+        let limits: Limits = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(limits.size, Some(4096));
+    }
+}
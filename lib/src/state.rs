@@ -0,0 +1,744 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use anyhow::{bail, Result};
+use chrono::prelude::*;
+use log::{debug, error, info, warn};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::schedule::Schedule;
+use crate::settings::Settings;
+use crate::task::{Task, TaskResult, TaskStatus};
+
+pub type SharedState = Arc<Mutex<State>>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum GroupStatus {
+    Running,
+    Paused,
+}
+
+/// This is the full representation of the current state of the Pueue daemon.
+///
+/// This includes
+/// - The currently used settings.
+/// - The full task list
+/// - The current status of all tasks
+/// - All known groups.
+///
+/// However, the State does NOT include:
+/// - Information about child processes
+/// - Handles to child processes
+///
+/// That information is saved in the daemon's TaskHandler.
+///
+/// Most functions implemented on the state shouldn't be used by third party software.
+/// The daemon is constantly changing and persisting the state. \
+/// Any changes applied to a state and saved to disk, will most likely be overwritten
+/// after a short time.
+///
+///
+/// The daemon uses the state as a piece of shared memory between it's threads.
+/// It's wrapped in a MutexGuard, which allows us to guarantee sequential access to any crucial
+/// information, such as status changes and incoming commands by the client.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct State {
+    max_id: usize,
+    /// The current settings used by the daemon.
+    pub settings: Settings,
+    /// All tasks currently managed by the daemon.
+    pub tasks: BTreeMap<usize, Task>,
+    /// All groups
+    pub groups: BTreeMap<String, GroupStatus>,
+    /// Groups, whose new tasks are currently held back due to their load limits,
+    /// and the reason why.
+    #[serde(default)]
+    pub held_back_groups: BTreeMap<String, String>,
+    #[serde(default)]
+    max_schedule_id: usize,
+    /// All recurring tasks.
+    #[serde(default)]
+    pub schedules: BTreeMap<usize, Schedule>,
+    #[serde(default)]
+    max_array_id: usize,
+    config_path: Option<PathBuf>,
+}
+
+impl State {
+    /// Create a new default state.
+    pub fn new(settings: &Settings, config_path: Option<PathBuf>) -> State {
+        // Create a default group state.
+        let mut groups = BTreeMap::new();
+        for group in settings.daemon.groups.keys() {
+            groups.insert(group.into(), GroupStatus::Running);
+        }
+
+        let mut state = State {
+            max_id: 0,
+            settings: settings.clone(),
+            tasks: BTreeMap::new(),
+            groups,
+            held_back_groups: BTreeMap::new(),
+            max_schedule_id: 0,
+            schedules: BTreeMap::new(),
+            max_array_id: 0,
+            config_path,
+        };
+        state.create_group("default");
+        state
+    }
+
+    /// Add a new task
+    pub fn add_task(&mut self, task: Task) -> usize {
+        let task_id = self.insert_task(task);
+        self.save();
+        task_id
+    }
+
+    /// Add a new task without saving the state.
+    /// Use this to add several tasks at once and save the state afterwards.
+    pub fn insert_task(&mut self, mut task: Task) -> usize {
+        task.id = self.max_id;
+        self.tasks.insert(self.max_id, task);
+        self.max_id += 1;
+        self.max_id - 1
+    }
+
+    /// Add a new recurring task.
+    pub fn add_schedule(&mut self, mut schedule: Schedule) -> usize {
+        schedule.id = self.max_schedule_id;
+        self.schedules.insert(self.max_schedule_id, schedule);
+        self.max_schedule_id += 1;
+        self.save();
+        self.max_schedule_id - 1
+    }
+
+    /// Reserve the id of a new task array.
+    pub fn next_array_id(&mut self) -> usize {
+        self.max_array_id += 1;
+        self.max_array_id - 1
+    }
+
+    /// Get the ids of all tasks that belong to a task array.
+    pub fn array_task_ids(&self, array_id: usize) -> Vec<usize> {
+        self.tasks
+            .iter()
+            .filter(|(_, task)| task.array_id == Some(array_id))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// A small helper to change the status of a specific task.
+    pub fn change_status(&mut self, id: usize, new_status: TaskStatus) {
+        if let Some(ref mut task) = self.tasks.get_mut(&id) {
+            task.status = new_status;
+            self.save();
+        };
+    }
+
+    /// Set the time a specific task should be enqueued at.
+    pub fn set_enqueue_at(&mut self, id: usize, enqueue_at: Option<DateTime<Local>>) {
+        if let Some(ref mut task) = self.tasks.get_mut(&id) {
+            task.enqueue_at = enqueue_at;
+        }
+    }
+
+    /// Get the capacity of a named resource.
+    /// Resources that haven't been configured act as a simple lock with a capacity of 1.
+    pub fn resource_capacity(&self, resource: &str) -> usize {
+        self.settings
+            .daemon
+            .resources
+            .get(resource)
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// Get the amount of each named resource, that's currently held by running or paused tasks.
+    pub fn used_resources(&self) -> BTreeMap<String, usize> {
+        let mut used = BTreeMap::new();
+        for task in self.tasks.values().filter(|task| task.is_running()) {
+            for (resource, amount) in task.resources.iter() {
+                *used.entry(resource.clone()).or_insert(0) += amount;
+            }
+        }
+
+        used
+    }
+
+    /// Get all resources that don't have enough free capacity to start the given task. \
+    /// `used` are the currently used resources, as returned by [State::used_resources].
+    pub fn blocking_resources(&self, task: &Task, used: &BTreeMap<String, usize>) -> Vec<String> {
+        task.resources
+            .iter()
+            .filter(|(resource, amount)| {
+                let used = used.get(*resource).copied().unwrap_or(0);
+                used + *amount > self.resource_capacity(resource)
+            })
+            .map(|(resource, _)| resource.clone())
+            .collect()
+    }
+
+    /// Add a new group to the daemon. \
+    /// This also check if the given group already exists.
+    /// Create a state.group entry and a settings.group entry, if it doesn't.
+    pub fn create_group(&mut self, group: &str) {
+        if !self.settings.daemon.groups.contains_key(group) {
+            self.settings.daemon.groups.insert(group.into(), 1);
+        }
+        if !self.groups.contains_key(group) {
+            self.groups.insert(group.into(), GroupStatus::Running);
+        }
+    }
+
+    /// Remove a group.
+    /// This also iterates through all tasks and sets any tasks' group
+    /// to the `default` group if it matches the deleted group.
+    pub fn remove_group(&mut self, group: &str) -> Result<()> {
+        if group.eq("default") {
+            bail!("You cannot remove the default group.");
+        }
+
+        self.settings.daemon.groups.remove(group);
+        self.settings.daemon.group_defaults.remove(group);
+        self.settings.daemon.group_limits.remove(group);
+        self.held_back_groups.remove(group);
+        self.groups.remove(group);
+
+        // Reset all tasks with removed group to the default.
+        for (_, task) in self.tasks.iter_mut() {
+            if task.group.eq(group) {
+                task.set_default_group();
+            }
+        }
+
+        // The same goes for the templates of all recurring tasks.
+        for (_, schedule) in self.schedules.iter_mut() {
+            if schedule.task.group.eq(group) {
+                schedule.task.set_default_group();
+            }
+        }
+
+        self.save();
+        self.save_settings()
+    }
+
+    /// Set the group status (running/paused) for all groups including the default queue.
+    pub fn set_status_for_all_groups(&mut self, status: GroupStatus) {
+        let keys = self.groups.keys().cloned().collect::<Vec<String>>();
+        for key in keys {
+            self.groups.insert(key, status.clone());
+        }
+        self.save()
+    }
+
+    /// Get all ids of task with a specific state inside a specific group.
+    pub fn task_ids_in_group_with_stati(&self, group: &str, stati: Vec<TaskStatus>) -> Vec<usize> {
+        self.tasks
+            .iter()
+            .filter(|(_, task)| stati.contains(&task.status))
+            .filter(|(_, task)| task.group.eq(group))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Get all ids of task inside a specific group.
+    pub fn task_ids_in_group(&self, group: &str) -> Vec<usize> {
+        self.tasks
+            .iter()
+            .filter(|(_, task)| task.group.eq(group))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// This checks, whether some tasks have one of the specified statuses. \
+    /// The first result is the list of task_ids that match these statuses. \
+    /// The second result is the list of task_ids that don't match these statuses. \
+    ///
+    /// By default, this checks all tasks in the current state. If a list of task_ids is
+    /// provided as the third parameter, only those tasks will be checked.
+    pub fn tasks_in_statuses(
+        &self,
+        statuses: Vec<TaskStatus>,
+        task_ids: Option<Vec<usize>>,
+    ) -> (Vec<usize>, Vec<usize>) {
+        let task_ids = match task_ids {
+            Some(ids) => ids,
+            None => self.tasks.keys().cloned().collect(),
+        };
+
+        let mut matching = Vec::new();
+        let mut mismatching = Vec::new();
+
+        // Filter all task id's that match the provided statuses.
+        for task_id in task_ids.iter() {
+            // Check whether the task exists and save all non-existing task ids.
+            match self.tasks.get(task_id) {
+                None => {
+                    mismatching.push(*task_id);
+                    continue;
+                }
+                Some(task) => {
+                    // Check whether the task status matches the specified statuses.
+                    if statuses.contains(&task.status) {
+                        matching.push(*task_id);
+                    } else {
+                        mismatching.push(*task_id);
+                    }
+                }
+            };
+        }
+
+        (matching, mismatching)
+    }
+
+    /// Check if a task can be deleted. \
+    /// We have to check all dependant tasks, that haven't finished yet.
+    /// This is necessary to prevent deletion of tasks which are specified as a dependency.
+    /// Tasks that are a dependency of a schedule can't be deleted as long as the schedule exists.
+    ///
+    /// `to_delete` A list of task ids, which should also be deleted.
+    ///             This allows to remove dependency tasks as well as their dependants.
+    pub fn is_task_removable(&self, task_id: &usize, to_delete: &[usize]) -> bool {
+        let is_scheduled_dependency = self
+            .schedules
+            .values()
+            .any(|schedule| schedule.task.dependencies.contains(task_id));
+        if is_scheduled_dependency {
+            return false;
+        }
+
+        // Get all task ids of any dependant tasks.
+        let dependants: Vec<usize> = self
+            .tasks
+            .iter()
+            .filter(|(_, task)| {
+                task.dependencies.contains(task_id) && task.status != TaskStatus::Done
+            })
+            .map(|(_, task)| task.id)
+            .collect();
+
+        if dependants.is_empty() {
+            return true;
+        }
+
+        // Check if the dependants are supposed to be deleted as well.
+        let should_delete_dependants = dependants.iter().all(|task_id| to_delete.contains(task_id));
+        if !should_delete_dependants {
+            return false;
+        }
+
+        // Lastly, do a recursive check if there are any dependants on our dependants
+        dependants
+            .iter()
+            .all(|task_id| self.is_task_removable(task_id, to_delete))
+    }
+
+    /// A small helper for handling task failures. \
+    /// Users can specify whether they want to pause the task's group or the
+    /// whole daemon on a failed tasks. This function wraps that logic and decides if anything should be
+    /// paused depending on the current settings.
+    ///
+    /// `group` should be the name of the failed task.
+    pub fn handle_task_failure(&mut self, group: String) {
+        if self.settings.daemon.pause_group_on_failure {
+            self.groups.insert(group, GroupStatus::Paused);
+        } else if self.settings.daemon.pause_all_on_failure {
+            self.set_status_for_all_groups(GroupStatus::Paused);
+        }
+    }
+
+    /// Do a full reset of the state.
+    /// This doesn't reset any processes!
+    ///
+    /// Task ids are reused after a reset. Schedules with dependencies are therefore removed,
+    /// since their dependencies would point to unrelated new tasks.
+    pub fn reset(&mut self) {
+        self.backup();
+        self.max_id = 0;
+        self.max_array_id = 0;
+        self.tasks = BTreeMap::new();
+        self.schedules.retain(|id, schedule| {
+            if schedule.task.dependencies.is_empty() {
+                return true;
+            }
+            warn!(
+                "Removing schedule {}, since its dependencies have been reset",
+                id
+            );
+            false
+        });
+        for schedule in self.schedules.values_mut() {
+            schedule.last_task_id = None;
+        }
+        self.set_status_for_all_groups(GroupStatus::Running);
+    }
+
+    /// A small convenience wrapper for saving the settings to a file.
+    pub fn save_settings(&self) -> Result<()> {
+        self.settings.save(&self.config_path)
+    }
+
+    /// Convenience wrapper around save_to_file.
+    pub fn save(&self) {
+        self.save_to_file(false);
+    }
+
+    /// Save the current current state in a file with a timestamp.
+    /// At the same time remove old state logs from the log directory.
+    /// This function is called, when large changes to the state are applied, e.g. clean/reset.
+    pub fn backup(&self) {
+        self.save_to_file(true);
+        if let Err(error) = self.rotate() {
+            error!("Failed to rotate files: {:?}", error);
+        };
+    }
+
+    /// Save the current state to disk. \
+    /// We do this to restore in case of a crash. \
+    /// If log == true, the file will be saved with a time stamp.
+    ///
+    /// In comparison to the daemon -> client communication, the state is saved
+    /// as JSON for better readability and debug purposes.
+    fn save_to_file(&self, log: bool) {
+        let serialized = serde_json::to_string(&self);
+        if let Err(error) = serialized {
+            error!("Failed to serialize state: {:?}", error);
+            return;
+        }
+
+        let serialized = serialized.unwrap();
+
+        let path = Path::new(&self.settings.shared.pueue_directory);
+        let (temp, real) = if log {
+            let path = path.join("log");
+            let now: DateTime<Utc> = Utc::now();
+            let time = now.format("%Y-%m-%d_%H-%M-%S");
+            (
+                path.join(format!("{}_state.json.partial", time)),
+                path.join(format!("{}_state.json", time)),
+            )
+        } else {
+            (path.join("state.json.partial"), path.join("state.json"))
+        };
+
+        // Write to temporary log file first, to prevent loss due to crashes.
+        if let Err(error) = fs::write(&temp, serialized) {
+            error!(
+                "Failed to write log to directory. File permissions? Error: {:?}",
+                error
+            );
+            return;
+        }
+
+        // Overwrite the original with the temp file, if everything went fine.
+        if let Err(error) = fs::rename(&temp, &real) {
+            error!(
+                "Failed to overwrite old log file. File permissions? Error: {:?}",
+                error
+            );
+            return;
+        }
+
+        if log {
+            debug!("State backup created at: {:?}", real);
+        } else {
+            debug!("State saved at: {:?}", real);
+        }
+    }
+
+    /// Restore the last state from a previous session. \
+    /// The state is stored as json in the log directory.
+    pub fn restore(&mut self) {
+        let path = Path::new(&self.settings.shared.pueue_directory).join("state.json");
+
+        // Ignore if the file doesn't exist. It doesn't have to.
+        if !path.exists() {
+            info!(
+                "Couldn't find state from previous session at location: {:?}",
+                path
+            );
+            return;
+        }
+        info!("Start restoring state");
+
+        // Try to load the file.
+        let data = fs::read_to_string(&path);
+        if let Err(error) = data {
+            error!("Failed to read previous state log: {:?}", error);
+            return;
+        }
+        let data = data.unwrap();
+
+        // Try to deserialize the state file.
+        let deserialized: Result<State, serde_json::error::Error> = serde_json::from_str(&data);
+        if let Err(error) = deserialized {
+            error!("Failed to deserialize previous state log: {:?}", error);
+            return;
+        }
+        let mut state = deserialized.unwrap();
+
+        // Copy group statuses from the previous state.
+        for (group, _) in state.settings.daemon.groups {
+            if let Some(status) = state.groups.get(&group) {
+                self.groups.insert(group.clone(), status.clone());
+            }
+        }
+
+        // Restore all tasks.
+        // While restoring the tasks, check for any invalid/broken stati.
+        for (task_id, task) in state.tasks.iter_mut() {
+            // Handle ungraceful shutdowns while executing tasks.
+            if task.status == TaskStatus::Running || task.status == TaskStatus::Paused {
+                info!(
+                    "Setting task {} with previous status {:?} to new status {:?}",
+                    task.id,
+                    task.status,
+                    TaskResult::Killed
+                );
+                task.status = TaskStatus::Done;
+                task.result = Some(TaskResult::Killed);
+            }
+
+            // Handle crash during editing of the task command.
+            if task.status == TaskStatus::Locked {
+                task.status = TaskStatus::Stashed;
+            }
+
+            // Go trough all tasks and set all groups that are no longer
+            // listed in the configuration file to the default.
+            if !self.settings.daemon.groups.contains_key(&task.group) {
+                task.set_default_group();
+            }
+
+            // If there are any queued tasks, pause the group.
+            // This should prevent any unwanted execution of tasks due to a system crash.
+            if task.status == TaskStatus::Queued {
+                info!(
+                    "Pausing group {} to prevent unwanted execution of previous tasks",
+                    &task.group
+                );
+                self.groups.insert(task.group.clone(), GroupStatus::Paused);
+            }
+
+            self.tasks.insert(*task_id, task.clone());
+        }
+
+        // Restore all recurring tasks.
+        // Schedules that missed their run while the daemon was down fire once right away.
+        for (_, schedule) in state.schedules.iter_mut() {
+            if !self
+                .settings
+                .daemon
+                .groups
+                .contains_key(&schedule.task.group)
+            {
+                schedule.task.set_default_group();
+            }
+        }
+        self.schedules = state.schedules;
+
+        self.max_id = state.max_id;
+        self.max_schedule_id = state.max_schedule_id;
+        self.max_array_id = state.max_array_id;
+    }
+
+    /// Remove old logs that aren't needed any longer.
+    fn rotate(&self) -> Result<()> {
+        let path = Path::new(&self.settings.shared.pueue_directory);
+        let path = path.join("log");
+
+        // Get all log files in the directory with their respective system time.
+        let mut entries: BTreeMap<SystemTime, PathBuf> = BTreeMap::new();
+        let mut directory_list = fs::read_dir(path)?;
+        while let Some(Ok(entry)) = directory_list.next() {
+            let path = entry.path();
+
+            let metadata = entry.metadata()?;
+            let time = metadata.modified()?;
+            entries.insert(time, path);
+        }
+
+        // Remove all files above the threshold.
+        // Old files are removed first (implictly by the BTree order).
+        let mut number_entries = entries.len();
+        let mut iter = entries.iter();
+        while number_entries > 10 {
+            if let Some((_, path)) = iter.next() {
+                fs::remove_file(path)?;
+                number_entries -= 1;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::Value;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    /// The fields of tasks, which have been saved by versions up to 0.12.2.
+    const OLD_TASK_FIELDS: [&str; 14] = [
+        "id",
+        "original_command",
+        "command",
+        "path",
+        "envs",
+        "group",
+        "enqueue_at",
+        "dependencies",
+        "label",
+        "status",
+        "prev_status",
+        "result",
+        "start",
+        "end",
+    ];
+
+    fn get_state(path: &Path) -> State {
+        let mut settings: Settings = Settings::default_config()
+            .expect("Failed to get default config")
+            .try_into()
+            .expect("Failed to get test settings");
+        settings.shared.pueue_directory = path.to_path_buf();
+
+        State::new(&settings, None)
+    }
+
+    #[test]
+    /// Tasks that have been saved by older versions are restored with the defaults of new fields.
+    fn restore_old_task_format() {
+        let dir = tempdir().unwrap();
+        let mut state = get_state(dir.path());
+        let task = Task::new(
+            "ls".into(),
+            "/tmp".into(),
+            HashMap::new(),
+            "default".into(),
+            TaskStatus::Stashed,
+            None,
+            Vec::new(),
+            None,
+        );
+        state.add_task(task);
+
+        // Strip all fields from the saved task, which didn't exist in older versions.
+        let path = dir.path().join("state.json");
+        let mut saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let task = saved["tasks"]["0"].as_object_mut().unwrap();
+        let new_fields: Vec<String> = task
+            .keys()
+            .filter(|field| !OLD_TASK_FIELDS.contains(&field.as_str()))
+            .cloned()
+            .collect();
+        for field in new_fields {
+            task.remove(&field);
+        }
+        fs::write(&path, saved.to_string()).unwrap();
+
+        let mut restored = get_state(dir.path());
+        restored.restore();
+
+        let task = restored.tasks.get(&0).expect("Task hasn't been restored");
+        assert_eq!(task.original_command, "ls");
+        assert_eq!(task.slots, 1);
+        assert_eq!(task.retries, 0);
+        assert!(!task.pty);
+    }
+
+    #[test]
+    /// Schedules, which depend on tasks, don't survive a reset, since task ids are reused.
+    fn reset_removes_dependant_schedules() {
+        let dir = tempdir().unwrap();
+        let mut state = get_state(dir.path());
+        let mut task = Task::new(
+            "ls".into(),
+            "/tmp".into(),
+            HashMap::new(),
+            "default".into(),
+            TaskStatus::Queued,
+            None,
+            Vec::new(),
+            None,
+        );
+        let independent = state.add_schedule(Schedule::new(
+            "* * * * *".into(),
+            task.clone(),
+            Local::now(),
+        ));
+        task.dependencies = vec![0];
+        state.add_schedule(Schedule::new("* * * * *".into(), task, Local::now()));
+        state.schedules.get_mut(&independent).unwrap().last_task_id = Some(3);
+
+        state.reset();
+
+        assert_eq!(
+            state.schedules.keys().collect::<Vec<_>>(),
+            vec![&independent]
+        );
+        assert_eq!(state.schedules[&independent].last_task_id, None);
+    }
+
+    #[test]
+    /// Only running and paused tasks hold their resources.
+    /// Tasks are blocked by all resources, that don't have enough free capacity.
+    fn used_and_blocking_resources() {
+        let dir = tempdir().unwrap();
+        let mut state = get_state(dir.path());
+        state.settings.daemon.resources.insert("gpu".into(), 3);
+
+        for status in [
+            TaskStatus::Running,
+            TaskStatus::Paused,
+            TaskStatus::Queued,
+            TaskStatus::Done,
+        ]
+        .iter()
+        {
+            let mut task = Task::new(
+                "ls".into(),
+                "/tmp".into(),
+                HashMap::new(),
+                "default".into(),
+                status.clone(),
+                None,
+                Vec::new(),
+                None,
+            );
+            task.resources.insert("gpu".into(), 1);
+            task.resources.insert("db".into(), 1);
+            state.insert_task(task);
+        }
+
+        let used = state.used_resources();
+        let expected: BTreeMap<String, usize> = vec![("db".into(), 2), ("gpu".into(), 2)]
+            .into_iter()
+            .collect();
+        assert_eq!(used, expected);
+
+        // The database only has a capacity of 1, while there's one GPU left.
+        let queued = &state.tasks[&2];
+        assert_eq!(
+            state.blocking_resources(queued, &used),
+            vec!["db".to_string()]
+        );
+
+        let mut task = queued.clone();
+        task.resources.remove("db");
+        assert!(state.blocking_resources(&task, &used).is_empty());
+        task.resources.insert("gpu".into(), 2);
+        assert_eq!(
+            state.blocking_resources(&task, &used),
+            vec!["gpu".to_string()]
+        );
+    }
+}
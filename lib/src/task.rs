@@ -0,0 +1,387 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+use strum_macros::Display;
+
+use crate::aliasing::insert_alias;
+use crate::io_priority::IoPriority;
+use crate::log_rotation::LogRotation;
+
+/// This enum represents the status of the internal task handling of Pueue.
+/// They basically represent the internal task life-cycle.
+#[derive(Clone, Debug, Display, PartialEq, Serialize, Deserialize)]
+pub enum TaskStatus {
+    /// The task is queued and waiting for a free slot
+    Queued,
+    /// The task has been manually stashed. It won't be executed until it's manually enqueued
+    Stashed,
+    /// The task is started and running
+    Running,
+    /// A previously running task has been paused
+    Paused,
+    /// Task finished. The actual result of the task is handled by the [TaskResult] enum.
+    Done,
+    /// Used while the command of a task is edited (to prevent starting the task)
+    Locked,
+}
+
+/// This enum represents the exit status of an actually spawned program.
+/// It's only used, once a task finished or failed in some kind of way.
+#[derive(Clone, Debug, Display, PartialEq, Serialize, Deserialize)]
+pub enum TaskResult {
+    /// Task exited with 0
+    Success,
+    /// The task failed in some other kind of way (error code != 0)
+    Failed(i32),
+    /// The task couldn't be spawned. Probably a typo in the command
+    FailedToSpawn(String),
+    /// Task has been actively killed by either the user or the daemon on shutdown
+    Killed,
+    /// The task has been ended by a signal other than SIGKILL, e.g. by a graceful kill.
+    /// Contains the name of the signal.
+    Signaled(String),
+    /// Some kind of IO error. This should barely ever happen. Please check the daemon logs.
+    Errored,
+    /// A dependency of the task failed.
+    DependencyFailed,
+    /// The task has been killed by the daemon, since it exceeded its timeout.
+    TimedOut,
+    /// The task has been killed by the OOM killer, since it exceeded its memory limit.
+    OutOfMemory,
+}
+
+/// The resources used by the process of a finished task and all of its awaited children.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// The CPU time spent in user mode in seconds.
+    pub user_time: f64,
+    /// The CPU time spent in kernel mode in seconds.
+    pub system_time: f64,
+    /// The peak resident set size of the largest process in bytes.
+    pub max_rss: u64,
+    /// How often the file system had to read from disk.
+    pub block_input: u64,
+    /// How often the file system had to write to disk.
+    pub block_output: u64,
+    /// How often a process gave up the CPU voluntarily, e.g. while waiting for I/O.
+    pub voluntary_context_switches: u64,
+    /// How often a process has been preempted by the scheduler.
+    pub involuntary_context_switches: u64,
+}
+
+/// The current resource usage of a running task's whole process tree.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CurrentUsage {
+    /// The CPU usage since the previous sample in percent of a single CPU.
+    pub cpu: f64,
+    /// The resident set size of all processes in bytes.
+    pub memory: u64,
+}
+
+/// The condition a dependency has to meet, before its dependant task can be started.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DependencyCondition {
+    /// The dependency has to finish successfully.
+    Success,
+    /// The dependency has to fail in some way.
+    Failure,
+    /// The dependency only has to finish, regardless of its result.
+    Any,
+}
+
+impl DependencyCondition {
+    /// Check whether the given dependency meets this condition. \
+    /// Returns `None`, as long as the dependency hasn't finished yet.
+    pub fn is_met_by(&self, dependency: &Task) -> Option<bool> {
+        if !dependency.is_done() {
+            return None;
+        }
+
+        match self {
+            DependencyCondition::Success => Some(!dependency.failed()),
+            DependencyCondition::Failure => Some(dependency.failed()),
+            DependencyCondition::Any => Some(true),
+        }
+    }
+}
+
+/// Tasks from states of older versions occupy a single slot.
+fn default_slots() -> usize {
+    1
+}
+
+/// Representation of a task.
+/// start will be set the second the task starts processing.
+/// `result`, `output` and `end` won't be initialized, until the task has finished.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Task {
+    pub id: usize,
+    pub original_command: String,
+    pub command: String,
+    pub path: String,
+    pub envs: HashMap<String, String>,
+    pub group: String,
+    pub enqueue_at: Option<DateTime<Local>>,
+    pub dependencies: Vec<usize>,
+    /// The conditions of all dependencies, which don't need to finish successfully.
+    /// Dependencies without an entry have to succeed.
+    #[serde(default)]
+    pub dependency_conditions: BTreeMap<usize, DependencyCondition>,
+    pub label: Option<String>,
+    /// The maximum runtime of the task in seconds.
+    /// The task will be killed by the daemon, if it runs any longer.
+    pub timeout: Option<u64>,
+    /// How often the task will be enqueued again, if it fails.
+    #[serde(default)]
+    pub retries: usize,
+    /// The delay in seconds before a failed task is enqueued again.
+    /// This delay is doubled with every further attempt.
+    pub retry_delay: Option<u64>,
+    /// The results of all previous failed attempts of this task.
+    #[serde(default)]
+    pub attempts: Vec<TaskResult>,
+    /// Queued tasks with a higher priority are started first.
+    /// Tasks with the same priority are started in the order of their ids.
+    #[serde(default)]
+    pub priority: i32,
+    /// Named resources and the amount of each resource, that's needed while the task runs.
+    /// The task won't be started, until enough of each resource is available.
+    #[serde(default)]
+    pub resources: BTreeMap<String, usize>,
+    /// How many of its group's parallel slots this task occupies while running.
+    #[serde(default = "default_slots")]
+    pub slots: usize,
+    /// The id of the task array this task belongs to, if it has been added via `--each`.
+    pub array_id: Option<usize>,
+    /// The maximum amount of memory in bytes, which the task may use.
+    pub memory_limit: Option<u64>,
+    /// The maximum amount of CPUs, which the task may use. Fractions of CPUs are allowed.
+    pub cpu_limit: Option<f64>,
+    /// The niceness of the task's process, from -20 (highest priority) to 19 (lowest priority).
+    pub nice: Option<i32>,
+    /// The I/O scheduling class and priority of the task's process.
+    pub ionice: Option<IoPriority>,
+    /// The maximum amount of file descriptors, which the task's process may open.
+    pub max_open_files: Option<u64>,
+    /// The maximum CPU time in seconds, which the task's process may use.
+    pub max_cpu_time: Option<u64>,
+    /// The shell that executes the command, e.g. `["bash", "-c"]`.
+    /// If not set, the shell of the task's group or the daemon is used.
+    pub shell: Option<Vec<String>>,
+    /// Execute the command directly instead of passing it to a shell.
+    /// The command is split into its arguments with the quoting rules of a posix shell.
+    #[serde(default)]
+    pub no_shell: bool,
+    /// Run the task on a pseudo terminal, which is owned by the daemon.
+    /// Its stdout and stderr are both written to the stdout log.
+    #[serde(default)]
+    pub pty: bool,
+    /// The maximum size of each log of the task in bytes.
+    pub max_log_size: Option<u64>,
+    /// What happens, once a log exceeds its maximum size.
+    pub log_rotation: Option<LogRotation>,
+    /// The resources used by the task's process. Only available once the task finished.
+    pub resource_usage: Option<ResourceUsage>,
+    /// The current resource usage of the task's processes, which is sampled while it runs.
+    pub current_usage: Option<CurrentUsage>,
+    pub status: TaskStatus,
+    /// This field is only used when editing the path/command of a task.
+    /// It's necessary, since we enter the `Locked` state during editing.
+    /// However, we have to go back to the previous state after we finished editing.
+    pub prev_status: TaskStatus,
+    pub result: Option<TaskResult>,
+    pub start: Option<DateTime<Local>>,
+    pub end: Option<DateTime<Local>>,
+}
+
+impl Task {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        original_command: String,
+        path: String,
+        envs: HashMap<String, String>,
+        group: String,
+        starting_status: TaskStatus,
+        enqueue_at: Option<DateTime<Local>>,
+        dependencies: Vec<usize>,
+        label: Option<String>,
+    ) -> Task {
+        let command = insert_alias(original_command.clone());
+
+        Task {
+            id: 0,
+            original_command,
+            command,
+            path,
+            envs,
+            group,
+            enqueue_at,
+            dependencies,
+            dependency_conditions: BTreeMap::new(),
+            label,
+            timeout: None,
+            retries: 0,
+            retry_delay: None,
+            attempts: Vec::new(),
+            priority: 0,
+            resources: BTreeMap::new(),
+            slots: 1,
+            array_id: None,
+            memory_limit: None,
+            cpu_limit: None,
+            nice: None,
+            ionice: None,
+            max_open_files: None,
+            max_cpu_time: None,
+            shell: None,
+            no_shell: false,
+            pty: false,
+            max_log_size: None,
+            log_rotation: None,
+            resource_usage: None,
+            current_usage: None,
+            status: starting_status.clone(),
+            prev_status: starting_status,
+            result: None,
+            start: None,
+            end: None,
+        }
+    }
+
+    /// A convenience function used to duplicate a task.
+    pub fn from_task(task: &Task) -> Task {
+        Task {
+            id: 0,
+            original_command: task.original_command.clone(),
+            command: task.command.clone(),
+            path: task.path.clone(),
+            envs: task.envs.clone(),
+            group: "default".to_string(),
+            enqueue_at: None,
+            dependencies: Vec::new(),
+            dependency_conditions: BTreeMap::new(),
+            label: task.label.clone(),
+            timeout: task.timeout,
+            retries: task.retries,
+            retry_delay: task.retry_delay,
+            attempts: Vec::new(),
+            priority: task.priority,
+            resources: task.resources.clone(),
+            slots: task.slots,
+            array_id: task.array_id,
+            memory_limit: task.memory_limit,
+            cpu_limit: task.cpu_limit,
+            nice: task.nice,
+            ionice: task.ionice,
+            max_open_files: task.max_open_files,
+            max_cpu_time: task.max_cpu_time,
+            shell: task.shell.clone(),
+            no_shell: task.no_shell,
+            pty: task.pty,
+            max_log_size: task.max_log_size,
+            log_rotation: task.log_rotation,
+            resource_usage: None,
+            current_usage: None,
+            status: TaskStatus::Queued,
+            prev_status: TaskStatus::Queued,
+            result: None,
+            start: None,
+            end: None,
+        }
+    }
+
+    /// Whether the task is having a running process managed by the TaskHandler
+    pub fn is_running(&self) -> bool {
+        self.status == TaskStatus::Running || self.status == TaskStatus::Paused
+    }
+
+    /// Whether the task's process finished.
+    pub fn is_done(&self) -> bool {
+        self.status == TaskStatus::Done
+    }
+
+    /// Check if the task errored. \
+    /// It either didn't run yet or finished successfully.
+    pub fn failed(&self) -> bool {
+        !matches!(self.result, None | Some(TaskResult::Success))
+    }
+
+    /// Get the condition the given dependency of this task has to meet.
+    pub fn dependency_condition(&self, dependency_id: usize) -> DependencyCondition {
+        self.dependency_conditions
+            .get(&dependency_id)
+            .copied()
+            .unwrap_or(DependencyCondition::Success)
+    }
+
+    pub fn is_queued(&self) -> bool {
+        self.status == TaskStatus::Queued || self.status == TaskStatus::Stashed
+    }
+
+    /// Small convenience function to set the task's group to the default group.
+    pub fn set_default_group(&mut self) {
+        self.group = String::from("default");
+    }
+
+    pub fn is_in_default_group(&self) -> bool {
+        self.group.eq("default")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_dependency(result: Option<TaskResult>) -> Task {
+        let status = if result.is_some() {
+            TaskStatus::Done
+        } else {
+            TaskStatus::Running
+        };
+        let mut task = Task::new(
+            "ls".into(),
+            "/tmp".into(),
+            HashMap::new(),
+            "default".into(),
+            status,
+            None,
+            Vec::new(),
+            None,
+        );
+        task.result = result;
+        task
+    }
+
+    #[test]
+    /// Conditions are only decided, once the dependency finished.
+    fn test_dependency_conditions() {
+        let running = get_dependency(None);
+        let succeeded = get_dependency(Some(TaskResult::Success));
+        let failed = get_dependency(Some(TaskResult::Failed(1)));
+        let killed = get_dependency(Some(TaskResult::Killed));
+
+        let conditions = [
+            DependencyCondition::Success,
+            DependencyCondition::Failure,
+            DependencyCondition::Any,
+        ];
+        for condition in conditions.iter() {
+            assert_eq!(condition.is_met_by(&running), None);
+        }
+
+        assert_eq!(
+            DependencyCondition::Success.is_met_by(&succeeded),
+            Some(true)
+        );
+        assert_eq!(DependencyCondition::Success.is_met_by(&failed), Some(false));
+        assert_eq!(
+            DependencyCondition::Failure.is_met_by(&succeeded),
+            Some(false)
+        );
+        assert_eq!(DependencyCondition::Failure.is_met_by(&failed), Some(true));
+        assert_eq!(DependencyCondition::Failure.is_met_by(&killed), Some(true));
+        assert_eq!(DependencyCondition::Any.is_met_by(&succeeded), Some(true));
+        assert_eq!(DependencyCondition::Any.is_met_by(&killed), Some(true));
+    }
+}